@@ -66,7 +66,7 @@ namespace Db.Api.Controllers
         {
             using var remove = _store.BeginDelete();
 
-            remove.Remove(new Key(key));
+            if (!remove.Remove(new Key(key))) return NotFound();
 
             return Ok();
         }
@@ -0,0 +1,27 @@
+using Db.Api.Mvc;
+using Db.Api.Storage;
+using Microsoft.AspNetCore.Mvc;
+
+namespace Db.Api.Controllers
+{
+    [Route("metrics")]
+    [ApiController]
+    public class MetricsController : CoreRtControllerBase
+    {
+        // The content type of the Prometheus text format
+        private const string PrometheusContentType = "text/plain; version=0.0.4";
+
+        private readonly DataStore _store;
+
+        public MetricsController(DataStore store)
+        {
+            _store = store;
+        }
+
+        [HttpGet]
+        public ActionResult Get()
+        {
+            return Content(_store.GetPrometheusMetrics(), PrometheusContentType);
+        }
+    }
+}
@@ -8,6 +8,7 @@ using Microsoft.Extensions.Configuration;
 using Microsoft.Extensions.DependencyInjection;
 using Serilog;
 using Serilog.Context;
+using Serilog.Extensions.Logging;
 
 namespace Db.Api
 {
@@ -35,6 +36,9 @@ namespace Db.Api
                     .AllowAnyMethod()
                     .AllowAnyOrigin()));
 
+            // Send log events from the native library to Serilog, before the store is opened
+            StorageLogging.Use(new SerilogLoggerFactory(Log.Logger));
+
             services.AddSingleton(new DataStore(MemoryPool<byte>.Shared, Store.Open(DataPath)));
         }
 
@@ -18,9 +18,9 @@ namespace Db.Api.Storage
             // The memory pool is borrowed so we don't dispose it
         }
 
-        public void Remove(Key key)
+        public bool Remove(Key key)
         {
-            _deleter.Remove(key);
+            return _deleter.Remove(key);
         }
     }
 }
\ No newline at end of file
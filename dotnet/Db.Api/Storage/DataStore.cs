@@ -35,5 +35,10 @@ namespace Db.Api.Storage
         {
             return new DataDeleter(_store.BeginDelete());
         }
+
+        public string GetPrometheusMetrics()
+        {
+            return _store.GetPrometheusMetrics();
+        }
     }
 }
\ No newline at end of file
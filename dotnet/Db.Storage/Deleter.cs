@@ -1,5 +1,6 @@
 using System;
 using System.Runtime.CompilerServices;
+using System.Threading.Tasks;
 using Db.Storage.Native;
 
 namespace Db.Storage
@@ -18,14 +19,85 @@ namespace Db.Storage
             _handle.Dispose();
         }
 
-        public void Remove(Key key)
+        public Task CompleteAsync()
+        {
+            var (task, state) = Completion.Begin();
+
+            try
+            {
+                // The native deleter is only released if the call succeeds
+                // Otherwise it's still owned by the handle and the callback won't be invoked
+                Bindings.db_delete_end_async(_handle.DangerousGetHandle(), Completion.Callback, state);
+            }
+            catch
+            {
+                Completion.Cancel(state);
+                throw;
+            }
+
+            _handle.Take();
+
+            return task;
+        }
+
+        // Remove a key, returning whether it was present
+        public bool Remove(Key key)
         {
             unsafe
             {
                 var rawKey = key.Value;
                 var keyPtr = Unsafe.AsPointer(ref rawKey);
 
-                Bindings.db_delete_remove(_handle, (IntPtr) keyPtr);
+                var result = Bindings.db_delete_remove(_handle, (IntPtr) keyPtr);
+
+                return !result.IsDone();
+            }
+        }
+
+        // Remove a key, reading its previous value into the buffer
+        // The result is done if the key wasn't present
+        // If the buffer is too small the key has already been removed, and its value is only kept
+        // for a retry with the same key straight after, any other call on the deleter discards it
+        // If the value can't be read the key is still removed, and the thrown `StorageException`
+        // has a result where `IsRemovedUnreadable` is true
+        public ReadResult TryRemoveReturning(Key key, Span<byte> buffer)
+        {
+            unsafe
+            {
+                var rawKey = key.Value;
+                var keyPtr = Unsafe.AsPointer(ref rawKey);
+
+                fixed (byte* bufferPtr = buffer)
+                {
+                    var result = Bindings.db_delete_remove_returning(
+                        _handle,
+                        (IntPtr) keyPtr,
+                        (IntPtr) bufferPtr,
+                        (UIntPtr) buffer.Length,
+                        out var actualValueLength);
+
+                    if (result.IsBufferTooSmall()) return ReadResult.BufferTooSmall((int) actualValueLength);
+
+                    if (result.IsDone()) return ReadResult.Done();
+
+                    return ReadResult.Data(key, buffer, ..(int) actualValueLength);
+                }
+            }
+        }
+
+        // Remove every key from start up to, but not including, end
+        public ulong RemoveRange(Key start, Key end)
+        {
+            unsafe
+            {
+                var rawStart = start.Value;
+                var startPtr = Unsafe.AsPointer(ref rawStart);
+                var rawEnd = end.Value;
+                var endPtr = Unsafe.AsPointer(ref rawEnd);
+
+                Bindings.db_delete_range(_handle, (IntPtr) startPtr, (IntPtr) endPtr, out var removed);
+
+                return (ulong) removed;
             }
         }
     }
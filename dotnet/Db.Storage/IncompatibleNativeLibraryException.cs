@@ -0,0 +1,20 @@
+using System;
+using Db.Storage.Native;
+
+namespace Db.Storage
+{
+    public class IncompatibleNativeLibraryException : Exception
+    {
+        internal IncompatibleNativeLibraryException(uint major, uint minor)
+            : base($"the native library implements version {major}.{minor} of its interface, but version {Bindings.DB_ABI_MAJOR}.{Bindings.DB_ABI_MINOR} is required")
+        {
+            Major = major;
+            Minor = minor;
+        }
+
+        // The version of the interface implemented by the native library
+        // Libraries that don't report a version have the version 0.0
+        public uint Major { get; }
+        public uint Minor { get; }
+    }
+}
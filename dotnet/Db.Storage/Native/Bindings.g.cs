@@ -0,0 +1,480 @@
+// <auto-generated>
+// Generated by dbc-bindgen from `native/c/src`. Don't edit this file by hand.
+// </auto-generated>
+
+using System;
+using System.Runtime.InteropServices;
+
+namespace Db.Storage.Native
+{
+    static partial class Bindings
+    {
+        public const uint DB_ABI_MAJOR = 1;
+        public const uint DB_ABI_MINOR = 3;
+        public const ulong DB_CAPABILITY_CHECKSUMS = 1;
+        public const ulong DB_CAPABILITY_COMPRESSION = 2;
+        public const ulong DB_CAPABILITY_ENCRYPTION = 4;
+        public const ulong DB_CAPABILITY_INDEXES = 8;
+        public const ulong DB_CAPABILITY_FILTERS = 16;
+        public const ulong DB_CAPABILITY_VALIDATION = 32;
+        public const ulong DB_CAPABILITY_ASYNC_COMPLETION = 64;
+        public const ulong DB_CAPABILITY_DURABILITY = 128;
+        public const ulong DB_CAPABILITY_RANGE_DELETES = 256;
+        public const ulong DB_CAPABILITY_ERROR_DETAILS = 512;
+        public const ulong DB_CAPABILITY_LOGGING = 1024;
+        public const ulong DB_CAPABILITY_TRACING = 2048;
+        public const ulong DB_CAPABILITY_METRICS = 4096;
+        public const ulong DB_CAPABILITY_DEBUG = 8192;
+        public const ulong DB_CAPABILITY_CHECKED_HANDLES = 16384;
+
+        [DllImport(NativeLibrary, EntryPoint = "db_abi_version", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_abi_version(out uint major, out uint minor);
+
+        public static DbResult db_abi_version(out uint major, out uint minor, bool check = true)
+        {
+            return MaybeCheck(_db_abi_version(out major, out minor), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_capabilities", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_capabilities(out ulong flags);
+
+        public static DbResult db_capabilities(out ulong flags, bool check = true)
+        {
+            return MaybeCheck(_db_capabilities(out flags), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_last_result", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_last_result(
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen,
+            out DbResult result);
+
+        public static DbResult db_last_result(
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen,
+            out DbResult result,
+            bool check = true)
+        {
+            return MaybeCheck(_db_last_result(messageBuf, messageBufLen, out actualMessageLen, out result), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_last_result_cause", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_last_result_cause(
+            UIntPtr index,
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen);
+
+        public static DbResult db_last_result_cause(
+            UIntPtr index,
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_last_result_cause(index, messageBuf, messageBufLen, out actualMessageLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_last_result_backtrace", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_last_result_backtrace(
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen);
+
+        public static DbResult db_last_result_backtrace(
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_last_result_backtrace(messageBuf, messageBufLen, out actualMessageLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_result_details", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_result_details(
+            uint id,
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen);
+
+        public static DbResult db_result_details(
+            uint id,
+            IntPtr messageBuf,
+            UIntPtr messageBufLen,
+            out UIntPtr actualMessageLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_result_details(id, messageBuf, messageBufLen, out actualMessageLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_set_error_retention", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_set_error_retention(UIntPtr retention);
+
+        public static DbResult db_set_error_retention(UIntPtr retention, bool check = true)
+        {
+            return MaybeCheck(_db_set_error_retention(retention), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_trace_enable", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_trace_enable(UIntPtr capacity);
+
+        public static DbResult db_trace_enable(UIntPtr capacity, bool check = true)
+        {
+            return MaybeCheck(_db_trace_enable(capacity), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_trace_drain", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_trace_drain(
+            IntPtr spans,
+            UIntPtr spansLen,
+            out UIntPtr drained,
+            out ulong dropped);
+
+        public static DbResult db_trace_drain(
+            IntPtr spans,
+            UIntPtr spansLen,
+            out UIntPtr drained,
+            out ulong dropped,
+            bool check = true)
+        {
+            return MaybeCheck(_db_trace_drain(spans, spansLen, out drained, out dropped), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_last_result_fields", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_last_result_fields(ref DbErrorFields fields);
+
+        public static DbResult db_last_result_fields(ref DbErrorFields fields, bool check = true)
+        {
+            return MaybeCheck(_db_last_result_fields(ref fields), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_set_log_callback", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_set_log_callback(DbLogCallback callback, uint maxLevel);
+
+        public static DbResult db_set_log_callback(DbLogCallback callback, uint maxLevel, bool check = true)
+        {
+            return MaybeCheck(_db_set_log_callback(callback, maxLevel), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_store_open", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_store_open(IntPtr path, UIntPtr pathLen, out StoreHandle store);
+
+        public static DbResult db_store_open(IntPtr path, UIntPtr pathLen, out StoreHandle store, bool check = true)
+        {
+            return MaybeCheck(_db_store_open(path, pathLen, out store), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_store_open_with_options", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_store_open_with_options(
+            IntPtr path,
+            UIntPtr pathLen,
+            in DbStoreOptions options,
+            out StoreHandle store);
+
+        public static DbResult db_store_open_with_options(
+            IntPtr path,
+            UIntPtr pathLen,
+            in DbStoreOptions options,
+            out StoreHandle store,
+            bool check = true)
+        {
+            return MaybeCheck(_db_store_open_with_options(path, pathLen, in options, out store), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_store_close", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_store_close(IntPtr store);
+
+        public static DbResult db_store_close(IntPtr store, bool check = true)
+        {
+            return MaybeCheck(_db_store_close(store), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_store_metrics", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_store_metrics(StoreHandle store, ref DbStoreMetrics metrics);
+
+        public static DbResult db_store_metrics(StoreHandle store, ref DbStoreMetrics metrics, bool check = true)
+        {
+            return MaybeCheck(_db_store_metrics(store, ref metrics), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_store_metrics_prometheus", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_store_metrics_prometheus(
+            StoreHandle store,
+            IntPtr textBuf,
+            UIntPtr textBufLen,
+            out UIntPtr actualTextLen);
+
+        public static DbResult db_store_metrics_prometheus(
+            StoreHandle store,
+            IntPtr textBuf,
+            UIntPtr textBufLen,
+            out UIntPtr actualTextLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_store_metrics_prometheus(store, textBuf, textBufLen, out actualTextLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_store_reencrypt", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_store_reencrypt(
+            StoreHandle store,
+            out UIntPtr reencrypted,
+            out UIntPtr skipped);
+
+        public static DbResult db_store_reencrypt(
+            StoreHandle store,
+            out UIntPtr reencrypted,
+            out UIntPtr skipped,
+            bool check = true)
+        {
+            return MaybeCheck(_db_store_reencrypt(store, out reencrypted, out skipped), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_read_begin", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_read_begin(StoreHandle store, out ReaderHandle reader);
+
+        public static DbResult db_read_begin(StoreHandle store, out ReaderHandle reader, bool check = true)
+        {
+            return MaybeCheck(_db_read_begin(store, out reader), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_read_begin_with_filter", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_read_begin_with_filter(
+            StoreHandle store,
+            IntPtr filter,
+            UIntPtr filterLen,
+            out ReaderHandle reader);
+
+        public static DbResult db_read_begin_with_filter(
+            StoreHandle store,
+            IntPtr filter,
+            UIntPtr filterLen,
+            out ReaderHandle reader,
+            bool check = true)
+        {
+            return MaybeCheck(_db_read_begin_with_filter(store, filter, filterLen, out reader), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_index_query", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_index_query(
+            StoreHandle store,
+            IntPtr index,
+            UIntPtr indexLen,
+            IntPtr value,
+            UIntPtr valueLen,
+            out ReaderHandle reader);
+
+        public static DbResult db_index_query(
+            StoreHandle store,
+            IntPtr index,
+            UIntPtr indexLen,
+            IntPtr value,
+            UIntPtr valueLen,
+            out ReaderHandle reader,
+            bool check = true)
+        {
+            return MaybeCheck(_db_index_query(store, index, indexLen, value, valueLen, out reader), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_read_next", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_read_next(
+            ReaderHandle reader,
+            out DbKey key,
+            IntPtr valueBuf,
+            UIntPtr valueBufLen,
+            out UIntPtr actualValueLen);
+
+        public static DbResult db_read_next(
+            ReaderHandle reader,
+            out DbKey key,
+            IntPtr valueBuf,
+            UIntPtr valueBufLen,
+            out UIntPtr actualValueLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_read_next(reader, out key, valueBuf, valueBufLen, out actualValueLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_read_end", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_read_end(IntPtr reader);
+
+        public static DbResult db_read_end(IntPtr reader, bool check = true)
+        {
+            return MaybeCheck(_db_read_end(reader), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_write_begin", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_write_begin(StoreHandle store, out WriterHandle writer);
+
+        public static DbResult db_write_begin(StoreHandle store, out WriterHandle writer, bool check = true)
+        {
+            return MaybeCheck(_db_write_begin(store, out writer), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_write_begin_with_options", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_write_begin_with_options(
+            StoreHandle store,
+            in DbWriteOptions options,
+            out WriterHandle writer);
+
+        public static DbResult db_write_begin_with_options(
+            StoreHandle store,
+            in DbWriteOptions options,
+            out WriterHandle writer,
+            bool check = true)
+        {
+            return MaybeCheck(_db_write_begin_with_options(store, in options, out writer), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_write_set", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_write_set(WriterHandle writer, IntPtr key, IntPtr value, UIntPtr valueLen);
+
+        public static DbResult db_write_set(
+            WriterHandle writer,
+            IntPtr key,
+            IntPtr value,
+            UIntPtr valueLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_write_set(writer, key, value, valueLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_write_end", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_write_end(IntPtr writer);
+
+        public static DbResult db_write_end(IntPtr writer, bool check = true)
+        {
+            return MaybeCheck(_db_write_end(writer), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_write_end_async", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_write_end_async(IntPtr writer, DbCallback callback, IntPtr state);
+
+        public static DbResult db_write_end_async(IntPtr writer, DbCallback callback, IntPtr state, bool check = true)
+        {
+            return MaybeCheck(_db_write_end_async(writer, callback, state), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_delete_begin", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_delete_begin(StoreHandle store, out DeleterHandle deleter);
+
+        public static DbResult db_delete_begin(StoreHandle store, out DeleterHandle deleter, bool check = true)
+        {
+            return MaybeCheck(_db_delete_begin(store, out deleter), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_delete_remove", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_delete_remove(DeleterHandle deleter, IntPtr key);
+
+        public static DbResult db_delete_remove(DeleterHandle deleter, IntPtr key, bool check = true)
+        {
+            return MaybeCheck(_db_delete_remove(deleter, key), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_delete_remove_returning", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_delete_remove_returning(
+            DeleterHandle deleter,
+            IntPtr key,
+            IntPtr valueBuf,
+            UIntPtr valueBufLen,
+            out UIntPtr actualValueLen);
+
+        public static DbResult db_delete_remove_returning(
+            DeleterHandle deleter,
+            IntPtr key,
+            IntPtr valueBuf,
+            UIntPtr valueBufLen,
+            out UIntPtr actualValueLen,
+            bool check = true)
+        {
+            return MaybeCheck(_db_delete_remove_returning(deleter, key, valueBuf, valueBufLen, out actualValueLen), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_delete_range", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_delete_range(
+            DeleterHandle deleter,
+            IntPtr start,
+            IntPtr end,
+            out UIntPtr removed);
+
+        public static DbResult db_delete_range(
+            DeleterHandle deleter,
+            IntPtr start,
+            IntPtr end,
+            out UIntPtr removed,
+            bool check = true)
+        {
+            return MaybeCheck(_db_delete_range(deleter, start, end, out removed), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_delete_end", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_delete_end(IntPtr deleter);
+
+        public static DbResult db_delete_end(IntPtr deleter, bool check = true)
+        {
+            return MaybeCheck(_db_delete_end(deleter), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_delete_end_async", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_delete_end_async(IntPtr deleter, DbCallback callback, IntPtr state);
+
+        public static DbResult db_delete_end_async(IntPtr deleter, DbCallback callback, IntPtr state, bool check = true)
+        {
+            return MaybeCheck(_db_delete_end_async(deleter, callback, state), check);
+        }
+
+#if DEBUG
+        [DllImport(NativeLibrary, EntryPoint = "db_test_error", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_test_error();
+
+        public static DbResult db_test_error(bool check = true)
+        {
+            return MaybeCheck(_db_test_error(), check);
+        }
+
+        [DllImport(NativeLibrary, EntryPoint = "db_test_ok", ExactSpelling = true,
+            CallingConvention = CallingConvention.Cdecl)]
+        private static extern DbResult _db_test_ok();
+
+        public static DbResult db_test_ok(bool check = true)
+        {
+            return MaybeCheck(_db_test_ok(), check);
+        }
+#endif
+    }
+}
@@ -0,0 +1,46 @@
+using System;
+using System.Runtime.InteropServices;
+using System.Threading.Tasks;
+
+namespace Db.Storage.Native
+{
+    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+    delegate void DbCallback(DbResult result, IntPtr state);
+
+    static class Completion
+    {
+        // Keep the delegate alive so it can be called after native calls return
+        public static readonly DbCallback Callback = OnComplete;
+
+        public static (Task, IntPtr) Begin()
+        {
+            var completion = new TaskCompletionSource<object>(TaskCreationOptions.RunContinuationsAsynchronously);
+            var state = GCHandle.Alloc(completion);
+
+            return (completion.Task, GCHandle.ToIntPtr(state));
+        }
+
+        public static void Cancel(IntPtr state)
+        {
+            GCHandle.FromIntPtr(state).Free();
+        }
+
+        private static void OnComplete(DbResult result, IntPtr state)
+        {
+            var handle = GCHandle.FromIntPtr(state);
+            var completion = (TaskCompletionSource<object>) handle.Target;
+            handle.Free();
+
+            try
+            {
+                // The callback runs on the thread that set the last result
+                result.Check();
+                completion.SetResult(null);
+            }
+            catch (Exception e)
+            {
+                completion.SetException(e);
+            }
+        }
+    }
+}
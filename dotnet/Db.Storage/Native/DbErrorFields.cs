@@ -0,0 +1,15 @@
+using System.Runtime.InteropServices;
+
+namespace Db.Storage.Native
+{
+    [StructLayout(LayoutKind.Sequential)]
+    struct DbErrorFields
+    {
+        public uint Size;
+        public DbResult Result;
+        [MarshalAs(UnmanagedType.U1)] public bool HasKey;
+        public DbKey Key;
+        [MarshalAs(UnmanagedType.U1)] public bool HasOsError;
+        public int OsError;
+    }
+}
@@ -6,15 +6,33 @@ namespace Db.Storage.Native
     [StructLayout(LayoutKind.Sequential)]
     public struct DbResult
     {
+        // Kinds are stable and must match the values in the native library
         private enum Kind : uint
         {
-            Ok,
+            Ok = 0,
 
-            Done,
-            BufferTooSmall,
+            Done = 1,
+            BufferTooSmall = 2,
 
-            ArgumentNull,
-            InternalError
+            ArgumentNull = 3,
+            InternalError = 4,
+
+            Corrupted = 5,
+            DecryptionFailed = 6,
+            ValidationFailed = 7,
+
+            Io = 8,
+            InvalidUtf8 = 9,
+            KeyTooLong = 10,
+            InvalidFilter = 11,
+            InvalidIndex = 12,
+            UnknownIndex = 13,
+            InvalidSchema = 14,
+            InvalidArgument = 15,
+
+            InvalidHandle = 16,
+
+            RemovedUnreadable = 17
         }
 
         private readonly Kind _result;
@@ -25,6 +43,18 @@ namespace Db.Storage.Native
             return LastResult.GetLastResult();
         }
 
+        // Get the message for this result if it's still retained
+        public string GetDetails()
+        {
+            return LastResult.GetRetainedError(_id);
+        }
+
+        // Set the number of errors retained for `GetDetails`
+        public static void SetErrorRetention(int retention)
+        {
+            LastResult.SetErrorRetention(retention);
+        }
+
         internal DbResult Check()
         {
             if (IsSuccess() || IsBufferTooSmall()) return this;
@@ -35,9 +65,24 @@ namespace Db.Storage.Native
             // We need to use both because successful results won't
             // bother setting the id (it avoids some synchronization)
             if (lastResult._result == _result && lastResult._id == _id)
-                throw new Exception($"Native storage failed ({_result}), {msg?.TrimEnd()}");
+            {
+                var (_, causes, backtrace, key, osError) = LastResult.GetLastErrorDetails();
 
-            throw new Exception($"Native storage failed with {_result}");
+                throw new StorageException(
+                    $"Native storage failed ({_result}), {msg?.TrimEnd()}",
+                    this,
+                    causes,
+                    backtrace,
+                    key,
+                    osError);
+            }
+
+            // The last result was overwritten, so fall back to the retained error history
+            var retained = LastResult.GetRetainedError(_id);
+            if (retained != null)
+                throw new StorageException($"Native storage failed ({_result}), {retained.TrimEnd()}", this);
+
+            throw new StorageException($"Native storage failed with {_result}", this);
         }
 
         public bool IsSuccess()
@@ -54,5 +99,70 @@ namespace Db.Storage.Native
         {
             return _result == Kind.BufferTooSmall;
         }
+
+        public bool IsCorrupted()
+        {
+            return _result == Kind.Corrupted;
+        }
+
+        public bool IsDecryptionFailed()
+        {
+            return _result == Kind.DecryptionFailed;
+        }
+
+        public bool IsValidationFailed()
+        {
+            return _result == Kind.ValidationFailed;
+        }
+
+        public bool IsIo()
+        {
+            return _result == Kind.Io;
+        }
+
+        public bool IsInvalidUtf8()
+        {
+            return _result == Kind.InvalidUtf8;
+        }
+
+        public bool IsKeyTooLong()
+        {
+            return _result == Kind.KeyTooLong;
+        }
+
+        public bool IsInvalidFilter()
+        {
+            return _result == Kind.InvalidFilter;
+        }
+
+        public bool IsInvalidIndex()
+        {
+            return _result == Kind.InvalidIndex;
+        }
+
+        public bool IsUnknownIndex()
+        {
+            return _result == Kind.UnknownIndex;
+        }
+
+        public bool IsInvalidSchema()
+        {
+            return _result == Kind.InvalidSchema;
+        }
+
+        public bool IsInvalidArgument()
+        {
+            return _result == Kind.InvalidArgument;
+        }
+
+        public bool IsInvalidHandle()
+        {
+            return _result == Kind.InvalidHandle;
+        }
+
+        public bool IsRemovedUnreadable()
+        {
+            return _result == Kind.RemovedUnreadable;
+        }
     }
 }
@@ -0,0 +1,17 @@
+using System;
+using System.Runtime.InteropServices;
+
+namespace Db.Storage.Native
+{
+    [StructLayout(LayoutKind.Sequential)]
+    struct DbSpan
+    {
+        public uint Size;
+        public IntPtr Name;
+        public UIntPtr NameLen;
+        public ulong StartUnixMicros;
+        public ulong DurationNanos;
+        public ulong ArgBytes;
+        public DbResult Result;
+    }
+}
@@ -0,0 +1,29 @@
+using System.Runtime.InteropServices;
+
+namespace Db.Storage.Native
+{
+    [StructLayout(LayoutKind.Sequential)]
+    unsafe struct DbStoreMetrics
+    {
+        // The number of error kinds there's room to count, `ErrorKinds` says how many are used
+        public const int MaxErrorKinds = 32;
+        public const int BatchSizeBuckets = 8;
+        public const int LatencyBuckets = 10;
+
+        public uint Size;
+        public ulong Reads;
+        public ulong Writes;
+        public ulong Deletes;
+        public ulong BytesRead;
+        public ulong BytesWritten;
+        public ulong Flushes;
+        public ulong FlushRequests;
+        public ulong FlushLargestBatch;
+        public fixed ulong FlushBatchSizes[BatchSizeBuckets];
+        public ulong FlushLatencyCount;
+        public ulong FlushLatencySumMicros;
+        public fixed ulong FlushLatencyBuckets[LatencyBuckets];
+        public uint ErrorKinds;
+        public fixed ulong Errors[MaxErrorKinds];
+    }
+}
@@ -0,0 +1,37 @@
+using System;
+using System.Runtime.InteropServices;
+
+namespace Db.Storage.Native
+{
+    [StructLayout(LayoutKind.Sequential)]
+    struct DbStoreOptions
+    {
+        public uint Size;
+        [MarshalAs(UnmanagedType.U1)] public bool Checksums;
+        public StoreCompression Compression;
+        public IntPtr EncryptionKeys;
+        public UIntPtr EncryptionKeysLen;
+        public uint ActiveEncryptionKey;
+        public IntPtr Indexes;
+        public UIntPtr IndexesLen;
+        [MarshalAs(UnmanagedType.U1)] public bool ValidateJson;
+        public IntPtr Schema;
+        public UIntPtr SchemaLen;
+    }
+
+    [StructLayout(LayoutKind.Sequential)]
+    unsafe struct DbEncryptionKey
+    {
+        public uint Id;
+        public fixed byte Key[StoreEncryptionKey.Size];
+    }
+
+    [StructLayout(LayoutKind.Sequential)]
+    struct DbIndex
+    {
+        public IntPtr Name;
+        public UIntPtr NameLen;
+        public IntPtr Path;
+        public UIntPtr PathLen;
+    }
+}
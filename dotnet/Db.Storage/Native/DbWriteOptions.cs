@@ -0,0 +1,12 @@
+using System.Runtime.InteropServices;
+
+namespace Db.Storage.Native
+{
+    [StructLayout(LayoutKind.Sequential)]
+    struct DbWriteOptions
+    {
+        public uint Size;
+        public WriteDurability Durability;
+        public uint GroupCommitMs;
+    }
+}
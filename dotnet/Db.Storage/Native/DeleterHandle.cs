@@ -12,6 +12,16 @@ namespace Db.Storage.Native
 
         public override bool IsInvalid => handle == IntPtr.Zero;
 
+        // Take ownership of the handle so it won't be released when disposed
+        internal IntPtr Take()
+        {
+            var h = handle;
+            handle = IntPtr.Zero;
+            SetHandleAsInvalid();
+
+            return h;
+        }
+
         protected override bool ReleaseHandle()
         {
             if (handle == IntPtr.Zero) return true;
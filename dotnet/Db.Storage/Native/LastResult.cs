@@ -1,4 +1,6 @@
 using System;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
 using System.Text;
 
 namespace Db.Storage.Native
@@ -25,5 +27,70 @@ namespace Db.Storage.Native
                 return (lastResult, Encoding.UTF8.GetString(messageBufPtr, (int) actualMessageLen));
             }
         }
+    
+        public static (DbResult, IReadOnlyList<string>, string, Key?, int?) GetLastErrorDetails()
+        {
+            var fields = new DbErrorFields { Size = (uint) Marshal.SizeOf<DbErrorFields>() };
+            Bindings.db_last_result_fields(ref fields, false);
+
+            var causes = new List<string>();
+            while (true)
+            {
+                var index = (UIntPtr) causes.Count;
+                var cause = FillMessage((buf, len) => (Bindings.db_last_result_cause(index, buf, len, out var actual, false), actual));
+
+                if (cause == null) break;
+                causes.Add(cause);
+            }
+
+            var backtrace = FillMessage((buf, len) => (Bindings.db_last_result_backtrace(buf, len, out var actual, false), actual));
+
+            return (
+                fields.Result,
+                causes,
+                backtrace,
+                fields.HasKey ? new Key(fields.Key) : (Key?) null,
+                fields.HasOsError ? fields.OsError : (int?) null);
+        }
+
+        // Returns null when the error is no longer retained
+        public static string GetRetainedError(uint id)
+        {
+            return FillMessage((buf, len) => (Bindings.db_result_details(id, buf, len, out var actual, false), actual));
+        }
+
+        public static void SetErrorRetention(int retention)
+        {
+            if (retention < 0) throw new ArgumentOutOfRangeException(nameof(retention));
+
+            Bindings.db_set_error_retention((UIntPtr) retention);
+        }
+
+        // Returns null when there's no message to fill
+        public static string FillMessage(Func<IntPtr, UIntPtr, (DbResult, UIntPtr)> fill)
+        {
+            var buffer = new byte[1024];
+
+            while (true)
+            {
+                unsafe
+                {
+                    fixed (byte* bufferPtr = buffer)
+                    {
+                        var (result, actualLen) = fill((IntPtr) bufferPtr, (UIntPtr) buffer.Length);
+
+                        if (result.IsBufferTooSmall())
+                        {
+                            buffer = new byte[(int) actualLen];
+                            continue;
+                        }
+
+                        if (!result.IsSuccess() || result.IsDone()) return null;
+
+                        return Encoding.UTF8.GetString(bufferPtr, (int) actualLen);
+                    }
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
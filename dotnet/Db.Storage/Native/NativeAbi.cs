@@ -0,0 +1,37 @@
+using System;
+
+namespace Db.Storage.Native
+{
+    static class NativeAbi
+    {
+        private static readonly Lazy<Exception> Incompatible = new Lazy<Exception>(Check);
+
+        // Throws if the native library wasn't built for the version of the interface these bindings were generated from
+        public static void EnsureCompatible()
+        {
+            var incompatible = Incompatible.Value;
+            if (incompatible != null) throw incompatible;
+        }
+
+        private static Exception Check()
+        {
+            uint major, minor;
+            try
+            {
+                Bindings.db_abi_version(out major, out minor);
+            }
+            catch (EntryPointNotFoundException)
+            {
+                // Libraries built before the interface was versioned don't export `db_abi_version`
+                return new IncompatibleNativeLibraryException(0, 0);
+            }
+
+            if (major != Bindings.DB_ABI_MAJOR || minor < Bindings.DB_ABI_MINOR)
+            {
+                return new IncompatibleNativeLibraryException(major, minor);
+            }
+
+            return null;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+using System;
+using System.Collections.Concurrent;
+using System.Runtime.InteropServices;
+using System.Text;
+using Microsoft.Extensions.Logging;
+
+namespace Db.Storage.Native
+{
+    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+    delegate void DbLogCallback(uint level, IntPtr target, UIntPtr targetLen, IntPtr message, UIntPtr messageLen);
+
+    static class NativeLog
+    {
+        // Keep the delegate alive so it can be called after native calls return
+        private static readonly DbLogCallback Callback = OnLog;
+
+        // Native targets are module paths, so they're used as logger categories
+        private static readonly ConcurrentDictionary<string, ILogger> Loggers = new ConcurrentDictionary<string, ILogger>();
+
+        private static ILoggerFactory _factory;
+
+        public static void Set(ILoggerFactory factory, LogLevel minLevel)
+        {
+            _factory = factory;
+            Loggers.Clear();
+
+            Bindings.db_set_log_callback(Callback, ToNativeLevel(minLevel));
+        }
+
+        public static void Clear()
+        {
+            Bindings.db_set_log_callback(null, 0);
+
+            _factory = null;
+            Loggers.Clear();
+        }
+
+        private static uint ToNativeLevel(LogLevel level)
+        {
+            switch (level)
+            {
+                case LogLevel.Trace: return 5;
+                case LogLevel.Debug: return 4;
+                case LogLevel.Information: return 3;
+                case LogLevel.Warning: return 2;
+                case LogLevel.Error:
+                case LogLevel.Critical: return 1;
+                default: return 0;
+            }
+        }
+
+        private static LogLevel FromNativeLevel(uint level)
+        {
+            switch (level)
+            {
+                case 1: return LogLevel.Error;
+                case 2: return LogLevel.Warning;
+                case 3: return LogLevel.Information;
+                case 4: return LogLevel.Debug;
+                default: return LogLevel.Trace;
+            }
+        }
+
+        private static unsafe void OnLog(uint level, IntPtr target, UIntPtr targetLen, IntPtr message, UIntPtr messageLen)
+        {
+            // Exceptions must not unwind into native code
+            try
+            {
+                var factory = _factory;
+                if (factory == null) return;
+
+                var category = Encoding.UTF8.GetString((byte*) target, (int) targetLen);
+                var text = Encoding.UTF8.GetString((byte*) message, (int) messageLen);
+
+                var logger = Loggers.GetOrAdd(category, factory.CreateLogger);
+                logger.Log(FromNativeLevel(level), "{Message}", text);
+            }
+            catch
+            {
+                // Ignore failures in the logger
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+using System;
+using System.Collections.Concurrent;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
+using System.Text;
+using Db.Storage.Native;
+
+namespace Db.Storage
+{
+    public readonly struct NativeSpan
+    {
+        internal NativeSpan(string name, DateTimeOffset start, TimeSpan duration, ulong argumentBytes, DbResult result)
+        {
+            Name = name;
+            Start = start;
+            Duration = duration;
+            ArgumentBytes = argumentBytes;
+            Result = result;
+        }
+
+        // The name of the native function, like `db_write_set`
+        public string Name { get; }
+
+        public DateTimeOffset Start { get; }
+
+        public TimeSpan Duration { get; }
+
+        // The sum of the size arguments to the call, like buffer lengths
+        public ulong ArgumentBytes { get; }
+
+        public DbResult Result { get; }
+    }
+
+    public static class NativeTracing
+    {
+        private const int DrainBatchSize = 256;
+
+        // Span names are static strings in the native library, so they're decoded once
+        private static readonly ConcurrentDictionary<IntPtr, string> Names = new ConcurrentDictionary<IntPtr, string>();
+
+        // Start recording spans for native calls
+        // Once `capacity` spans are waiting to be drained the oldest are dropped
+        public static void Enable(int capacity)
+        {
+            if (capacity <= 0) throw new ArgumentOutOfRangeException(nameof(capacity));
+
+            Bindings.db_trace_enable((UIntPtr) capacity);
+        }
+
+        public static void Disable()
+        {
+            Bindings.db_trace_enable(UIntPtr.Zero);
+        }
+
+        // Take the spans recorded since the last drain, oldest first
+        // `dropped` is the number of spans lost because the buffer was full
+        public static IReadOnlyList<NativeSpan> Drain(out ulong dropped)
+        {
+            var spans = new List<NativeSpan>();
+            var buffer = new DbSpan[DrainBatchSize];
+            dropped = 0;
+
+            while (true)
+            {
+                UIntPtr drained;
+                ulong batchDropped;
+
+                // The native library uses the size of the first span for all of them
+                buffer[0].Size = (uint) Marshal.SizeOf<DbSpan>();
+
+                unsafe
+                {
+                    fixed (DbSpan* bufferPtr = buffer)
+                    {
+                        Bindings.db_trace_drain((IntPtr) bufferPtr, (UIntPtr) buffer.Length, out drained, out batchDropped);
+                    }
+                }
+
+                dropped += batchDropped;
+
+                for (var i = 0; i < (int) drained; i++)
+                {
+                    spans.Add(ToSpan(buffer[i]));
+                }
+
+                if ((int) drained < buffer.Length) return spans;
+            }
+        }
+
+        private static NativeSpan ToSpan(DbSpan span)
+        {
+            var name = Names.GetOrAdd(span.Name, ptr => DecodeName(ptr, span.NameLen));
+            var start = DateTimeOffset.UnixEpoch.AddTicks((long) span.StartUnixMicros * 10);
+            var duration = TimeSpan.FromTicks((long) (span.DurationNanos / 100));
+
+            return new NativeSpan(name, start, duration, span.ArgBytes, span.Result);
+        }
+
+        private static unsafe string DecodeName(IntPtr name, UIntPtr nameLen)
+        {
+            return Encoding.UTF8.GetString((byte*) name, (int) nameLen);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+namespace Db.Storage
+{
+    // The outcome of rewriting payloads with the active encryption key
+    public sealed class Reencryption
+    {
+        internal Reencryption(ulong reencrypted, ulong skipped)
+        {
+            Reencrypted = reencrypted;
+            Skipped = skipped;
+        }
+
+        // The number of records that were rewritten with the active key
+        public ulong Reencrypted { get; }
+
+        // The number of records that couldn't be read, so are still encrypted with a retired key
+        public ulong Skipped { get; }
+    }
+}
@@ -0,0 +1,27 @@
+using System;
+using Db.Storage.Native;
+
+namespace Db.Storage
+{
+    // The features supported by the native library
+    [Flags]
+    public enum StorageCapabilities : ulong
+    {
+        None = 0,
+        Checksums = Bindings.DB_CAPABILITY_CHECKSUMS,
+        Compression = Bindings.DB_CAPABILITY_COMPRESSION,
+        Encryption = Bindings.DB_CAPABILITY_ENCRYPTION,
+        Indexes = Bindings.DB_CAPABILITY_INDEXES,
+        Filters = Bindings.DB_CAPABILITY_FILTERS,
+        Validation = Bindings.DB_CAPABILITY_VALIDATION,
+        AsyncCompletion = Bindings.DB_CAPABILITY_ASYNC_COMPLETION,
+        Durability = Bindings.DB_CAPABILITY_DURABILITY,
+        RangeDeletes = Bindings.DB_CAPABILITY_RANGE_DELETES,
+        ErrorDetails = Bindings.DB_CAPABILITY_ERROR_DETAILS,
+        Logging = Bindings.DB_CAPABILITY_LOGGING,
+        Tracing = Bindings.DB_CAPABILITY_TRACING,
+        Metrics = Bindings.DB_CAPABILITY_METRICS,
+        Debug = Bindings.DB_CAPABILITY_DEBUG,
+        CheckedHandles = Bindings.DB_CAPABILITY_CHECKED_HANDLES
+    }
+}
@@ -0,0 +1,38 @@
+using System;
+using System.Collections.Generic;
+using Db.Storage.Native;
+
+namespace Db.Storage
+{
+    public class StorageException : Exception
+    {
+        internal StorageException(
+            string message,
+            DbResult result,
+            IReadOnlyList<string> causes = null,
+            string nativeBacktrace = null,
+            Key? key = null,
+            int? osError = null)
+            : base(message)
+        {
+            Result = result;
+            Causes = causes ?? Array.Empty<string>();
+            NativeBacktrace = nativeBacktrace;
+            Key = key;
+            OsError = osError;
+        }
+
+        public DbResult Result { get; }
+
+        // The messages of the native error and each of its causes, outermost first
+        public IReadOnlyList<string> Causes { get; }
+
+        public string NativeBacktrace { get; }
+
+        // The key of the record involved in the error
+        public Key? Key { get; }
+
+        // The OS error code of the IO error that caused the error
+        public int? OsError { get; }
+    }
+}
@@ -0,0 +1,23 @@
+using System;
+using Db.Storage.Native;
+using Microsoft.Extensions.Logging;
+
+namespace Db.Storage
+{
+    public static class StorageLogging
+    {
+        // Send events from the native library to loggers created by the factory
+        // Loggers are created for each native target, like `db::store::durability`
+        public static void Use(ILoggerFactory factory, LogLevel minLevel = LogLevel.Information)
+        {
+            if (factory == null) throw new ArgumentNullException(nameof(factory));
+
+            NativeLog.Set(factory, minLevel);
+        }
+
+        public static void Disable()
+        {
+            NativeLog.Clear();
+        }
+    }
+}
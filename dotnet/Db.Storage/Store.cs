@@ -1,4 +1,6 @@
 using System;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
 using System.Text;
 using Db.Storage.Native;
 
@@ -8,6 +10,18 @@ namespace Db.Storage
     {
         private StoreHandle _handle;
 
+        // The features supported by the native library
+        public static StorageCapabilities Capabilities
+        {
+            get
+            {
+                NativeAbi.EnsureCompatible();
+
+                Bindings.db_capabilities(out var flags);
+                return (StorageCapabilities) flags;
+            }
+        }
+
         public void Dispose()
         {
             _handle.Dispose();
@@ -16,6 +30,8 @@ namespace Db.Storage
         public static Store Open(string path)
         {
             if (path == null) throw new ArgumentNullException(nameof(path));
+            NativeAbi.EnsureCompatible();
+
             var pathUtf8 = Encoding.UTF8.GetBytes(path);
 
             unsafe
@@ -32,22 +48,193 @@ namespace Db.Storage
             }
         }
 
+        public static Store Open(string path, StoreOptions options)
+        {
+            if (path == null) throw new ArgumentNullException(nameof(path));
+            if (options == null) throw new ArgumentNullException(nameof(options));
+            NativeAbi.EnsureCompatible();
+
+            var pathUtf8 = Encoding.UTF8.GetBytes(path);
+            var schemaUtf8 = options.Schema != null ? Encoding.UTF8.GetBytes(options.Schema) : Array.Empty<byte>();
+
+            var keys = options.EncryptionKeys ?? Array.Empty<StoreEncryptionKey>();
+            var rawKeys = new DbEncryptionKey[keys.Count];
+
+            var indexes = options.Indexes ?? Array.Empty<StoreIndex>();
+            var rawIndexes = new DbIndex[indexes.Count];
+
+            // The index names and paths are pinned until the store has been opened
+            var pinned = new List<GCHandle>();
+
+            try
+            {
+                unsafe
+                {
+                    for (var i = 0; i < keys.Count; i++)
+                    {
+                        rawKeys[i].Id = keys[i].Id;
+
+                        fixed (byte* rawKey = rawKeys[i].Key)
+                        {
+                            for (var b = 0; b < StoreEncryptionKey.Size; b++) rawKey[b] = keys[i].Key[b];
+                        }
+                    }
+
+                    for (var i = 0; i < indexes.Count; i++)
+                    {
+                        var nameUtf8 = Encoding.UTF8.GetBytes(indexes[i].Name);
+                        var indexPathUtf8 = Encoding.UTF8.GetBytes(indexes[i].Path);
+
+                        var name = GCHandle.Alloc(nameUtf8, GCHandleType.Pinned);
+                        pinned.Add(name);
+                        var indexPath = GCHandle.Alloc(indexPathUtf8, GCHandleType.Pinned);
+                        pinned.Add(indexPath);
+
+                        rawIndexes[i] = new DbIndex
+                        {
+                            Name = name.AddrOfPinnedObject(),
+                            NameLen = (UIntPtr) nameUtf8.Length,
+                            Path = indexPath.AddrOfPinnedObject(),
+                            PathLen = (UIntPtr) indexPathUtf8.Length
+                        };
+                    }
+
+                    fixed (byte* pathUtf8Ptr = pathUtf8)
+                    fixed (DbEncryptionKey* rawKeysPtr = rawKeys)
+                    fixed (DbIndex* rawIndexesPtr = rawIndexes)
+                    fixed (byte* schemaUtf8Ptr = schemaUtf8)
+                    {
+                        var rawOptions = new DbStoreOptions
+                        {
+                            Size = (uint) Marshal.SizeOf<DbStoreOptions>(),
+                            Checksums = options.Checksums,
+                            Compression = options.Compression,
+                            EncryptionKeys = (IntPtr) rawKeysPtr,
+                            EncryptionKeysLen = (UIntPtr) rawKeys.Length,
+                            ActiveEncryptionKey = options.ActiveEncryptionKey,
+                            Indexes = (IntPtr) rawIndexesPtr,
+                            IndexesLen = (UIntPtr) rawIndexes.Length,
+                            ValidateJson = options.ValidateJson || options.Schema != null,
+                            Schema = (IntPtr) schemaUtf8Ptr,
+                            SchemaLen = (UIntPtr) schemaUtf8.Length
+                        };
+
+                        Bindings.db_store_open_with_options(
+                            (IntPtr) pathUtf8Ptr,
+                            (UIntPtr) pathUtf8.Length,
+                            in rawOptions,
+                            out var handle);
+
+                        return new Store
+                        {
+                            _handle = handle
+                        };
+                    }
+                }
+            }
+            finally
+            {
+                foreach (var handle in pinned) handle.Free();
+            }
+        }
+
         public Reader BeginRead()
         {
             Bindings.db_read_begin(_handle, out var readerHandle);
             return new Reader(readerHandle);
         }
 
+        public Reader BeginRead(string filter)
+        {
+            if (filter == null) throw new ArgumentNullException(nameof(filter));
+            var filterUtf8 = Encoding.UTF8.GetBytes(filter);
+
+            unsafe
+            {
+                fixed (byte* filterUtf8Ptr = filterUtf8)
+                {
+                    Bindings.db_read_begin_with_filter(
+                        _handle,
+                        (IntPtr) filterUtf8Ptr,
+                        (UIntPtr) filterUtf8.Length,
+                        out var readerHandle);
+
+                    return new Reader(readerHandle);
+                }
+            }
+        }
+
+        public Reader QueryIndex(string index, string value)
+        {
+            if (index == null) throw new ArgumentNullException(nameof(index));
+            if (value == null) throw new ArgumentNullException(nameof(value));
+            var indexUtf8 = Encoding.UTF8.GetBytes(index);
+            var valueUtf8 = Encoding.UTF8.GetBytes(value);
+
+            unsafe
+            {
+                fixed (byte* indexUtf8Ptr = indexUtf8)
+                fixed (byte* valueUtf8Ptr = valueUtf8)
+                {
+                    Bindings.db_index_query(
+                        _handle,
+                        (IntPtr) indexUtf8Ptr,
+                        (UIntPtr) indexUtf8.Length,
+                        (IntPtr) valueUtf8Ptr,
+                        (UIntPtr) valueUtf8.Length,
+                        out var readerHandle);
+
+                    return new Reader(readerHandle);
+                }
+            }
+        }
+
         public Writer BeginWrite()
         {
             Bindings.db_write_begin(_handle, out var writerHandle);
             return new Writer(writerHandle);
         }
 
+        public Writer BeginWrite(WriteOptions options)
+        {
+            if (options == null) throw new ArgumentNullException(nameof(options));
+
+            var rawOptions = new DbWriteOptions
+            {
+                Size = (uint) Marshal.SizeOf<DbWriteOptions>(),
+                Durability = options.Durability,
+                GroupCommitMs = (uint) options.GroupCommitWindow.TotalMilliseconds
+            };
+
+            Bindings.db_write_begin_with_options(_handle, in rawOptions, out var writerHandle);
+            return new Writer(writerHandle);
+        }
+
         public Deleter BeginDelete()
         {
             Bindings.db_delete_begin(_handle, out var deleterHandle);
             return new Deleter(deleterHandle);
         }
+
+        public StoreMetrics GetMetrics()
+        {
+            var metrics = new DbStoreMetrics { Size = (uint) Marshal.SizeOf<DbStoreMetrics>() };
+            Bindings.db_store_metrics(_handle, ref metrics);
+            return new StoreMetrics(metrics);
+        }
+
+        // Get the metrics in the Prometheus text format, ready to serve on `/metrics`
+        public string GetPrometheusMetrics()
+        {
+            return LastResult.FillMessage((buf, len) => (Bindings.db_store_metrics_prometheus(_handle, buf, len, out var actual), actual));
+        }
+
+        // Rewrite payloads encrypted with a retired key using the active key
+        // Records that can't be read are skipped, so they need to be checked before retired keys are dropped
+        public Reencryption Reencrypt()
+        {
+            Bindings.db_store_reencrypt(_handle, out var reencrypted, out var skipped);
+            return new Reencryption((ulong) reencrypted, (ulong) skipped);
+        }
     }
 }
@@ -0,0 +1,109 @@
+using System;
+using System.Collections.Generic;
+using Db.Storage.Native;
+
+namespace Db.Storage
+{
+    public sealed class StoreMetrics
+    {
+        // The names of error kinds, in the order they're counted by the native library
+        private static readonly string[] ErrorKindNames =
+        {
+            "other",
+            "io",
+            "corrupted",
+            "decryption_failed",
+            "validation_failed",
+            "key_too_long",
+            "invalid_filter",
+            "invalid_index",
+            "unknown_index",
+            "invalid_schema",
+            "invalid_argument"
+        };
+
+        // The upper bounds of the flush latency buckets, the last bucket counts the rest
+        public static readonly IReadOnlyList<TimeSpan> FlushLatencyBounds = new[]
+        {
+            TimeSpan.FromTicks(1_000),
+            TimeSpan.FromTicks(5_000),
+            TimeSpan.FromMilliseconds(1),
+            TimeSpan.FromMilliseconds(5),
+            TimeSpan.FromMilliseconds(10),
+            TimeSpan.FromMilliseconds(50),
+            TimeSpan.FromMilliseconds(100),
+            TimeSpan.FromMilliseconds(500),
+            TimeSpan.FromSeconds(1)
+        };
+
+        internal unsafe StoreMetrics(DbStoreMetrics raw)
+        {
+            Reads = raw.Reads;
+            Writes = raw.Writes;
+            Deletes = raw.Deletes;
+            BytesRead = raw.BytesRead;
+            BytesWritten = raw.BytesWritten;
+            Flushes = raw.Flushes;
+            FlushRequests = raw.FlushRequests;
+            FlushLargestBatch = raw.FlushLargestBatch;
+            FlushLatencyCount = raw.FlushLatencyCount;
+            FlushLatencySum = TimeSpan.FromTicks((long) raw.FlushLatencySumMicros * 10);
+
+            var errors = new Dictionary<string, ulong>();
+            // Kinds added to the native library after these bindings aren't named, so they're skipped
+            var errorKinds = Math.Min(raw.ErrorKinds, (uint) ErrorKindNames.Length);
+            for (var i = 0; i < errorKinds; i++)
+            {
+                errors.Add(ErrorKindNames[i], raw.Errors[i]);
+            }
+
+            Errors = errors;
+
+            var batchSizes = new ulong[DbStoreMetrics.BatchSizeBuckets];
+            for (var i = 0; i < batchSizes.Length; i++)
+            {
+                batchSizes[i] = raw.FlushBatchSizes[i];
+            }
+
+            FlushBatchSizes = batchSizes;
+
+            var latencyBuckets = new ulong[DbStoreMetrics.LatencyBuckets];
+            for (var i = 0; i < latencyBuckets.Length; i++)
+            {
+                latencyBuckets[i] = raw.FlushLatencyBuckets[i];
+            }
+
+            FlushLatencyBuckets = latencyBuckets;
+        }
+
+        public ulong Reads { get; }
+
+        public ulong Writes { get; }
+
+        public ulong Deletes { get; }
+
+        // Payload bytes before they're encoded
+        public ulong BytesRead { get; }
+
+        public ulong BytesWritten { get; }
+
+        // The number of errors by kind, like `io` or `key_too_long`
+        public IReadOnlyDictionary<string, ulong> Errors { get; }
+
+        public ulong Flushes { get; }
+
+        public ulong FlushRequests { get; }
+
+        public ulong FlushLargestBatch { get; }
+
+        // Bucket `i` counts flushes shared by at least `2^i` and fewer than `2^(i + 1)` completions
+        public IReadOnlyList<ulong> FlushBatchSizes { get; }
+
+        public ulong FlushLatencyCount { get; }
+
+        public TimeSpan FlushLatencySum { get; }
+
+        // Counts flushes by `FlushLatencyBounds`
+        public IReadOnlyList<ulong> FlushLatencyBuckets { get; }
+    }
+}
@@ -0,0 +1,59 @@
+using System;
+using System.Collections.Generic;
+
+namespace Db.Storage
+{
+    public sealed class StoreOptions
+    {
+        public bool Checksums { get; set; }
+
+        public StoreCompression Compression { get; set; }
+
+        public IReadOnlyList<StoreEncryptionKey> EncryptionKeys { get; set; }
+
+        public uint ActiveEncryptionKey { get; set; }
+
+        public IReadOnlyList<StoreIndex> Indexes { get; set; }
+
+        public bool ValidateJson { get; set; }
+
+        public string Schema { get; set; }
+    }
+
+    public enum StoreCompression : uint
+    {
+        None,
+        Lz4
+    }
+
+    public sealed class StoreEncryptionKey
+    {
+        public const int Size = 32;
+
+        public StoreEncryptionKey(uint id, byte[] key)
+        {
+            if (key == null) throw new ArgumentNullException(nameof(key));
+            if (key.Length != Size) throw new ArgumentException($"Encryption keys must be {Size} bytes", nameof(key));
+
+            Id = id;
+            Key = key;
+        }
+
+        public uint Id { get; }
+
+        public byte[] Key { get; }
+    }
+
+    public sealed class StoreIndex
+    {
+        public StoreIndex(string name, string path)
+        {
+            Name = name ?? throw new ArgumentNullException(nameof(name));
+            Path = path ?? throw new ArgumentNullException(nameof(path));
+        }
+
+        public string Name { get; }
+
+        public string Path { get; }
+    }
+}
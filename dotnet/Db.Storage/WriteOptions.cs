@@ -0,0 +1,21 @@
+using System;
+
+namespace Db.Storage
+{
+    public sealed class WriteOptions
+    {
+        public WriteDurability Durability { get; set; } = WriteDurability.FlushOnComplete;
+
+        public TimeSpan GroupCommitWindow { get; set; }
+    }
+
+    public enum WriteDurability : uint
+    {
+        None,
+        FlushOnComplete,
+        FlushEveryWrite,
+        // Share a flush with other writers ended within `GroupCommitWindow`
+        // Ending the writer waits for the shared flush, so changes are durable once it returns
+        GroupCommit
+    }
+}
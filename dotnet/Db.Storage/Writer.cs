@@ -1,5 +1,6 @@
 using System;
 using System.Runtime.CompilerServices;
+using System.Threading.Tasks;
 using Db.Storage.Native;
 
 namespace Db.Storage
@@ -18,6 +19,27 @@ namespace Db.Storage
             _handle.Dispose();
         }
 
+        public Task CompleteAsync()
+        {
+            var (task, state) = Completion.Begin();
+
+            try
+            {
+                // The native writer is only released if the call succeeds
+                // Otherwise it's still owned by the handle and the callback won't be invoked
+                Bindings.db_write_end_async(_handle.DangerousGetHandle(), Completion.Callback, state);
+            }
+            catch
+            {
+                Completion.Cancel(state);
+                throw;
+            }
+
+            _handle.Take();
+
+            return task;
+        }
+
         public void Set(Key key, ReadOnlySpan<byte> value)
         {
             unsafe
@@ -45,7 +45,8 @@ namespace Db.Tests.Api
 
             using (var deleter = store.BeginDelete())
             {
-                deleter.Remove(deletedKey);
+                Assert.True(deleter.Remove(deletedKey));
+                Assert.False(deleter.Remove(deletedKey));
             }
 
             using var reader = store.BeginRead();
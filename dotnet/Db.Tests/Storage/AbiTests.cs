@@ -0,0 +1,43 @@
+using System.Runtime.InteropServices;
+using Db.Storage;
+using Db.Storage.Native;
+using Xunit;
+
+namespace Db.Tests.Storage
+{
+    public class AbiTests
+    {
+        [Fact]
+        public void NativeLibraryMatchesBindings()
+        {
+            Bindings.db_abi_version(out var major, out var minor);
+
+            Assert.Equal(Bindings.DB_ABI_MAJOR, major);
+            Assert.True(minor >= Bindings.DB_ABI_MINOR);
+        }
+
+        [Fact]
+        public void StoreMetricsFieldOffsetsMatchNativeLibrary()
+        {
+            // Fields are only ever added to the end, so these offsets must never change
+            Assert.Equal(48, (int) Marshal.OffsetOf<DbStoreMetrics>(nameof(DbStoreMetrics.Flushes)));
+            Assert.Equal(152, (int) Marshal.OffsetOf<DbStoreMetrics>(nameof(DbStoreMetrics.FlushLatencyBuckets)));
+            Assert.Equal(232, (int) Marshal.OffsetOf<DbStoreMetrics>(nameof(DbStoreMetrics.ErrorKinds)));
+            Assert.Equal(240, (int) Marshal.OffsetOf<DbStoreMetrics>(nameof(DbStoreMetrics.Errors)));
+            Assert.Equal(496, Marshal.SizeOf<DbStoreMetrics>());
+        }
+
+        [Fact]
+        public void NativeLibraryReportsCapabilities()
+        {
+            var capabilities = Store.Capabilities;
+
+            Assert.True(capabilities.HasFlag(StorageCapabilities.Checksums));
+            Assert.True(capabilities.HasFlag(StorageCapabilities.Metrics));
+#if DEBUG
+            Assert.True(capabilities.HasFlag(StorageCapabilities.Debug));
+            Assert.True(capabilities.HasFlag(StorageCapabilities.CheckedHandles));
+#endif
+        }
+    }
+}
@@ -1,5 +1,6 @@
 using System;
 using System.Linq;
+using System.Threading.Tasks;
 using Db.Storage;
 using Db.Tests.Support;
 using Xunit;
@@ -77,5 +78,283 @@ namespace Db.Tests.Storage
                 Assert.Equal(events.Length, count);
             }
         }
+
+        [Fact]
+        public async Task CompletedWritesCanBeRead()
+        {
+            var events = new[]
+            {
+                Some.Event(),
+                Some.Event()
+            };
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    foreach (var (key, payload) in events) writer.Set(key, payload);
+
+                    await writer.CompleteAsync();
+                }
+
+                Assert.Equal(events.Length, ReadCount(store.Store));
+            }
+        }
+
+        [Fact]
+        public async Task CompletedDeletesCannotBeRead()
+        {
+            var events = new[]
+            {
+                Some.Event(),
+                Some.Event()
+            };
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    foreach (var (key, payload) in events) writer.Set(key, payload);
+                }
+
+                using (var deleter = store.Store.BeginDelete())
+                {
+                    deleter.Remove(events[0].Item1);
+
+                    await deleter.CompleteAsync();
+                }
+
+                Assert.Equal(events.Length - 1, ReadCount(store.Store));
+            }
+        }
+
+        [Fact]
+        public async Task CompletingTwiceFails()
+        {
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    await writer.CompleteAsync();
+
+                    Assert.ThrowsAny<Exception>(() => writer.CompleteAsync());
+                }
+            }
+        }
+
+        [Theory]
+        [InlineData(WriteDurability.None)]
+        [InlineData(WriteDurability.FlushOnComplete)]
+        [InlineData(WriteDurability.FlushEveryWrite)]
+        [InlineData(WriteDurability.GroupCommit)]
+        public async Task WrittenDataCanBeReadWithAnyDurability(WriteDurability durability)
+        {
+            var events = new[]
+            {
+                Some.Event(),
+                Some.Event()
+            };
+
+            var options = new WriteOptions
+            {
+                Durability = durability,
+                GroupCommitWindow = TimeSpan.FromMilliseconds(5)
+            };
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite(options))
+                {
+                    foreach (var (key, payload) in events) writer.Set(key, payload);
+
+                    await writer.CompleteAsync();
+                }
+
+                Assert.Equal(events.Length, ReadCount(store.Store));
+            }
+        }
+
+        [Fact]
+        public void RemoveReportsWhetherTheKeyWasPresent()
+        {
+            var (key, payload) = Some.Event();
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    writer.Set(key, payload);
+                }
+
+                using (var deleter = store.Store.BeginDelete())
+                {
+                    Assert.True(deleter.Remove(key));
+                    Assert.False(deleter.Remove(key));
+                }
+            }
+        }
+
+        [Fact]
+        public void RemovedDataIsReturned()
+        {
+            var (key, payload) = Some.Event();
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    writer.Set(key, payload);
+                }
+
+                using (var deleter = store.Store.BeginDelete())
+                {
+                    var readInto = new byte[1];
+
+                    var removed = deleter.TryRemoveReturning(key, readInto.AsSpan());
+                    Assert.True(removed.IsBufferTooSmall(out var required));
+
+                    // The payload of a key removed into a buffer that's too small is kept for the next attempt
+                    readInto = new byte[required];
+                    removed = deleter.TryRemoveReturning(key, readInto.AsSpan());
+
+                    removed.GetData(out var removedKey, out var removedPayload);
+
+                    Assert.Equal(key, removedKey);
+                    Assert.Equal(payload, removedPayload.Span.ToArray());
+
+                    Assert.True(deleter.TryRemoveReturning(key, readInto.AsSpan()).IsDone);
+                }
+
+                Assert.Equal(0, ReadCount(store.Store));
+            }
+        }
+
+        [Fact]
+        public void RemovedDataIsOnlyKeptForTheNextAttempt()
+        {
+            var (key, payload) = Some.Event();
+            var (otherKey, _) = Some.Event();
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    writer.Set(key, payload);
+                }
+
+                using (var deleter = store.Store.BeginDelete())
+                {
+                    var removed = deleter.TryRemoveReturning(key, new byte[1].AsSpan());
+                    Assert.True(removed.IsBufferTooSmall(out var required));
+
+                    // Any other call on the deleter discards the kept payload
+                    deleter.Remove(otherKey);
+
+                    Assert.True(deleter.TryRemoveReturning(key, new byte[required].AsSpan()).IsDone);
+                }
+            }
+        }
+
+        [Fact]
+        public void RemoveRangeExcludesTheEnd()
+        {
+            var events = new[]
+            {
+                Some.EventWith(1, "Data 1"),
+                Some.EventWith(2, "Data 2"),
+                Some.EventWith(3, "Data 3")
+            };
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    foreach (var (key, payload) in events) writer.Set(key, payload);
+                }
+
+                using (var deleter = store.Store.BeginDelete())
+                {
+                    Assert.Equal(2UL, deleter.RemoveRange(Some.KeyWith(1), Some.KeyWith(3)));
+                }
+
+                Assert.Equal(1, ReadCount(store.Store));
+            }
+        }
+
+        [Fact]
+        public void MetricsCountReadsWritesAndDeletes()
+        {
+            var events = new[]
+            {
+                Some.Event(),
+                Some.Event()
+            };
+
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    foreach (var (key, payload) in events) writer.Set(key, payload);
+                }
+
+                using (var deleter = store.Store.BeginDelete())
+                {
+                    deleter.Remove(events[0].Item1);
+                }
+
+                ReadCount(store.Store);
+
+                var metrics = store.Store.GetMetrics();
+
+                Assert.Equal((ulong) events.Length, metrics.Writes);
+                Assert.Equal((ulong) events.Sum(evt => evt.Item2.Length), metrics.BytesWritten);
+                Assert.Equal(1UL, metrics.Deletes);
+                Assert.Equal(1UL, metrics.Reads);
+                Assert.All(metrics.Errors.Values, errors => Assert.Equal(0UL, errors));
+            }
+        }
+
+        [Fact]
+        public void PrometheusMetricsCountWrites()
+        {
+            using (var store = new TempStore())
+            {
+                using (var writer = store.Store.BeginWrite())
+                {
+                    var (key, payload) = Some.Event();
+                    writer.Set(key, payload);
+                }
+
+                var metrics = store.Store.GetPrometheusMetrics();
+
+                Assert.Contains("db_writes_total 1\n", metrics);
+            }
+        }
+
+        // Read every record in the store, returning how many there were
+        // Results can't be held across awaits, so async tests read through here
+        private static int ReadCount(Store store)
+        {
+            var count = 0;
+
+            using (var reader = store.BeginRead())
+            {
+                var readInto = new byte[1];
+
+                ReadResult read;
+                while (!(read = reader.TryReadNext(readInto.AsSpan())).IsDone)
+                {
+                    if (read.IsBufferTooSmall(out var required))
+                    {
+                        readInto = new byte[required];
+                        continue;
+                    }
+
+                    count += 1;
+                }
+            }
+
+            return count;
+        }
     }
 }
\ No newline at end of file
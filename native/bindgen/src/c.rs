@@ -0,0 +1,327 @@
+/*!
+Generating a C header.
+
+Constants are declared as macros. Handles are declared as opaque structs. Other types are declared with the same layout
+as their Rust definitions. Types that don't start with `Db` are given that prefix,
+so the `Kind` of a `DbResult` is declared as `DbKind`.
+*/
+
+use std::fmt::Write;
+
+use crate::manifest::{
+    Constant,
+    Definition,
+    Function,
+    Manifest,
+    Type,
+    TypeDef,
+};
+
+/**
+Prototypes longer than this have their parameters split over multiple lines.
+*/
+const MAX_LINE_LEN: usize = 100;
+
+/**
+Generate a C header with types and prototypes for a manifest.
+*/
+pub fn generate(manifest: &Manifest) -> String {
+    let mut out = String::new();
+
+    out.push_str(
+        "/* Generated by dbc-bindgen from `native/c/src`. Don't edit this file by hand. */\n\
+         \n\
+         #ifndef DB_H\n\
+         #define DB_H\n\
+         \n\
+         #include <stdbool.h>\n\
+         #include <stddef.h>\n\
+         #include <stdint.h>\n\
+         \n\
+         #ifdef __cplusplus\n\
+         extern \"C\" {\n\
+         #endif\n",
+    );
+
+    for constant in &manifest.constants {
+        out.push('\n');
+        generate_constant(&mut out, constant);
+    }
+
+    for ty in &manifest.types {
+        out.push('\n');
+        generate_type(&mut out, ty);
+    }
+
+    // Functions that are only exported from debug builds of `dbc` are behind `DB_DEBUG`
+    let mut debug_only = false;
+    for function in &manifest.functions {
+        if debug_only && !function.debug_only {
+            out.push_str("#endif\n");
+        }
+
+        out.push('\n');
+
+        if !debug_only && function.debug_only {
+            out.push_str("#ifdef DB_DEBUG\n");
+        }
+        debug_only = function.debug_only;
+
+        generate_function(&mut out, function);
+    }
+
+    if debug_only {
+        out.push_str("#endif\n");
+    }
+
+    out.push_str(
+        "\n\
+         #ifdef __cplusplus\n\
+         }\n\
+         #endif\n\
+         \n\
+         #endif\n",
+    );
+
+    out
+}
+
+fn generate_constant(out: &mut String, constant: &Constant) {
+    if let Some(ref docs) = constant.docs {
+        let _ = writeln!(out, "/*\n{}\n*/", docs);
+    }
+
+    let suffix = match constant.ty {
+        Type::Primitive { ref name } if name == "u64" || name == "i64" => "64",
+        _ => "32",
+    };
+    let signed = match constant.ty {
+        Type::Primitive { ref name } if name.starts_with('i') => "",
+        _ => "U",
+    };
+
+    let _ = writeln!(
+        out,
+        "#define {} {}INT{}_C({})",
+        constant.name, signed, suffix, constant.value
+    );
+}
+
+fn generate_type(out: &mut String, ty: &TypeDef) {
+    let name = c_name(&ty.name);
+
+    if let Some(ref docs) = ty.docs {
+        let _ = writeln!(out, "/*\n{}\n*/", docs);
+    }
+
+    match &ty.def {
+        Definition::Opaque => {
+            let _ = writeln!(out, "typedef struct {0} {0};", name);
+        }
+        Definition::Struct { fields } => {
+            let _ = writeln!(out, "typedef struct {} {{", name);
+            for field in fields {
+                let _ = writeln!(out, "    {};", declare(&field.ty, &field.name));
+            }
+            let _ = writeln!(out, "}} {};", name);
+        }
+        // Enums are declared as integers so they're the same size as in Rust
+        Definition::Enum { variants } => {
+            let _ = writeln!(out, "typedef uint32_t {};", name);
+
+            let prefix = upper_snake_case(&name);
+            for variant in variants {
+                let _ = writeln!(
+                    out,
+                    "#define {}_{} {}",
+                    prefix,
+                    upper_snake_case(&variant.name),
+                    variant.value
+                );
+            }
+        }
+        Definition::Callback { args } => {
+            let args: Vec<_> = args.iter().map(|arg| declare(&arg.ty, &arg.name)).collect();
+
+            signature(out, &format!("typedef void (*{})", name), &args);
+        }
+        Definition::Alias { ty } => {
+            let _ = writeln!(out, "typedef {};", declare(ty, &name));
+        }
+    }
+}
+
+fn generate_function(out: &mut String, function: &Function) {
+    let args: Vec<_> = function
+        .args
+        .iter()
+        .map(|arg| declare(&arg.ty, &arg.name))
+        .collect();
+
+    signature(out, &format!("DbResult {}", function.name), &args);
+}
+
+/**
+Write a signature on a single line if it fits, or with a parameter per line if it doesn't.
+*/
+fn signature(out: &mut String, prefix: &str, args: &[String]) {
+    let single = format!("{}({});", prefix, params(args));
+
+    if single.len() <= MAX_LINE_LEN {
+        let _ = writeln!(out, "{}", single);
+    } else {
+        let _ = writeln!(out, "{}(\n    {});", prefix, args.join(",\n    "));
+    }
+}
+
+fn params(args: &[String]) -> String {
+    if args.is_empty() {
+        "void".to_owned()
+    } else {
+        args.join(", ")
+    }
+}
+
+/**
+Declare a C variable with the given type and name.
+*/
+fn declare(ty: &Type, name: &str) -> String {
+    match ty {
+        Type::Ref { target } => format!("const {}", declare(target, &format!("*{}", name))),
+        Type::RefMut { target } | Type::Out { target } => declare(target, &format!("*{}", name)),
+        Type::Pointer { target, mutable } => {
+            let declared = declare(target, &format!("*{}", name));
+
+            if *mutable {
+                declared
+            } else {
+                format!("const {}", declared)
+            }
+        }
+        Type::Array { element, len } => declare(element, &format!("{}[{}]", name, len)),
+        Type::Handle { target, .. } => format!("{} *{}", c_name(target), name),
+        Type::Named { name: ty } => format!("{} {}", c_name(ty), name),
+        Type::Primitive { name: ty } => format!("{} {}", primitive(ty), name),
+    }
+}
+
+fn primitive(name: &str) -> &'static str {
+    match name {
+        "size_t" => "size_t",
+        "u8" => "uint8_t",
+        "u16" => "uint16_t",
+        "u32" => "uint32_t",
+        "u64" => "uint64_t",
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "bool" => "bool",
+        _ => "void",
+    }
+}
+
+fn c_name(name: &str) -> String {
+    if name.starts_with("Db") {
+        name.to_owned()
+    } else {
+        format!("Db{}", name)
+    }
+}
+
+fn upper_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+
+        out.extend(c.to_uppercase());
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn declarations() {
+        let manifest = Manifest::from_source(
+            r#"
+            pub type DbStoreHandle<'a> = HandleShared<'a, DbStore>;
+
+            #[repr(C)]
+            pub struct DbStore {
+                inner: store::Store,
+            }
+
+            /**
+            The result of a call.
+            */
+            #[repr(C)]
+            pub struct DbResult {
+                kind: Kind,
+                id: u32,
+            }
+
+            #[repr(u32)]
+            enum Kind {
+                Ok = 0,
+                BufferTooSmall = 2,
+            }
+
+            #[repr(transparent)]
+            pub struct DbCallback(Option<extern "C" fn(result: DbResult, state: *mut c_void)>);
+
+            #[ffi]
+            fn db_store_open(path: Ref<u8>, path_len: size_t, store: Out<DbStoreHandle>) -> DbResult {
+                DbResult::ok()
+            }
+
+            #[ffi]
+            fn db_store_close_async(store: DbStoreHandle, callback: DbCallback) -> DbResult {
+                DbResult::ok()
+            }
+
+            #[cfg(debug_assertions)]
+            #[ffi]
+            fn db_test_ok() -> DbResult {
+                DbResult::ok()
+            }
+            "#,
+        )
+        .unwrap();
+
+        let header = generate(&manifest);
+
+        assert!(header.contains("typedef struct DbStore DbStore;\n"));
+        assert!(header.contains(
+            "typedef uint32_t DbKind;\n#define DB_KIND_OK 0\n#define DB_KIND_BUFFER_TOO_SMALL 2\n"
+        ));
+        assert!(header.contains(
+            "/*\nThe result of a call.\n*/\ntypedef struct DbResult {\n    DbKind kind;\n    uint32_t id;\n} DbResult;\n"
+        ));
+        assert!(header.contains("typedef void (*DbCallback)(DbResult result, void *state);\n"));
+        assert!(header.contains(
+            "DbResult db_store_open(const uint8_t *path, size_t path_len, DbStore **store);\n"
+        ));
+        assert!(header.contains("#ifdef DB_DEBUG\nDbResult db_test_ok(void);\n#endif\n"));
+    }
+
+    #[test]
+    fn generated_header_is_up_to_date() {
+        let manifest =
+            crate::read_manifest(crate::default_source(), &crate::default_dependencies()).unwrap();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../c/include/db.h");
+        let expected = fs::read_to_string(path).unwrap().replace("\r\n", "\n");
+
+        assert!(
+            expected == generate(&manifest),
+            "`db.h` is out of date, regenerate it with `cargo run -p dbc-bindgen -- c`"
+        );
+    }
+}
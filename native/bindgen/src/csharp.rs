@@ -0,0 +1,287 @@
+/*!
+Generating C# `DllImport` declarations.
+
+The declarations are a partial `Bindings` class. The rest of the class declares
+the `NativeLibrary` to import from and the `MaybeCheck` function used to check results.
+
+Pointers to buffers, which are followed by an argument with the same name and a `_len` suffix,
+and pointers to keys are passed as `IntPtr`. Handles that are freed by a function are also
+passed as `IntPtr`, because they're released by their `SafeHandle`.
+*/
+
+use std::fmt::Write;
+
+use crate::manifest::{
+    Arg,
+    Function,
+    Manifest,
+    Type,
+};
+
+/**
+Lines longer than this have their parameters split over multiple lines.
+*/
+const MAX_LINE_LEN: usize = 120;
+
+/**
+Generate a C# source file with `DllImport` declarations for a manifest.
+*/
+pub fn generate(manifest: &Manifest) -> String {
+    let mut out = String::new();
+
+    out.push_str(
+        "// <auto-generated>\n\
+         // Generated by dbc-bindgen from `native/c/src`. Don't edit this file by hand.\n\
+         // </auto-generated>\n\
+         \n\
+         using System;\n\
+         using System.Runtime.InteropServices;\n\
+         \n\
+         namespace Db.Storage.Native\n\
+         {\n    static partial class Bindings\n    {\n",
+    );
+
+    for constant in &manifest.constants {
+        let _ = writeln!(
+            out,
+            "        public const {} {} = {};",
+            value_type(&constant.ty),
+            constant.name,
+            constant.value
+        );
+    }
+
+    if !manifest.constants.is_empty() {
+        out.push('\n');
+    }
+
+    // Consecutive debug-only functions share a single `#if DEBUG` block
+    let mut debug_only = false;
+    for (i, function) in manifest.functions.iter().enumerate() {
+        if debug_only && !function.debug_only {
+            out.push_str("#endif\n");
+        }
+
+        if i > 0 {
+            out.push('\n');
+        }
+
+        if !debug_only && function.debug_only {
+            out.push_str("#if DEBUG\n");
+        }
+        debug_only = function.debug_only;
+
+        generate_function(&mut out, function);
+    }
+
+    if debug_only {
+        out.push_str("#endif\n");
+    }
+
+    out.push_str("    }\n}\n");
+
+    out
+}
+
+fn generate_function(out: &mut String, function: &Function) {
+    let name = &function.name;
+
+    let params: Vec<_> = (0..function.args.len())
+        .map(|i| param(&function.args, i))
+        .collect();
+
+    let decls: Vec<_> = params
+        .iter()
+        .map(|param| format!("{}{} {}", param.modifier, param.ty, param.name))
+        .collect();
+    let calls: Vec<_> = params
+        .iter()
+        .map(|param| format!("{}{}", param.modifier, param.name))
+        .collect();
+
+    let _ = writeln!(
+        out,
+        "        [DllImport(NativeLibrary, EntryPoint = \"{}\", ExactSpelling = true,",
+        name
+    );
+    out.push_str("            CallingConvention = CallingConvention.Cdecl)]\n");
+    signature(
+        out,
+        &format!("        private static extern DbResult _{}(", name),
+        &decls,
+        ");",
+    );
+    out.push('\n');
+
+    let mut public_decls = decls.clone();
+    public_decls.push("bool check = true".to_owned());
+    signature(
+        out,
+        &format!("        public static DbResult {}(", name),
+        &public_decls,
+        ")",
+    );
+    out.push_str("        {\n");
+    let _ = writeln!(
+        out,
+        "            return MaybeCheck(_{}({}), check);",
+        name,
+        calls.join(", ")
+    );
+    out.push_str("        }\n");
+}
+
+/**
+Write a signature on a single line if it fits, or with a parameter per line if it doesn't.
+*/
+fn signature(out: &mut String, prefix: &str, params: &[String], suffix: &str) {
+    let single = format!("{}{}{}", prefix, params.join(", "), suffix);
+
+    if single.len() <= MAX_LINE_LEN || params.is_empty() {
+        let _ = writeln!(out, "{}", single);
+    } else {
+        let _ = writeln!(out, "{}", prefix);
+        let _ = writeln!(
+            out,
+            "            {}{}",
+            params.join(",\n            "),
+            suffix
+        );
+    }
+}
+
+struct Param {
+    modifier: &'static str,
+    ty: String,
+    name: String,
+}
+
+fn param(args: &[Arg], i: usize) -> Param {
+    let arg = &args[i];
+
+    // Buffers are followed by their length
+    let is_buffer = args
+        .get(i + 1)
+        .map(|next| next.name == format!("{}_len", arg.name))
+        .unwrap_or(false);
+
+    let (modifier, ty) = match arg.ty {
+        _ if is_buffer || arg.releases => ("", "IntPtr".to_owned()),
+        Type::Ref { ref target } => match **target {
+            Type::Primitive { ref name } if name == "u8" => ("", "IntPtr".to_owned()),
+            Type::Named { ref name } if name == "DbKey" => ("", "IntPtr".to_owned()),
+            ref target => ("in ", value_type(target)),
+        },
+        Type::RefMut { ref target } => ("ref ", value_type(target)),
+        Type::Out { ref target } => match **target {
+            Type::Primitive { ref name } if name == "u8" => ("", "IntPtr".to_owned()),
+            ref target => ("out ", value_type(target)),
+        },
+        ref ty => ("", value_type(ty)),
+    };
+
+    Param {
+        modifier,
+        ty,
+        name: camel_case(&arg.name),
+    }
+}
+
+fn value_type(ty: &Type) -> String {
+    match ty {
+        Type::Primitive { name } => match &**name {
+            "size_t" => "UIntPtr",
+            "u8" => "byte",
+            "u16" => "ushort",
+            "u32" => "uint",
+            "u64" => "ulong",
+            "i32" => "int",
+            "i64" => "long",
+            "bool" => "bool",
+            _ => "IntPtr",
+        }
+        .to_owned(),
+        // `DbStore` is wrapped in a `StoreHandle`
+        Type::Handle { target, .. } => {
+            format!("{}Handle", target.trim_start_matches("Db"))
+        }
+        Type::Named { name } if name == "DbCallbackState" => "IntPtr".to_owned(),
+        Type::Named { name } => name.clone(),
+        Type::Ref { .. }
+        | Type::RefMut { .. }
+        | Type::Out { .. }
+        | Type::Pointer { .. }
+        | Type::Array { .. } => "IntPtr".to_owned(),
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn param_types() {
+        let manifest = Manifest::from_source(
+            r#"
+            pub type DbWriterHandle<'a> = HandleExclusive<'a, DbWriter>;
+
+            #[ffi]
+            fn db_write_set(writer: DbWriterHandle, key: Ref<DbKey>, value: Ref<u8>, value_len: size_t, removed: Out<size_t>) -> DbResult {
+                DbResult::ok()
+            }
+
+            #[ffi(releases(writer))]
+            fn db_write_end(writer: DbWriterHandle) -> DbResult {
+                DbResult::ok()
+            }
+            "#,
+        )
+        .unwrap();
+
+        let bindings = generate(&manifest);
+
+        assert!(bindings.contains(
+            "            WriterHandle writer,\n            IntPtr key,\n            IntPtr value,\n            UIntPtr valueLen,\n            out UIntPtr removed);\n"
+        ));
+        assert!(bindings
+            .contains("        private static extern DbResult _db_write_end(IntPtr writer);\n"));
+        assert!(bindings.contains("            return MaybeCheck(_db_write_end(writer), check);\n"));
+    }
+
+    #[test]
+    fn generated_bindings_are_up_to_date() {
+        let manifest =
+            crate::read_manifest(crate::default_source(), &crate::default_dependencies()).unwrap();
+
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../dotnet/Db.Storage/Native/Bindings.g.cs"
+        );
+        let expected = fs::read_to_string(path).unwrap().replace("\r\n", "\n");
+
+        assert!(
+            expected == generate(&manifest),
+            "`Bindings.g.cs` is out of date, regenerate it with `cargo run -p dbc-bindgen -- csharp`"
+        );
+    }
+}
@@ -0,0 +1,119 @@
+/*!
+Generating bindings for `dbc` from its source.
+
+Functions marked with `#[ffi]` in `dbc` are collected into a manifest that describes
+their names and arguments. The manifest can be serialized as JSON for other tools,
+and is used here to generate C# `DllImport` declarations and a C header.
+*/
+
+// `failure_derive` implements `Fail` inside an anonymous `const`
+#![allow(non_local_definitions)]
+
+pub mod c;
+pub mod csharp;
+pub mod manifest;
+
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use failure_derive::*;
+
+pub use self::manifest::Manifest;
+
+/**
+An error encountered while generating bindings.
+*/
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "failed to read or write `{}`", _0)]
+    Io(String, #[cause] io::Error),
+    #[fail(display = "failed to parse the source")]
+    Parse(#[cause] syn::Error),
+    #[fail(display = "the type of `{}` in `{}` isn't supported", name, item)]
+    UnsupportedType { item: String, name: String },
+    #[fail(display = "the arguments to `#[ffi]` on `{}` are invalid", _0)]
+    InvalidAttribute(String),
+    #[fail(display = "failed to serialize the manifest")]
+    Json(#[cause] serde_json::Error),
+}
+
+/**
+The path to the source of `dbc` in this repository.
+*/
+pub fn default_source() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../c/src")
+}
+
+/**
+The paths to the source of crates `dbc` depends on in this repository.
+*/
+pub fn default_dependencies() -> Vec<PathBuf> {
+    vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("../db/src")]
+}
+
+/**
+Read a manifest from the source directory of a crate.
+
+Functions are collected from the `lib.rs` file in the directory.
+Types are collected from any file in the directory.
+*/
+pub fn read_manifest(
+    src: impl AsRef<Path>,
+    dependencies: &[impl AsRef<Path>],
+) -> Result<Manifest, Error> {
+    let src = src.as_ref();
+
+    // Read `lib.rs` first so functions are in the order they're declared
+    let mut sources = vec![read(&src.join("lib.rs"))?];
+    for path in source_files(src)? {
+        if path != src.join("lib.rs") {
+            sources.push(read(&path)?);
+        }
+    }
+
+    let mut dependency_sources = Vec::new();
+    for dependency in dependencies {
+        for path in source_files(dependency.as_ref())? {
+            dependency_sources.push(read(&path)?);
+        }
+    }
+
+    Manifest::from_sources(
+        &sources.iter().map(|s| &**s).collect::<Vec<_>>(),
+        &dependency_sources.iter().map(|s| &**s).collect::<Vec<_>>(),
+    )
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|e| Error::Io(path.display().to_string(), e))
+}
+
+/**
+Find the Rust source files in a directory, in a stable order.
+*/
+fn source_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+
+    let entries = fs::read_dir(dir).map_err(|e| Error::Io(dir.display().to_string(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| Error::Io(dir.display().to_string(), e))?
+            .path();
+
+        if path.is_dir() {
+            files.extend(source_files(&path)?);
+        } else if path.extension().map(|ext| ext == "rs").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
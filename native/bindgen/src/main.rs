@@ -0,0 +1,75 @@
+/*!
+Generate bindings for `dbc`.
+
+Usage: `dbc-bindgen <manifest | csharp | c> [output path]`
+
+If no path is given then C# bindings are written to `dotnet/Db.Storage/Native/Bindings.g.cs`,
+the C header is written to `native/c/include/db.h`, and the manifest is written to standard output.
+*/
+
+use std::{
+    env,
+    fs,
+    path::Path,
+    process,
+};
+
+use failure::Fail;
+
+use dbc_bindgen::{
+    c,
+    csharp,
+    Error,
+};
+
+fn main() {
+    let mut args = env::args().skip(1);
+
+    let kind = args.next().unwrap_or_default();
+    let out = args.next();
+
+    if let Err(e) = run(&kind, out.as_ref().map(Path::new)) {
+        eprintln!("error: {}", e);
+        for cause in (&e as &dyn Fail).iter_causes() {
+            eprintln!("caused by: {}", cause);
+        }
+
+        process::exit(1);
+    }
+}
+
+fn run(kind: &str, out: Option<&Path>) -> Result<(), Error> {
+    let manifest = dbc_bindgen::read_manifest(
+        dbc_bindgen::default_source(),
+        &dbc_bindgen::default_dependencies(),
+    )?;
+
+    let (generated, default_out) = match kind {
+        "manifest" => (manifest.to_json()?, None),
+        "csharp" => (
+            csharp::generate(&manifest),
+            Some(
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("../../dotnet/Db.Storage/Native/Bindings.g.cs"),
+            ),
+        ),
+        "c" => (
+            c::generate(&manifest),
+            Some(Path::new(env!("CARGO_MANIFEST_DIR")).join("../c/include/db.h")),
+        ),
+        _ => {
+            eprintln!("usage: dbc-bindgen <manifest | csharp | c> [output path]");
+            process::exit(2);
+        }
+    };
+
+    match out.map(Path::to_path_buf).or(default_out) {
+        Some(out) => {
+            fs::write(&out, generated).map_err(|e| Error::Io(out.display().to_string(), e))
+        }
+        None => {
+            print!("{}", generated);
+            Ok(())
+        }
+    }
+}
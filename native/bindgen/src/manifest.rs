@@ -0,0 +1,861 @@
+/*!
+A description of the functions exported from `dbc`.
+*/
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use syn::{
+    Attribute,
+    GenericArgument,
+    Item,
+    Meta,
+    NestedMeta,
+    Pat,
+    PathArguments,
+};
+
+use crate::Error;
+
+/**
+The functions exported from `dbc`, in the order they're declared,
+and the types they use, in an order where each type is declared before it's used.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub constants: Vec<Constant>,
+    pub types: Vec<TypeDef>,
+    pub functions: Vec<Function>,
+}
+
+/**
+A public constant declared by `dbc` with a `DB_` prefix, like `DB_ABI_MAJOR`.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Constant {
+    pub name: String,
+    pub docs: Option<String>,
+    pub ty: Type,
+    pub value: u64,
+}
+
+/**
+A type declared by `dbc`.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeDef {
+    pub name: String,
+    pub docs: Option<String>,
+    pub def: Definition,
+}
+
+/**
+The definition of a type.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Definition {
+    /**
+    A type that's only accessed through a handle.
+    */
+    Opaque,
+    /**
+    A `#[repr(C)]` struct.
+    */
+    Struct { fields: Vec<Field> },
+    /**
+    A `#[repr(u32)]` enum.
+    */
+    Enum { variants: Vec<Variant> },
+    /**
+    A nullable function pointer, like `Option<extern fn(DbResult)>`.
+    */
+    Callback { args: Vec<Field> },
+    /**
+    A `#[repr(transparent)]` wrapper around another type, like a pointer.
+    */
+    Alias { ty: Type },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub value: u32,
+}
+
+/**
+An exported function.
+
+Every function returns a `DbResult`.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    /**
+    Whether the function catches panics and sets the last result.
+    */
+    pub catch: bool,
+    /**
+    Whether the function is only exported from debug builds.
+    */
+    pub debug_only: bool,
+    pub args: Vec<Arg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arg {
+    pub name: String,
+    pub ty: Type,
+    /**
+    Whether the argument is a handle that's freed by the function.
+    */
+    pub releases: bool,
+}
+
+/**
+The type of an argument.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Type {
+    /**
+    A primitive, like `size_t` or `u32`.
+    */
+    Primitive { name: String },
+    /**
+    A handle to a value owned by `dbc`, like `DbStore`.
+    */
+    Handle { target: String, exclusive: bool },
+    /**
+    A pointer to an initialized value.
+    */
+    Ref { target: Box<Type> },
+    /**
+    A pointer to an initialized value that may be written by the function.
+    */
+    RefMut { target: Box<Type> },
+    /**
+    A pointer to a value that's initialized by the function.
+    */
+    Out { target: Box<Type> },
+    /**
+    A raw pointer, like `*const u8`.
+    */
+    Pointer { target: Box<Type>, mutable: bool },
+    /**
+    A fixed-size array, like `[u8; 16]`.
+    */
+    Array { element: Box<Type>, len: usize },
+    /**
+    Some other type declared by `dbc`, like `DbKey`.
+    */
+    Named { name: String },
+}
+
+impl Manifest {
+    /**
+    Collect the `#[ffi]` functions declared in the source of a file.
+    */
+    pub fn from_source(source: &str) -> Result<Self, Error> {
+        Manifest::from_sources(&[source], &[])
+    }
+
+    /**
+    Collect the `#[ffi]` functions declared in the source of a crate, along with the types they use.
+
+    The constants in `dependencies` are used to determine the length of arrays.
+    */
+    pub fn from_sources(sources: &[&str], dependencies: &[&str]) -> Result<Self, Error> {
+        let parse = |source: &&str| syn::parse_file(source).map_err(Error::Parse);
+
+        let files = sources.iter().map(parse).collect::<Result<Vec<_>, _>>()?;
+        let dependencies = dependencies
+            .iter()
+            .map(parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let items = || files.iter().flat_map(|file| file.items.iter());
+
+        let mut cx = Context::default();
+        for item in items().chain(dependencies.iter().flat_map(|file| file.items.iter())) {
+            if let Item::Const(item) = item {
+                if let Some(value) = int(&item.expr) {
+                    cx.constants.insert(item.ident.to_string(), value);
+                }
+            }
+        }
+
+        let mut constants = Vec::new();
+        for item in items() {
+            if let Item::Const(item) = item {
+                let name = item.ident.to_string();
+
+                if !name.starts_with("DB_") || !is_pub(&item.vis) {
+                    continue;
+                }
+
+                let unsupported = || Error::UnsupportedType {
+                    item: name.clone(),
+                    name: name.clone(),
+                };
+
+                let ty = match cx.resolve(&item.ty) {
+                    Some(ty @ Type::Primitive { .. }) => ty,
+                    _ => return Err(unsupported()),
+                };
+
+                constants.push(Constant {
+                    docs: docs(&item.attrs),
+                    ty,
+                    value: int(&item.expr).ok_or_else(unsupported)?,
+                    name,
+                });
+            }
+        }
+
+        // Handle types are declared as aliases, like `DbStoreHandle<'a> = HandleShared<'a, DbStore>`
+        for item in items() {
+            if let Item::Type(alias) = item {
+                if let Some(Type::Handle { target, exclusive }) = cx.resolve_path(&alias.ty) {
+                    cx.handles
+                        .insert(alias.ident.to_string(), (target, exclusive));
+                }
+            }
+        }
+
+        let mut functions = Vec::new();
+        for item in items() {
+            if let Item::Fn(item) = item {
+                let attr = match item.attrs.iter().find(|attr| attr.path.is_ident("ffi")) {
+                    Some(attr) => attr,
+                    None => continue,
+                };
+
+                functions.push(function(item, attr, &cx)?);
+            }
+        }
+
+        let mut declared = HashMap::new();
+        for item in items() {
+            match item {
+                Item::Struct(item) if is_repr(&item.attrs) => {
+                    declared.insert(item.ident.to_string(), item as &dyn Declared);
+                }
+                Item::Enum(item) if is_repr(&item.attrs) => {
+                    declared.insert(item.ident.to_string(), item as &dyn Declared);
+                }
+                _ => (),
+            }
+        }
+
+        let mut types = Vec::new();
+        let mut visited = HashSet::new();
+        for function in &functions {
+            for arg in &function.args {
+                collect_types(&arg.ty, &declared, &cx, &mut visited, &mut types)?;
+            }
+        }
+
+        Ok(Manifest {
+            constants,
+            types,
+            functions,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(Error::Json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(Error::Json)
+    }
+}
+
+/**
+Items and constants that are used to resolve types.
+*/
+#[derive(Default)]
+struct Context {
+    handles: HashMap<String, (String, bool)>,
+    constants: HashMap<String, u64>,
+}
+
+fn function(item: &syn::ItemFn, attr: &Attribute, cx: &Context) -> Result<Function, Error> {
+    let name = item.sig.ident.to_string();
+    let invalid = || Error::InvalidAttribute(name.clone());
+
+    let mut catch = true;
+    let mut releases = Vec::new();
+    match attr.parse_meta().map_err(|_| invalid())? {
+        Meta::Path(_) => (),
+        Meta::List(list) => {
+            for arg in list.nested {
+                match arg {
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("no_catch") => {
+                        catch = false;
+                    }
+                    // Tracing options don't change how a function is called
+                    NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("no_trace") => (),
+                    NestedMeta::Meta(Meta::List(ref list)) if list.path.is_ident("lengths") => {
+                        for len in list.nested.iter() {
+                            match len {
+                                NestedMeta::Meta(Meta::Path(path)) => {
+                                    path.get_ident().ok_or_else(invalid)?;
+                                }
+                                _ => return Err(invalid()),
+                            }
+                        }
+                    }
+                    NestedMeta::Meta(Meta::List(ref list)) if list.path.is_ident("releases") => {
+                        for handle in list.nested.iter() {
+                            match handle {
+                                NestedMeta::Meta(Meta::Path(path)) => {
+                                    releases.push(path.get_ident().ok_or_else(invalid)?.to_string())
+                                }
+                                _ => return Err(invalid()),
+                            }
+                        }
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+        }
+        Meta::NameValue(_) => return Err(invalid()),
+    }
+
+    let debug_only = item.attrs.iter().any(is_debug_only);
+
+    let mut args = Vec::new();
+    for input in &item.sig.inputs {
+        let input = match input {
+            syn::FnArg::Typed(input) => input,
+            syn::FnArg::Receiver(_) => return Err(invalid()),
+        };
+
+        let arg = match *input.pat {
+            Pat::Ident(ref pat) => pat.ident.to_string(),
+            _ => return Err(invalid()),
+        };
+
+        let ty = cx
+            .resolve(&input.ty)
+            .ok_or_else(|| Error::UnsupportedType {
+                item: name.clone(),
+                name: arg.clone(),
+            })?;
+
+        args.push(Arg {
+            releases: releases.contains(&arg),
+            name: arg,
+            ty,
+        });
+    }
+
+    Ok(Function {
+        name,
+        catch,
+        debug_only,
+        args,
+    })
+}
+
+fn is_debug_only(attr: &Attribute) -> bool {
+    if !attr.path.is_ident("cfg") {
+        return false;
+    }
+
+    match attr.parse_meta() {
+        Ok(Meta::List(list)) => list.nested.iter().any(|cfg| match cfg {
+            NestedMeta::Meta(Meta::Path(path)) => path.is_ident("debug_assertions"),
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+/**
+Collect a type and the types it depends on, after those dependencies.
+*/
+fn collect_types(
+    ty: &Type,
+    declared: &HashMap<String, &dyn Declared>,
+    cx: &Context,
+    visited: &mut HashSet<String>,
+    types: &mut Vec<TypeDef>,
+) -> Result<(), Error> {
+    let name = match ty {
+        Type::Primitive { .. } => return Ok(()),
+        Type::Ref { target }
+        | Type::RefMut { target }
+        | Type::Out { target }
+        | Type::Pointer { target, .. } => {
+            return collect_types(target, declared, cx, visited, types)
+        }
+        Type::Array { element, .. } => return collect_types(element, declared, cx, visited, types),
+        Type::Handle { target, .. } => {
+            if visited.insert(target.clone()) {
+                types.push(TypeDef {
+                    name: target.clone(),
+                    docs: declared.get(target).and_then(|item| docs(item.attrs())),
+                    def: Definition::Opaque,
+                });
+            }
+
+            return Ok(());
+        }
+        Type::Named { name } => name,
+    };
+
+    if !visited.insert(name.clone()) {
+        return Ok(());
+    }
+
+    let (docs, def) = match declared.get(name) {
+        Some(item) => (docs(item.attrs()), item.definition(cx)?),
+        None => (None, Definition::Opaque),
+    };
+
+    match &def {
+        Definition::Struct { fields } | Definition::Callback { args: fields } => {
+            for field in fields {
+                collect_types(&field.ty, declared, cx, visited, types)?;
+            }
+        }
+        Definition::Alias { ty } => collect_types(ty, declared, cx, visited, types)?,
+        Definition::Opaque | Definition::Enum { .. } => (),
+    }
+
+    types.push(TypeDef {
+        name: name.clone(),
+        docs,
+        def,
+    });
+
+    Ok(())
+}
+
+/**
+A struct or enum that might be used at the FFI boundary.
+*/
+trait Declared {
+    fn attrs(&self) -> &[Attribute];
+    fn definition(&self, cx: &Context) -> Result<Definition, Error>;
+}
+
+impl Declared for syn::ItemStruct {
+    fn attrs(&self) -> &[Attribute] {
+        &self.attrs
+    }
+
+    fn definition(&self, cx: &Context) -> Result<Definition, Error> {
+        let name = self.ident.to_string();
+
+        let mut fields = Vec::new();
+        for (i, field) in self.fields.iter().enumerate() {
+            // Skip markers like `PhantomData`
+            if let syn::Type::Path(path) = &field.ty {
+                if path
+                    .path
+                    .segments
+                    .last()
+                    .map(|s| s.ident == "PhantomData")
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+            }
+
+            let field_name = field
+                .ident
+                .as_ref()
+                .map(|ident| ident.to_string())
+                .unwrap_or_else(|| i.to_string());
+
+            let unsupported = || Error::UnsupportedType {
+                item: name.clone(),
+                name: field_name.clone(),
+            };
+
+            // `Option<extern fn()>` is a nullable function pointer
+            if let Some(callback) = bare_fn(&field.ty) {
+                let mut args = Vec::new();
+                for (i, arg) in callback.inputs.iter().enumerate() {
+                    args.push(Field {
+                        name: arg
+                            .name
+                            .as_ref()
+                            .map(|(ident, _)| ident.to_string())
+                            .unwrap_or_else(|| format!("arg{}", i)),
+                        ty: cx.resolve(&arg.ty).ok_or_else(unsupported)?,
+                    });
+                }
+
+                if let syn::ReturnType::Type(..) = callback.output {
+                    return Err(unsupported());
+                }
+
+                return Ok(Definition::Callback { args });
+            }
+
+            fields.push(Field {
+                ty: cx.resolve(&field.ty).ok_or_else(unsupported)?,
+                name: field_name,
+            });
+        }
+
+        // Transparent wrappers around arrays are declared as structs so they can be passed by value
+        let transparent = self
+            .attrs
+            .iter()
+            .any(|attr| repr(attr).as_deref() == Some("transparent"));
+        match (transparent, fields.len()) {
+            (true, 1) => match fields[0].ty {
+                Type::Array { .. } => {
+                    fields[0].name = "value".to_owned();
+                    Ok(Definition::Struct { fields })
+                }
+                _ => Ok(Definition::Alias {
+                    ty: fields.remove(0).ty,
+                }),
+            },
+            _ => Ok(Definition::Struct { fields }),
+        }
+    }
+}
+
+impl Declared for syn::ItemEnum {
+    fn attrs(&self) -> &[Attribute] {
+        &self.attrs
+    }
+
+    fn definition(&self, _: &Context) -> Result<Definition, Error> {
+        let mut variants = Vec::new();
+        let mut next = 0;
+        for variant in &self.variants {
+            let value = match &variant.discriminant {
+                Some((_, expr)) => int(expr).ok_or_else(|| Error::UnsupportedType {
+                    item: self.ident.to_string(),
+                    name: variant.ident.to_string(),
+                })? as u32,
+                None => next,
+            };
+
+            variants.push(Variant {
+                name: variant.ident.to_string(),
+                value,
+            });
+
+            next = value + 1;
+        }
+
+        Ok(Definition::Enum { variants })
+    }
+}
+
+/**
+Get the function pointer in an `Option<extern fn()>`.
+*/
+fn bare_fn(ty: &syn::Type) -> Option<&syn::TypeBareFn> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(syn::Type::BareFn(f)) => Some(f),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/**
+Whether an item has a representation that's stable across the FFI boundary.
+*/
+fn is_repr(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter_map(repr)
+        .any(|repr| repr == "C" || repr == "transparent" || repr == "u32")
+}
+
+fn repr(attr: &Attribute) -> Option<String> {
+    if !attr.path.is_ident("repr") {
+        return None;
+    }
+
+    match attr.parse_meta() {
+        Ok(Meta::List(list)) => list.nested.iter().find_map(|repr| match repr {
+            NestedMeta::Meta(Meta::Path(path)) => path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/**
+The text of the doc comments on an item, without any common indentation.
+*/
+fn docs(attrs: &[Attribute]) -> Option<String> {
+    let mut text = String::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("doc")) {
+        if let Ok(Meta::NameValue(syn::MetaNameValue {
+            lit: syn::Lit::Str(doc),
+            ..
+        })) = attr.parse_meta()
+        {
+            text.push_str(&doc.value());
+            text.push('\n');
+        }
+    }
+
+    let lines: Vec<_> = text.lines().collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()?;
+
+    let docs = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(docs.trim_matches('\n').to_owned())
+}
+
+/**
+Evaluate an integer literal, or a literal shifted by another, like `1 << 2`.
+*/
+fn int(expr: &syn::Expr) -> Option<u64> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(lit),
+            ..
+        }) => lit.base10_parse().ok(),
+        syn::Expr::Binary(syn::ExprBinary {
+            left,
+            op: syn::BinOp::Shl(_),
+            right,
+            ..
+        }) => int(left)?.checked_shl(int(right)? as u32),
+        syn::Expr::Paren(expr) => int(&expr.expr),
+        _ => None,
+    }
+}
+
+fn is_pub(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+impl Context {
+    fn resolve(&self, ty: &syn::Type) -> Option<Type> {
+        match ty {
+            syn::Type::Path(_) => self.resolve_path(ty),
+            syn::Type::Paren(ty) => self.resolve(&ty.elem),
+            syn::Type::Ptr(ptr) => Some(Type::Pointer {
+                target: Box::new(self.resolve(&ptr.elem)?),
+                mutable: ptr.mutability.is_some(),
+            }),
+            syn::Type::Array(array) => {
+                let len = match &array.len {
+                    syn::Expr::Path(path) => *self
+                        .constants
+                        .get(&path.path.segments.last()?.ident.to_string())?,
+                    len => int(len)?,
+                } as usize;
+
+                Some(Type::Array {
+                    element: Box::new(self.resolve(&array.elem)?),
+                    len,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve_path(&self, ty: &syn::Type) -> Option<Type> {
+        let path = match ty {
+            syn::Type::Path(path) if path.qself.is_none() => &path.path,
+            _ => return None,
+        };
+
+        let segment = path.segments.last()?;
+        let ident = segment.ident.to_string();
+
+        // The first type argument, ignoring lifetimes
+        let target = || match segment.arguments {
+            PathArguments::AngleBracketed(ref args) => args.args.iter().find_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }),
+            _ => None,
+        };
+
+        let named = |ty: &syn::Type| match ty {
+            syn::Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+
+        Some(match &*ident {
+            "size_t" | "usize" => Type::Primitive {
+                name: "size_t".to_owned(),
+            },
+            "u8" | "u16" | "u32" | "u64" | "i32" | "i64" | "bool" => {
+                Type::Primitive { name: ident }
+            }
+            "c_void" => Type::Primitive {
+                name: "void".to_owned(),
+            },
+            "Ref" => Type::Ref {
+                target: Box::new(self.resolve(target()?)?),
+            },
+            "RefMut" => Type::RefMut {
+                target: Box::new(self.resolve(target()?)?),
+            },
+            "Out" => Type::Out {
+                target: Box::new(self.resolve(target()?)?),
+            },
+            "HandleShared" | "HandleExclusive" => Type::Handle {
+                target: named(target()?)?,
+                exclusive: ident == "HandleExclusive",
+            },
+            _ => match self.handles.get(&ident) {
+                Some((target, exclusive)) => Type::Handle {
+                    target: target.clone(),
+                    exclusive: *exclusive,
+                },
+                None => Type::Named { name: ident },
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+        pub type DbStoreHandle<'a> = HandleShared<'a, DbStore>;
+
+        #[ffi(no_catch)]
+        fn db_last_result(message_buf: Out<u8>, message_buf_len: size_t) -> DbResult {
+            DbResult::ok()
+        }
+
+        #[ffi(releases(store))]
+        fn db_store_close(store: DbStoreHandle) -> DbResult {
+            DbResult::ok()
+        }
+
+        #[cfg(debug_assertions)]
+        #[ffi]
+        fn db_test_ok() -> DbResult {
+            DbResult::ok()
+        }
+
+        fn not_exported() {}
+    "#;
+
+    #[test]
+    fn collect_functions() {
+        let manifest = Manifest::from_source(SOURCE).unwrap();
+
+        let names: Vec<_> = manifest.functions.iter().map(|f| &*f.name).collect();
+        assert_eq!(
+            vec!["db_last_result", "db_store_close", "db_test_ok"],
+            names
+        );
+
+        let last_result = &manifest.functions[0];
+        assert!(!last_result.catch);
+        assert_eq!(
+            Type::Out {
+                target: Box::new(Type::Primitive {
+                    name: "u8".to_owned()
+                })
+            },
+            last_result.args[0].ty
+        );
+
+        let close = &manifest.functions[1];
+        assert!(close.catch);
+        assert!(close.args[0].releases);
+        assert_eq!(
+            Type::Handle {
+                target: "DbStore".to_owned(),
+                exclusive: false
+            },
+            close.args[0].ty
+        );
+
+        assert!(manifest.functions[2].debug_only);
+    }
+
+    #[test]
+    fn no_trace_functions_are_caught() {
+        let manifest = Manifest::from_source(
+            r#"
+            #[ffi(no_trace)]
+            fn db_trace_enable(capacity: size_t) -> DbResult {
+                DbResult::ok()
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert!(manifest.functions[0].catch);
+    }
+
+    #[test]
+    fn lengths_dont_change_functions() {
+        let manifest = Manifest::from_source(
+            r#"
+            #[ffi(lengths(path_len))]
+            fn db_store_open(path: Ref<u8>, path_len: size_t) -> DbResult {
+                DbResult::ok()
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert!(manifest.functions[0].catch);
+        assert_eq!(2, manifest.functions[0].args.len());
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let manifest = Manifest::from_source(SOURCE).unwrap();
+
+        let json = manifest.to_json().unwrap();
+        assert!(json.contains(r#""kind": "handle""#));
+
+        assert_eq!(manifest, Manifest::from_json(&json).unwrap());
+    }
+
+    #[test]
+    fn invalid_attribute() {
+        let source = "#[ffi(catch_everything)] fn db_f() -> DbResult { DbResult::ok() }";
+
+        assert!(Manifest::from_source(source).is_err());
+    }
+}
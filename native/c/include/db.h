@@ -0,0 +1,442 @@
+/* Generated by dbc-bindgen from `native/c/src`. Don't edit this file by hand. */
+
+#ifndef DB_H
+#define DB_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+The major version of the interface.
+
+This changes whenever a function or struct changes in a way that isn't compatible
+with callers built against a previous version. Callers must match it exactly.
+*/
+#define DB_ABI_MAJOR UINT32_C(1)
+
+/*
+The minor version of the interface.
+
+This changes whenever functions, result kinds, capabilities, or fields at the end of a versioned struct are added.
+Callers must be built against a minor version that's less than or equal to this one.
+*/
+#define DB_ABI_MINOR UINT32_C(3)
+
+/*
+Payloads can be checksummed with `DbStoreOptions.checksums`.
+*/
+#define DB_CAPABILITY_CHECKSUMS UINT64_C(1)
+
+/*
+Payloads can be compressed with `DbStoreOptions.compression`.
+*/
+#define DB_CAPABILITY_COMPRESSION UINT64_C(2)
+
+/*
+Payloads can be encrypted with `DbStoreOptions.encryption_keys`, and rewritten with the active key with `db_store_reencrypt`.
+*/
+#define DB_CAPABILITY_ENCRYPTION UINT64_C(4)
+
+/*
+Secondary indexes can be maintained with `DbStoreOptions.indexes` and queried with `db_index_query`.
+*/
+#define DB_CAPABILITY_INDEXES UINT64_C(8)
+
+/*
+Reads can be filtered with `db_read_begin_with_filter`.
+*/
+#define DB_CAPABILITY_FILTERS UINT64_C(16)
+
+/*
+Payloads can be validated with `DbStoreOptions.validate_json` and `DbStoreOptions.schema`.
+*/
+#define DB_CAPABILITY_VALIDATION UINT64_C(32)
+
+/*
+Writers and deleters can be ended asynchronously with `db_write_end_async` and `db_delete_end_async`.
+*/
+#define DB_CAPABILITY_ASYNC_COMPLETION UINT64_C(64)
+
+/*
+Writers can be given a durability with `db_write_begin_with_options`.
+*/
+#define DB_CAPABILITY_DURABILITY UINT64_C(128)
+
+/*
+Ranges of keys can be deleted with `db_delete_range`.
+*/
+#define DB_CAPABILITY_RANGE_DELETES UINT64_C(256)
+
+/*
+Errors have causes, backtraces, and fields, and can be retained with `db_set_error_retention`.
+*/
+#define DB_CAPABILITY_ERROR_DETAILS UINT64_C(512)
+
+/*
+Log events can be received with `db_set_log_callback`.
+*/
+#define DB_CAPABILITY_LOGGING UINT64_C(1024)
+
+/*
+Calls can be traced with `db_trace_enable`.
+*/
+#define DB_CAPABILITY_TRACING UINT64_C(2048)
+
+/*
+Operations on a store can be counted with `db_store_metrics`.
+*/
+#define DB_CAPABILITY_METRICS UINT64_C(4096)
+
+/*
+The library is a debug build that exports test functions, like `db_test_ok`.
+*/
+#define DB_CAPABILITY_DEBUG UINT64_C(8192)
+
+/*
+Handles are checked, so using a handle after it's been released returns `DB_KIND_INVALID_HANDLE`.
+*/
+#define DB_CAPABILITY_CHECKED_HANDLES UINT64_C(16384)
+
+/*
+The kind of a result.
+
+The values of kinds are stable, new kinds are only ever added to the end.
+*/
+typedef uint32_t DbKind;
+#define DB_KIND_OK 0
+#define DB_KIND_DONE 1
+#define DB_KIND_BUFFER_TOO_SMALL 2
+#define DB_KIND_ARGUMENT_NULL 3
+#define DB_KIND_INTERNAL_ERROR 4
+#define DB_KIND_CORRUPTED 5
+#define DB_KIND_DECRYPTION_FAILED 6
+#define DB_KIND_VALIDATION_FAILED 7
+#define DB_KIND_IO 8
+#define DB_KIND_INVALID_UTF8 9
+#define DB_KIND_KEY_TOO_LONG 10
+#define DB_KIND_INVALID_FILTER 11
+#define DB_KIND_INVALID_INDEX 12
+#define DB_KIND_UNKNOWN_INDEX 13
+#define DB_KIND_INVALID_SCHEMA 14
+#define DB_KIND_INVALID_ARGUMENT 15
+#define DB_KIND_INVALID_HANDLE 16
+#define DB_KIND_REMOVED_UNREADABLE 17
+
+/*
+The result of making a call across an FFI boundary.
+
+The result may indicate success or an error.
+If an error is returned, the thread-local `last_result` can be inspected for more details.
+*/
+typedef struct DbResult {
+    DbKind kind;
+    uint32_t id;
+} DbResult;
+
+/*
+A completed FFI call.
+
+The name is a static string that remains valid for the life of the process.
+
+`size` must be set to the size of the struct in the first span of a buffer before it's written.
+*/
+typedef struct DbSpan {
+    uint32_t size;
+    const uint8_t *name;
+    size_t name_len;
+    uint64_t start_unix_micros;
+    uint64_t duration_nanos;
+    uint64_t arg_bytes;
+    DbResult result;
+} DbSpan;
+
+typedef struct DbKey {
+    uint8_t value[16];
+} DbKey;
+
+/*
+Machine-readable fields from the last error.
+
+`key` is only set if `has_key` is `true`, and `os_error` is only set if `has_os_error` is `true`.
+
+`size` must be set to the size of the struct before it's written.
+*/
+typedef struct DbErrorFields {
+    uint32_t size;
+    DbResult result;
+    bool has_key;
+    DbKey key;
+    bool has_os_error;
+    int32_t os_error;
+} DbErrorFields;
+
+/*
+A function called with log events.
+
+Levels are `1` for errors, `2` for warnings, `3` for info, `4` for debug, and `5` for trace.
+The target and message are UTF8 and are only valid for the duration of the call.
+The callback may be called from any thread, including threads owned by this library.
+*/
+typedef void (*DbLogCallback)(
+    uint32_t level,
+    const uint8_t *target,
+    size_t target_len,
+    const uint8_t *message,
+    size_t message_len);
+
+typedef struct DbStore DbStore;
+
+/*
+A key used to encrypt payloads.
+*/
+typedef struct DbEncryptionKey {
+    uint32_t id;
+    uint8_t key[32];
+} DbEncryptionKey;
+
+/*
+A secondary index over a field in JSON payloads.
+*/
+typedef struct DbIndex {
+    const uint8_t *name;
+    size_t name_len;
+    const uint8_t *path;
+    size_t path_len;
+} DbIndex;
+
+/*
+Options for opening a store.
+
+`compression` is `0` for no compression and `1` for LZ4.
+
+If `encryption_keys_len` is `0` then payloads aren't encrypted.
+Otherwise, the key with the id `active_encryption_key` is used to encrypt new payloads
+and any other keys are only used to decrypt existing ones.
+
+If `indexes_len` is `0` then no indexes are maintained.
+
+`checksums` and `validate_json` are flags that must be `0` or `1`.
+
+If `validate_json` is `1` then payloads must be well-formed JSON to be written.
+If `schema_len` is also non-zero then payloads must match the JSON Schema in `schema`.
+
+`size` must be set to the size of the struct.
+*/
+typedef struct DbStoreOptions {
+    uint32_t size;
+    uint8_t checksums;
+    uint32_t compression;
+    const DbEncryptionKey *encryption_keys;
+    size_t encryption_keys_len;
+    uint32_t active_encryption_key;
+    const DbIndex *indexes;
+    size_t indexes_len;
+    uint8_t validate_json;
+    const uint8_t *schema;
+    size_t schema_len;
+} DbStoreOptions;
+
+/*
+A snapshot of the operations made on a store.
+
+`errors` is indexed by the position of the error kind: other, io, corrupted,
+decryption failed, validation failed, key too long, invalid filter, invalid index,
+unknown index, invalid schema, invalid argument.
+`error_kinds` is the number of those kinds that are counted.
+New kinds are only ever added to the end, and the rest of `errors` is zero.
+
+`flush_latency_buckets` counts flushes by how long they took, in microseconds,
+up to 100, 500, 1000, 5000, 10000, 50000, 100000, 500000, 1000000, and the rest.
+
+`size` must be set to the size of the struct before it's written.
+*/
+typedef struct DbStoreMetrics {
+    uint32_t size;
+    uint64_t reads;
+    uint64_t writes;
+    uint64_t deletes;
+    uint64_t bytes_read;
+    uint64_t bytes_written;
+    uint64_t flushes;
+    uint64_t flush_requests;
+    uint64_t flush_largest_batch;
+    uint64_t flush_batch_sizes[8];
+    uint64_t flush_latency_count;
+    uint64_t flush_latency_sum_micros;
+    uint64_t flush_latency_buckets[10];
+    uint32_t error_kinds;
+    uint64_t errors[32];
+} DbStoreMetrics;
+
+typedef struct DbReader DbReader;
+
+typedef struct DbWriter DbWriter;
+
+/*
+Options for beginning a writer.
+
+`durability` is one of:
+
+- `0` to never explicitly flush.
+- `1` to flush when the writer is ended.
+- `2` to flush after every write.
+- `3` to flush within `group_commit_ms` of the writer being ended, sharing the flush with other writers.
+  Ending the writer waits for the flush.
+
+`size` must be set to the size of the struct.
+*/
+typedef struct DbWriteOptions {
+    uint32_t size;
+    uint32_t durability;
+    uint32_t group_commit_ms;
+} DbWriteOptions;
+
+/*
+An opaque value that's passed back to a callback as-is.
+*/
+typedef void *DbCallbackState;
+
+/*
+A function called with the result of an asynchronous operation.
+*/
+typedef void (*DbCallback)(DbResult result, DbCallbackState state);
+
+/*
+A deleter that removes records from a store.
+
+When `db_delete_remove_returning` returns `DB_KIND_BUFFER_TOO_SMALL` the record has already been removed.
+Its payload is only held for a retry with the same key that comes straight after.
+Any other call on the deleter discards it.
+
+When a record is removed but its payload can't be read `DB_KIND_REMOVED_UNREADABLE` is returned.
+*/
+typedef struct DbDeleter DbDeleter;
+
+DbResult db_abi_version(uint32_t *major, uint32_t *minor);
+
+DbResult db_capabilities(uint64_t *flags);
+
+DbResult db_last_result(
+    uint8_t *message_buf,
+    size_t message_buf_len,
+    size_t *actual_message_len,
+    DbResult *result);
+
+DbResult db_last_result_cause(
+    size_t index,
+    uint8_t *message_buf,
+    size_t message_buf_len,
+    size_t *actual_message_len);
+
+DbResult db_last_result_backtrace(
+    uint8_t *message_buf,
+    size_t message_buf_len,
+    size_t *actual_message_len);
+
+DbResult db_result_details(
+    uint32_t id,
+    uint8_t *message_buf,
+    size_t message_buf_len,
+    size_t *actual_message_len);
+
+DbResult db_set_error_retention(size_t retention);
+
+DbResult db_trace_enable(size_t capacity);
+
+DbResult db_trace_drain(DbSpan *spans, size_t spans_len, size_t *drained, uint64_t *dropped);
+
+DbResult db_last_result_fields(DbErrorFields *fields);
+
+DbResult db_set_log_callback(DbLogCallback callback, uint32_t max_level);
+
+DbResult db_store_open(const uint8_t *path, size_t path_len, DbStore **store);
+
+DbResult db_store_open_with_options(
+    const uint8_t *path,
+    size_t path_len,
+    const DbStoreOptions *options,
+    DbStore **store);
+
+DbResult db_store_close(DbStore *store);
+
+DbResult db_store_metrics(DbStore *store, DbStoreMetrics *metrics);
+
+DbResult db_store_metrics_prometheus(
+    DbStore *store,
+    uint8_t *text_buf,
+    size_t text_buf_len,
+    size_t *actual_text_len);
+
+DbResult db_store_reencrypt(DbStore *store, size_t *reencrypted, size_t *skipped);
+
+DbResult db_read_begin(DbStore *store, DbReader **reader);
+
+DbResult db_read_begin_with_filter(
+    DbStore *store,
+    const uint8_t *filter,
+    size_t filter_len,
+    DbReader **reader);
+
+DbResult db_index_query(
+    DbStore *store,
+    const uint8_t *index,
+    size_t index_len,
+    const uint8_t *value,
+    size_t value_len,
+    DbReader **reader);
+
+DbResult db_read_next(
+    DbReader *reader,
+    DbKey *key,
+    uint8_t *value_buf,
+    size_t value_buf_len,
+    size_t *actual_value_len);
+
+DbResult db_read_end(DbReader *reader);
+
+DbResult db_write_begin(DbStore *store, DbWriter **writer);
+
+DbResult db_write_begin_with_options(
+    DbStore *store,
+    const DbWriteOptions *options,
+    DbWriter **writer);
+
+DbResult db_write_set(DbWriter *writer, const DbKey *key, const uint8_t *value, size_t value_len);
+
+DbResult db_write_end(DbWriter *writer);
+
+DbResult db_write_end_async(DbWriter *writer, DbCallback callback, DbCallbackState state);
+
+DbResult db_delete_begin(DbStore *store, DbDeleter **deleter);
+
+DbResult db_delete_remove(DbDeleter *deleter, const DbKey *key);
+
+DbResult db_delete_remove_returning(
+    DbDeleter *deleter,
+    const DbKey *key,
+    uint8_t *value_buf,
+    size_t value_buf_len,
+    size_t *actual_value_len);
+
+DbResult db_delete_range(DbDeleter *deleter, const DbKey *start, const DbKey *end, size_t *removed);
+
+DbResult db_delete_end(DbDeleter *deleter);
+
+DbResult db_delete_end_async(DbDeleter *deleter, DbCallback callback, DbCallbackState state);
+
+#ifdef DB_DEBUG
+DbResult db_test_error(void);
+
+DbResult db_test_ok(void);
+#endif
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
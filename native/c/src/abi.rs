@@ -0,0 +1,129 @@
+/*!
+Versioning the interface exported by this library.
+
+Callers are built against a particular version of the interface and check it
+against the library they load before calling any other functions.
+The `DB_` constants here are included in generated bindings.
+*/
+
+/**
+The major version of the interface.
+
+This changes whenever a function or struct changes in a way that isn't compatible
+with callers built against a previous version. Callers must match it exactly.
+*/
+pub const DB_ABI_MAJOR: u32 = 1;
+
+/**
+The minor version of the interface.
+
+This changes whenever functions, result kinds, capabilities, or fields at the end of a versioned struct are added.
+Callers must be built against a minor version that's less than or equal to this one.
+*/
+pub const DB_ABI_MINOR: u32 = 3;
+
+/**
+Payloads can be checksummed with `DbStoreOptions.checksums`.
+*/
+pub const DB_CAPABILITY_CHECKSUMS: u64 = 1 << 0;
+
+/**
+Payloads can be compressed with `DbStoreOptions.compression`.
+*/
+pub const DB_CAPABILITY_COMPRESSION: u64 = 1 << 1;
+
+/**
+Payloads can be encrypted with `DbStoreOptions.encryption_keys`, and rewritten with the active key with `db_store_reencrypt`.
+*/
+pub const DB_CAPABILITY_ENCRYPTION: u64 = 1 << 2;
+
+/**
+Secondary indexes can be maintained with `DbStoreOptions.indexes` and queried with `db_index_query`.
+*/
+pub const DB_CAPABILITY_INDEXES: u64 = 1 << 3;
+
+/**
+Reads can be filtered with `db_read_begin_with_filter`.
+*/
+pub const DB_CAPABILITY_FILTERS: u64 = 1 << 4;
+
+/**
+Payloads can be validated with `DbStoreOptions.validate_json` and `DbStoreOptions.schema`.
+*/
+pub const DB_CAPABILITY_VALIDATION: u64 = 1 << 5;
+
+/**
+Writers and deleters can be ended asynchronously with `db_write_end_async` and `db_delete_end_async`.
+*/
+pub const DB_CAPABILITY_ASYNC_COMPLETION: u64 = 1 << 6;
+
+/**
+Writers can be given a durability with `db_write_begin_with_options`.
+*/
+pub const DB_CAPABILITY_DURABILITY: u64 = 1 << 7;
+
+/**
+Ranges of keys can be deleted with `db_delete_range`.
+*/
+pub const DB_CAPABILITY_RANGE_DELETES: u64 = 1 << 8;
+
+/**
+Errors have causes, backtraces, and fields, and can be retained with `db_set_error_retention`.
+*/
+pub const DB_CAPABILITY_ERROR_DETAILS: u64 = 1 << 9;
+
+/**
+Log events can be received with `db_set_log_callback`.
+*/
+pub const DB_CAPABILITY_LOGGING: u64 = 1 << 10;
+
+/**
+Calls can be traced with `db_trace_enable`.
+*/
+pub const DB_CAPABILITY_TRACING: u64 = 1 << 11;
+
+/**
+Operations on a store can be counted with `db_store_metrics`.
+*/
+pub const DB_CAPABILITY_METRICS: u64 = 1 << 12;
+
+/**
+The library is a debug build that exports test functions, like `db_test_ok`.
+*/
+pub const DB_CAPABILITY_DEBUG: u64 = 1 << 13;
+
+/**
+Handles are checked, so using a handle after it's been released returns `DB_KIND_INVALID_HANDLE`.
+*/
+pub const DB_CAPABILITY_CHECKED_HANDLES: u64 = 1 << 14;
+
+/**
+The capabilities of this build of the library.
+*/
+pub(super) fn capabilities() -> u64 {
+    let capabilities = DB_CAPABILITY_CHECKSUMS
+        | DB_CAPABILITY_COMPRESSION
+        | DB_CAPABILITY_ENCRYPTION
+        | DB_CAPABILITY_INDEXES
+        | DB_CAPABILITY_FILTERS
+        | DB_CAPABILITY_VALIDATION
+        | DB_CAPABILITY_ASYNC_COMPLETION
+        | DB_CAPABILITY_DURABILITY
+        | DB_CAPABILITY_RANGE_DELETES
+        | DB_CAPABILITY_ERROR_DETAILS
+        | DB_CAPABILITY_LOGGING
+        | DB_CAPABILITY_TRACING
+        | DB_CAPABILITY_METRICS;
+
+    let capabilities = if cfg!(debug_assertions) {
+        capabilities | DB_CAPABILITY_DEBUG
+    } else {
+        capabilities
+    };
+
+    if cfg!(any(debug_assertions, feature = "checked-handles")) {
+        capabilities | DB_CAPABILITY_CHECKED_HANDLES
+    } else {
+        capabilities
+    }
+}
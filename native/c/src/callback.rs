@@ -0,0 +1,136 @@
+/*!
+Completing operations on a worker pool and reporting their results through callbacks.
+
+Callbacks are invoked on a worker thread owned by this library.
+The thread-local last result is set on that thread before the callback is invoked,
+so `db_last_result` can be called from within the callback to get more details about an error.
+
+If an operation can't be sent to a worker then it's completed on the calling thread instead.
+That way, once a handle has been released, its callback is always invoked.
+*/
+
+use std::{
+    os::raw::c_void,
+    panic::UnwindSafe,
+    thread,
+};
+
+use crate::{
+    is_null::IsNull,
+    DbResult,
+};
+
+/**
+The number of threads that complete operations.
+*/
+const WORKER_THREADS: usize = 4;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+lazy_static! {
+    static ref WORKERS: crossbeam_channel::Sender<Job> = {
+        let (jobs, queue) = crossbeam_channel::unbounded::<Job>();
+
+        // If no workers can be spawned then every receiver is dropped,
+        // so jobs fail to send and are completed on the calling thread
+        for i in 0..WORKER_THREADS {
+            let queue = queue.clone();
+
+            let _ = thread::Builder::new()
+                .name(format!("dbc-complete-{}", i))
+                .spawn(move || {
+                    for job in queue {
+                        job();
+                    }
+                });
+        }
+
+        jobs
+    };
+}
+
+/**
+A function called with the result of an asynchronous operation.
+*/
+#[repr(transparent)]
+pub struct DbCallback(Option<extern "C" fn(result: DbResult, state: DbCallbackState)>);
+
+/**
+An opaque value that's passed back to a callback as-is.
+*/
+#[repr(transparent)]
+pub struct DbCallbackState(*mut c_void);
+
+unsafe_impl!("The state is never dereferenced, only passed back to the caller" => impl Send for DbCallbackState {});
+
+impl IsNull for DbCallback {
+    fn is_null(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+// The state is allowed to be null
+impl IsNull for DbCallbackState {
+    fn is_null(&self) -> bool {
+        false
+    }
+}
+
+/**
+Run an operation on the worker pool, and invoke the callback with its result.
+*/
+pub(super) fn complete(
+    callback: DbCallback,
+    state: DbCallbackState,
+    f: impl FnOnce() -> DbResult + Send + UnwindSafe + 'static,
+) -> DbResult {
+    let callback = match callback.0 {
+        Some(callback) => callback,
+        None => return DbResult::argument_null(),
+    };
+
+    let job = Box::new(move || {
+        let result = DbResult::catch(f);
+
+        callback(result, state);
+    });
+
+    if let Err(crossbeam_channel::SendError(job)) = WORKERS.send(job) {
+        job();
+    }
+
+    DbResult::ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+
+    extern "C" fn send_result(result: DbResult, state: DbCallbackState) {
+        let tx = unsafe_block!("The state is a boxed sender" => Box::from_raw(state.0 as *mut mpsc::Sender<DbResult>));
+
+        tx.send(result).unwrap();
+    }
+
+    fn complete_with(f: impl FnOnce() -> DbResult + Send + UnwindSafe + 'static) -> DbResult {
+        let (tx, rx) = mpsc::channel();
+        let state = DbCallbackState(Box::into_raw(Box::new(tx)) as *mut c_void);
+
+        assert!(complete(DbCallback(Some(send_result)), state, f).is_ok());
+
+        rx.recv().unwrap()
+    }
+
+    #[test]
+    fn complete_ok() {
+        assert!(complete_with(DbResult::ok).is_ok());
+    }
+
+    #[test]
+    fn complete_err() {
+        assert!(complete_with(DbResult::buffer_too_small).is_buffer_too_small());
+        assert!(complete_with(|| panic!("something didn't work")).is_internal_error());
+    }
+}
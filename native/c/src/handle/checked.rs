@@ -0,0 +1,219 @@
+/*!
+Handles that are ids in a global table instead of raw pointers.
+
+Each slot in the table has a generation that's incremented whenever its value is removed.
+An id combines the index of a slot with its generation at the time the value was inserted,
+so handles that have already been released, or that were never allocated, are detected
+instead of being dereferenced. Slots also remember the type of their value, so a handle
+can't be passed where a handle to a different type is expected.
+
+This doesn't protect from a handle being released on one thread while it's still being
+used on another.
+*/
+
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    mem,
+    sync::Mutex,
+};
+
+use super::Error;
+
+// The low bits of an id are the index of its slot and the high bits are its generation
+const INDEX_BITS: usize = mem::size_of::<usize>() * 4;
+const INDEX_MASK: usize = (1 << INDEX_BITS) - 1;
+const MAX_GENERATION: usize = usize::MAX >> INDEX_BITS;
+
+struct Slot {
+    // Generations start at `1` so ids are never `0`
+    generation: usize,
+    value: Option<(TypeId, *mut ())>,
+}
+
+struct Table {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+}
+
+unsafe_impl!("Pointers in the table are only dereferenced through their handles" => impl Send for Table {});
+
+lazy_static! {
+    static ref TABLE: Mutex<Table> = Mutex::new(Table {
+        slots: Vec::new(),
+        free: Vec::new(),
+    });
+}
+
+impl Table {
+    fn insert(&mut self, ty: TypeId, ptr: *mut ()) -> usize {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                assert!(self.slots.len() <= INDEX_MASK, "the handle table is full");
+
+                self.slots.push(Slot {
+                    generation: 1,
+                    value: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+
+        let slot = &mut self.slots[index];
+        slot.value = Some((ty, ptr));
+
+        slot.generation << INDEX_BITS | index
+    }
+
+    fn get(&self, id: usize, ty: TypeId) -> Result<*mut (), Error> {
+        let (index, generation) = (id & INDEX_MASK, id >> INDEX_BITS);
+
+        match self.slots.get(index) {
+            Some(Slot {
+                generation: current,
+                value: Some((current_ty, ptr)),
+            }) if *current == generation && *current_ty == ty => Ok(*ptr),
+            _ => Err(Error::InvalidHandle(id)),
+        }
+    }
+
+    fn remove(&mut self, id: usize, ty: TypeId) -> Result<*mut (), Error> {
+        let ptr = self.get(id, ty)?;
+
+        let index = id & INDEX_MASK;
+        let slot = &mut self.slots[index];
+
+        slot.value = None;
+        slot.generation = if slot.generation == MAX_GENERATION {
+            1
+        } else {
+            slot.generation + 1
+        };
+        self.free.push(index);
+
+        Ok(ptr)
+    }
+}
+
+/**
+The id of a boxed value in the handle table.
+*/
+#[repr(transparent)]
+pub(super) struct Raw<T: ?Sized>(usize, PhantomData<*mut T>);
+
+impl<T: 'static> Raw<T> {
+    pub(super) fn alloc(value: Box<T>) -> Self {
+        let ptr = Box::into_raw(value) as *mut ();
+        let id = TABLE
+            .lock()
+            .expect("failed to lock handles")
+            .insert(TypeId::of::<T>(), ptr);
+
+        Raw(id, PhantomData)
+    }
+
+    pub(super) fn get(&self) -> Result<*mut T, Error> {
+        let ptr = TABLE
+            .lock()
+            .expect("failed to lock handles")
+            .get(self.0, TypeId::of::<T>())?;
+
+        Ok(ptr as *mut T)
+    }
+
+    pub(super) fn remove(self) -> Result<*mut T, Error> {
+        let ptr = TABLE
+            .lock()
+            .expect("failed to lock handles")
+            .remove(self.0, TypeId::of::<T>())?;
+
+        Ok(ptr as *mut T)
+    }
+}
+
+impl<T: ?Sized> Raw<T> {
+    pub(super) fn is_null(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copy<T>(raw: &Raw<T>) -> Raw<T> {
+        Raw(raw.0, PhantomData)
+    }
+
+    fn free<T: 'static>(raw: Raw<T>) {
+        let ptr = raw.remove().unwrap();
+        drop(unsafe_block!("The pointer was removed from the table" => Box::from_raw(ptr)));
+    }
+
+    #[test]
+    fn get_live_handle() {
+        let raw = Raw::alloc(Box::new(42u32));
+
+        assert!(!raw.is_null());
+        assert_eq!(
+            42,
+            unsafe_block!("The handle is live" => *raw.get().unwrap())
+        );
+
+        free(raw);
+    }
+
+    #[test]
+    fn get_released_handle() {
+        let raw = Raw::alloc(Box::new(42u32));
+        let stale = copy(&raw);
+
+        free(raw);
+
+        assert!(stale.get().is_err());
+    }
+
+    #[test]
+    fn remove_released_handle() {
+        let raw = Raw::alloc(Box::new(42u32));
+        let stale = copy(&raw);
+
+        free(raw);
+
+        assert!(stale.remove().is_err());
+    }
+
+    #[test]
+    fn get_reused_slot() {
+        let raw = Raw::alloc(Box::new(42u32));
+        let stale = copy(&raw);
+
+        free(raw);
+
+        // The slot may be reused, but with a new generation
+        let reused = Raw::alloc(Box::new(43u32));
+
+        assert_ne!(stale.0, reused.0);
+        assert!(stale.get().is_err());
+
+        free(reused);
+    }
+
+    #[test]
+    fn get_different_type() {
+        let raw = Raw::alloc(Box::new(42u32));
+        let other: Raw<u64> = Raw(raw.0, PhantomData);
+
+        assert!(other.get().is_err());
+
+        free(raw);
+    }
+
+    #[test]
+    fn get_unallocated_handle() {
+        let raw: Raw<u32> = Raw(usize::MAX, PhantomData);
+
+        assert!(raw.get().is_err());
+    }
+}
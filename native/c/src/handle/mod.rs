@@ -1,5 +1,6 @@
 use std::{
     marker::PhantomData,
+    mem,
     panic::{
         RefUnwindSafe,
         UnwindSafe,
@@ -10,8 +11,26 @@ use std::{
 
 pub(crate) mod thread_bound;
 
+#[cfg(any(debug_assertions, feature = "checked-handles"))]
+mod checked;
+#[cfg(not(any(debug_assertions, feature = "checked-handles")))]
+mod unchecked;
+
+#[cfg(any(debug_assertions, feature = "checked-handles"))]
+use self::checked::Raw;
+#[cfg(not(any(debug_assertions, feature = "checked-handles")))]
+use self::unchecked::Raw;
+
+use failure_derive::*;
+
 use self::thread_bound::ThreadBound;
-use super::is_null::IsNull;
+use super::{
+    is_null::IsNull,
+    versioned::{
+        self,
+        Versioned,
+    },
+};
 
 /*
 The handles here are wrappers for a shared `&T` and an exclusive `&mut T`.
@@ -19,8 +38,26 @@ The handles here are wrappers for a shared `&T` and an exclusive `&mut T`.
 They protect from data races, but don't protect from use-after-free bugs.
 The caller is expected to maintain that invariant, which in .NET can be
 achieved using `SafeHandle`s.
+
+In debug builds, or with the `checked-handles` feature, handles are generation-checked
+ids in a global table instead of raw pointers. Using a handle after it's been released,
+releasing it twice, or passing it where a different kind of handle is expected returns
+an `InvalidHandle` result instead of being undefined behaviour.
 */
 
+#[derive(Debug, Fail)]
+#[cfg_attr(
+    not(any(debug_assertions, feature = "checked-handles")),
+    allow(dead_code)
+)]
+pub(super) enum Error {
+    #[fail(
+        display = "the handle `{:#x}` is invalid or has already been released",
+        _0
+    )]
+    InvalidHandle(usize),
+}
+
 /**
 A shared handle that can be accessed concurrently by multiple threads.
 
@@ -29,34 +66,33 @@ The interior value can be treated like `&T`.
 Consumers must ensure a handle is not used again after it has been deallocated.
 */
 #[repr(transparent)]
-pub struct HandleShared<'a, T: ?Sized>(*const T, PhantomData<&'a T>);
+pub struct HandleShared<'a, T: ?Sized>(Raw<T>, PhantomData<&'a T>);
 
 unsafe_impl!("The handle is semantically `&T`" => impl<'a, T: ?Sized> Send for HandleShared<'a, T> where &'a T: Send {});
 unsafe_impl!("The handle is semantically `&T`" => impl<'a, T: ?Sized> Sync for HandleShared<'a, T> where &'a T: Sync {});
 
 impl<'a, T: ?Sized + RefUnwindSafe> UnwindSafe for HandleShared<'a, T> {}
 
-impl<'a, T> HandleShared<'a, T>
+impl<'a, T: 'static> HandleShared<'a, T>
 where
     HandleShared<'a, T>: Send + Sync,
 {
-    pub(super) fn alloc(value: T) -> Self
-    where
-        T: 'static,
-    {
+    pub(super) fn alloc(value: T) -> Self {
         let v = Box::new(value);
-        HandleShared(Box::into_raw(v), PhantomData)
+        HandleShared(Raw::alloc(v), PhantomData)
     }
 
-    pub(super) fn as_ref(&self) -> &T {
-        unsafe_block!("We own the interior value" => { &*self.0 })
+    pub(super) fn as_ref(&self) -> Result<&T, Error> {
+        let ptr = self.0.get()?;
+
+        Ok(unsafe_block!("We own the interior value" => &*ptr))
     }
 
     unsafe_fn!("There are no other live references and the handle won't be used again" =>
-        pub(super) fn dealloc<R>(handle: Self, f: impl FnOnce(T) -> R) -> R {
-            let v = Box::from_raw(handle.0 as *mut T);
-            f(*v)
-        });
+    pub(super) fn dealloc<R>(handle: Self, f: impl FnOnce(T) -> R) -> Result<R, Error> {
+        let v = Box::from_raw(handle.0.remove()?);
+        Ok(f(*v))
+    });
 }
 
 /**
@@ -74,37 +110,36 @@ The handle _can_ be deallocated from a different thread than the one that create
 Consumers must ensure a handle is not used again after it has been deallocated.
 */
 #[repr(transparent)]
-pub struct HandleExclusive<'a, T: ?Sized>(*mut ThreadBound<T>, PhantomData<&'a mut T>);
+pub struct HandleExclusive<'a, T: ?Sized>(Raw<ThreadBound<T>>, PhantomData<&'a mut T>);
 
 unsafe_impl!("The handle is semantically `&mut T`" => impl<'a, T: ?Sized> Send for HandleExclusive<'a, T> where &'a mut ThreadBound<T>: Send {});
 unsafe_impl!("The handle uses `ThreadBound` for synchronization" => impl<'a, T: ?Sized> Sync for HandleExclusive<'a, T> where &'a mut ThreadBound<T>: Sync {});
 
 impl<'a, T: ?Sized + RefUnwindSafe> UnwindSafe for HandleExclusive<'a, T> {}
 
-impl<'a, T> HandleExclusive<'a, T>
+impl<'a, T: 'static> HandleExclusive<'a, T>
 where
     HandleExclusive<'a, T>: Send + Sync,
 {
-    pub(super) fn alloc(value: T) -> Self
-    where
-        T: 'static,
-    {
+    pub(super) fn alloc(value: T) -> Self {
         let v = Box::new(ThreadBound::new(value));
-        HandleExclusive(Box::into_raw(v), PhantomData)
+        HandleExclusive(Raw::alloc(v), PhantomData)
     }
 
-    pub(super) fn as_mut(&mut self) -> &mut T {
-        unsafe_block!("We own the interior value" => { &mut *(*self.0).get_raw() })
+    pub(super) fn as_mut(&mut self) -> Result<&mut T, Error> {
+        let ptr = self.0.get()?;
+
+        Ok(unsafe_block!("We own the interior value" => &mut *(*ptr).get_raw()))
     }
 
     unsafe_fn!("There are no other live references and the handle won't be used again" =>
-        pub(super) fn dealloc<R>(handle: Self, f: impl FnOnce(T) -> R) -> R
-        where
-            T: Send,
-        {
-            let v = Box::from_raw(handle.0);
-            f(v.into_inner())
-        });
+    pub(super) fn dealloc<R>(handle: Self, f: impl FnOnce(T) -> R) -> Result<R, Error>
+    where
+        T: Send,
+    {
+        let v = Box::from_raw(handle.0.remove()?);
+        Ok(f(v.into_inner()))
+    });
 }
 
 /**
@@ -124,12 +159,41 @@ impl<'a, T: ?Sized> Ref<'a, T> {
     });
 }
 
+impl<'a, T> Ref<'a, T> {
+    unsafe_fn!("The pointer must be nonnull, the length is correct, and will remain valid" => pub fn as_slice(&self, len: usize) -> &[T] {
+        slice::from_raw_parts(self.0, len)
+    });
+}
+
 impl<'a> Ref<'a, u8> {
     unsafe_fn!("The pointer must be nonnull, the length is correct, and will remain valid" => pub fn as_bytes(&self, len: usize) -> &[u8] {
         slice::from_raw_parts(self.0, len)
     });
 }
 
+impl<'a, T: Versioned> Ref<'a, T> {
+    unsafe_fn!("The pointer must be nonnull and valid for reads of the size in its leading `size` field" => pub fn read_versioned(&self) -> Result<T, versioned::Error> {
+        let size = versioned::check_size::<T>(ptr::read_unaligned(self.0 as *const u32))?;
+
+        let bytes = slice::from_raw_parts(self.0 as *const u8, size);
+        versioned::check_unknown_fields::<T>(bytes)?;
+
+        // Fields the caller doesn't know about keep their defaults
+        let mut value = T::default();
+        let len = size.min(mem::size_of::<T>());
+        ptr::copy_nonoverlapping(bytes.as_ptr(), &mut value as *mut T as *mut u8, len);
+
+        Ok(value)
+    });
+}
+
+// A null reference can be used for fields that aren't supplied by the caller
+impl<'a, T> Default for Ref<'a, T> {
+    fn default() -> Self {
+        Ref(ptr::null(), PhantomData)
+    }
+}
+
 /**
 An initialized parameter passed by exclusive reference.
 */
@@ -153,6 +217,28 @@ impl<'a> RefMut<'a, u8> {
     });
 }
 
+impl<'a, T: Versioned> RefMut<'a, T> {
+    unsafe_fn!("The pointer must be nonnull and valid for reads of its leading `size` field" => pub fn versioned_size(&self) -> Result<usize, versioned::Error> {
+        versioned::check_size::<T>(ptr::read_unaligned(self.0 as *const u32))
+    });
+
+    unsafe_fn!("The pointer must be nonnull and valid for writes of the size in its leading `size` field" => pub fn write_versioned(&mut self, value: T) -> Result<(), versioned::Error> {
+        let size = self.versioned_size()?;
+        self.write_versioned_at(size, 0, value);
+
+        Ok(())
+    });
+
+    unsafe_fn!("The pointer must be nonnull and valid for writes of `size` bytes up to and including the index" => pub fn write_versioned_at(&mut self, size: usize, index: usize, value: T) {
+        // Fields the caller doesn't know about are left untouched
+        let len = size.min(mem::size_of::<T>());
+        let dst = (self.0 as *mut u8).add(size * index);
+
+        ptr::copy_nonoverlapping(&value as *const T as *const u8, dst, len);
+        ptr::write_unaligned(dst as *mut u32, len as u32);
+    });
+}
+
 /**
 An uninitialized, assignable out parameter.
 */
@@ -168,6 +254,10 @@ impl<'a, T> Out<'a, T> {
     unsafe_fn!("The pointer must be nonnull and valid for writes" => pub fn init(&mut self, value: T) {
         ptr::write(self.0, value);
     });
+
+    unsafe_fn!("The pointer must be nonnull and valid for writes up to and including the index" => pub fn init_at(&mut self, index: usize, value: T) {
+        ptr::write(self.0.add(index), value);
+    });
 }
 
 impl<'a> Out<'a, u8> {
@@ -180,6 +270,27 @@ impl<'a> Out<'a, u8> {
     });
 }
 
+#[cfg(test)]
+impl<'a, T> Ref<'a, T> {
+    pub(crate) fn from_ptr(ptr: *const T) -> Self {
+        Ref(ptr, PhantomData)
+    }
+}
+
+#[cfg(test)]
+impl<'a, T> RefMut<'a, T> {
+    pub(crate) fn from_ptr(ptr: *mut T) -> Self {
+        RefMut(ptr, PhantomData)
+    }
+}
+
+#[cfg(test)]
+impl<'a, T> Out<'a, T> {
+    pub(crate) fn from_ptr(ptr: *mut T) -> Self {
+        Out(ptr, PhantomData)
+    }
+}
+
 impl<'a, T: ?Sized> IsNull for HandleExclusive<'a, T> {
     fn is_null(&self) -> bool {
         self.0.is_null()
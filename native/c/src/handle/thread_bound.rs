@@ -34,7 +34,7 @@ fn get_thread_id() -> usize {
     THREAD_ID.with(|x| *x)
 }
 
-thread_local!(static VALUE_ID: UnsafeCell<usize> = UnsafeCell::new(0));
+thread_local!(static VALUE_ID: UnsafeCell<usize> = const { UnsafeCell::new(0) });
 
 fn next_value_id() -> usize {
     VALUE_ID.with(|x| {
@@ -48,7 +48,9 @@ fn next_value_id() -> usize {
     })
 }
 
-struct Registry(HashMap<ValueId, (UnsafeCell<*mut ()>, Box<dyn Fn(&UnsafeCell<*mut ()>)>)>);
+type Cleanup = Box<dyn Fn(&UnsafeCell<*mut ()>)>;
+
+struct Registry(HashMap<ValueId, (UnsafeCell<*mut ()>, Cleanup)>);
 
 impl Drop for Registry {
     fn drop(&mut self) {
@@ -60,7 +62,14 @@ impl Drop for Registry {
         }
 
         // Drop any remaining values in the registry
-        for (_, value) in self.0.iter() {
+        if !self.0.is_empty() {
+            log::debug!(
+                "cleaning up {} values left on an exiting thread",
+                self.0.len()
+            );
+        }
+
+        for value in self.0.values() {
             (value.1)(&value.0);
         }
     }
@@ -141,11 +150,11 @@ impl<T: 'static> Drop for DeferredCleanup<T> {
         if mem::needs_drop::<T>() {
             if self.is_valid() {
                 unsafe_block!("The value exists on the current thread" => {
-                    self.into_inner_unchecked();
+                    self.take_unchecked();
                 });
             } else {
                 let mut garbage = GARBAGE.lock().expect("failed to lock garbage queue");
-                let garbage = garbage.entry(self.thread_id).or_insert_with(|| Vec::new());
+                let garbage = garbage.entry(self.thread_id).or_default();
 
                 garbage.push(self.value_id);
             }
@@ -169,6 +178,11 @@ impl<T: 'static> DeferredCleanup<T> {
         };
 
         if let Some(garbage) = garbage {
+            log::debug!(
+                "cleaning up {} values dropped on other threads",
+                garbage.len()
+            );
+
             let remove = |value_id: ValueId| {
                 REGISTRY.with(|registry| {
                     unsafe_block!("The value never has overlapping mutable aliases" => {
@@ -219,7 +233,7 @@ impl<T: 'static> DeferredCleanup<T> {
                 let registry = &(*registry.get()).0;
 
                 if let Some(item) = registry.get(&self.value_id) {
-                    f(mem::transmute(&item.0))
+                    f(mem::transmute::<&UnsafeCell<*mut ()>, &UnsafeCell<Box<T>>>(&item.0))
                 } else {
                     panic!("attempted to access resource from a different thread");
                 }
@@ -238,7 +252,7 @@ impl<T: 'static> DeferredCleanup<T> {
         self.thread_id == current_thread && has_value
     }
 
-    unsafe_fn!("The value must originate on the current thread" => fn into_inner_unchecked(&mut self) -> T {
+    unsafe_fn!("The value must originate on the current thread" => fn take_unchecked(&mut self) -> T {
         let ptr = REGISTRY
             .with(|registry| (*registry.get()).0.remove(&self.value_id))
             .unwrap()
@@ -258,13 +272,15 @@ impl<T: 'static> Deref for DeferredCleanup<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.with_value(|value| unsafe_block!("The borrow of self protects the inner value" => { &*value.get() }))
+        self.with_value(
+            |value| unsafe_block!("The borrow of self protects the inner value" => &*value.get()),
+        )
     }
 }
 
 impl<T: 'static> DerefMut for DeferredCleanup<T> {
     fn deref_mut(&mut self) -> &mut T {
-        self.with_value(|value| unsafe_block!("The borrow of self protects the inner value" => { &mut *value.get() }))
+        self.with_value(|value| unsafe_block!("The borrow of self protects the inner value" => &mut *value.get()))
     }
 }
 
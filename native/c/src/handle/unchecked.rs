@@ -0,0 +1,31 @@
+/*!
+Handles that are raw pointers to their values.
+*/
+
+use super::Error;
+
+/**
+A pointer to a boxed value.
+*/
+#[repr(transparent)]
+pub(super) struct Raw<T: ?Sized>(*mut T);
+
+impl<T> Raw<T> {
+    pub(super) fn alloc(value: Box<T>) -> Self {
+        Raw(Box::into_raw(value))
+    }
+
+    pub(super) fn get(&self) -> Result<*mut T, Error> {
+        Ok(self.0)
+    }
+
+    pub(super) fn remove(self) -> Result<*mut T, Error> {
+        Ok(self.0)
+    }
+}
+
+impl<T: ?Sized> Raw<T> {
+    pub(super) fn is_null(&self) -> bool {
+        self.0.is_null()
+    }
+}
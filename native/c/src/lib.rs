@@ -5,7 +5,9 @@ C interface to the database.
 // Unsafe is explicitly allowed through `unsafe_*` macros
 #![deny(unsafe_code)]
 // For converting Rust results into FFI results
-#![feature(try_trait)]
+#![feature(try_trait_v2, try_trait_v2_residual)]
+// `failure_derive` implements `Fail` inside an anonymous `const`
+#![allow(non_local_definitions)]
 
 #[macro_use]
 #[path = "../../std_ext/mod.rs"]
@@ -14,8 +16,12 @@ mod std_ext;
 #[macro_use]
 extern crate lazy_static;
 
-use std::str;
+use std::{
+    mem,
+    str,
+};
 
+use dbc_macros::ffi;
 use libc::size_t;
 
 use db::{
@@ -26,22 +32,69 @@ use db::{
     store,
 };
 
-#[macro_use]
-mod macros;
+use self::versioned::Versioned;
 
+mod abi;
+mod callback;
 mod handle;
 mod is_null;
+mod logging;
+mod metrics;
+mod options;
 mod read;
 mod result;
+mod trace;
+mod versioned;
 
 pub use self::{
+    abi::*,
+    callback::*,
     handle::*,
+    logging::*,
+    metrics::*,
+    options::*,
     result::*,
+    trace::DbSpan,
 };
 
 #[repr(transparent)]
+#[derive(Default)]
 pub struct DbKey([u8; 16]);
 
+/**
+Machine-readable fields from the last error.
+
+`key` is only set if `has_key` is `true`, and `os_error` is only set if `has_os_error` is `true`.
+
+`size` must be set to the size of the struct before it's written.
+*/
+#[repr(C)]
+pub struct DbErrorFields {
+    size: u32,
+    result: DbResult,
+    has_key: bool,
+    key: DbKey,
+    has_os_error: bool,
+    os_error: i32,
+}
+
+impl Default for DbErrorFields {
+    fn default() -> Self {
+        DbErrorFields {
+            size: 0,
+            result: DbResult::ok(),
+            has_key: false,
+            key: DbKey::default(),
+            has_os_error: false,
+            os_error: 0,
+        }
+    }
+}
+
+impl Versioned for DbErrorFields {
+    const MIN_SIZE: usize = mem::size_of::<DbErrorFields>();
+}
+
 #[repr(C)]
 pub struct DbStore {
     inner: store::Store,
@@ -63,224 +116,620 @@ pub struct DbWriter {
 
 pub type DbWriterHandle<'a> = HandleExclusive<'a, DbWriter>;
 
+/**
+A deleter that removes records from a store.
+
+When `db_delete_remove_returning` returns `DB_KIND_BUFFER_TOO_SMALL` the record has already been removed.
+Its payload is only held for a retry with the same key that comes straight after.
+Any other call on the deleter discards it.
+
+When a record is removed but its payload can't be read `DB_KIND_REMOVED_UNREADABLE` is returned.
+*/
 #[repr(C)]
 pub struct DbDeleter {
     inner: store::deleter::Deleter,
+    // A removed payload that didn't fit in the caller's buffer
+    removed: Option<(DbKey, Vec<u8>)>,
 }
 
 pub type DbDeleterHandle<'a> = HandleExclusive<'a, DbDeleter>;
 
-ffi_no_catch! {
-    fn db_last_result(
-        message_buf: Out<u8>,
-        message_buf_len: size_t,
-        actual_message_len: Out<size_t>,
-        result: Out<DbResult>
-    ) -> DbResult {
-        DbResult::with_last_result(|last_result| {
-            let (value, error) = last_result.unwrap_or((DbResult::ok(), None));
+#[ffi(no_catch)]
+fn db_abi_version(major: Out<u32>, minor: Out<u32>) -> DbResult {
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => major.init(DB_ABI_MAJOR));
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => minor.init(DB_ABI_MINOR));
+
+    DbResult::ok()
+}
 
-            unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => result.init(value));
+#[ffi(no_catch)]
+fn db_capabilities(flags: Out<u64>) -> DbResult {
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => flags.init(abi::capabilities()));
 
-            if let Some(error) = error {
-                let error = error.as_bytes();
+    DbResult::ok()
+}
 
-                unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_message_len.init(error.len()));
+#[ffi(no_catch)]
+fn db_last_result(
+    message_buf: Out<u8>,
+    message_buf_len: size_t,
+    actual_message_len: Out<size_t>,
+    result: Out<DbResult>
+) -> DbResult {
+    DbResult::with_last_result(|last_result| {
+        let (value, error) = last_result.unwrap_or((DbResult::ok(), None));
 
-                if message_buf_len < error.len() {
-                    return DbResult::buffer_too_small();
-                }
+        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => result.init(value));
 
-                unsafe_block!("The buffer is valid for writes and the length is within the buffer" => message_buf.init_bytes(error));
-            } else {
-                unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_message_len.init(0));
-            }
+        if let Some(error) = error {
+            message_into_buf(error, &mut message_buf, message_buf_len, &mut actual_message_len)
+        } else {
+            unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_message_len.init(0));
 
             DbResult::ok()
-        })
-    }
+        }
+    })
 }
 
-ffi! {
-    fn db_store_open(path: Ref<u8>, path_len: size_t, store: Out<DbStoreHandle>) -> DbResult {
-        let path_slice = unsafe_block!("The path lives as long as `db_store_open` and the length is within the path" => path.as_bytes(path_len));
-        let path = str::from_utf8(path_slice)?;
+#[ffi(no_catch)]
+fn db_last_result_cause(
+    index: size_t,
+    message_buf: Out<u8>,
+    message_buf_len: size_t,
+    actual_message_len: Out<size_t>
+) -> DbResult {
+    DbResult::with_last_details(|last_result| {
+        let cause = last_result
+            .and_then(|(_, details)| details)
+            .and_then(|details| details.causes.get(index));
+
+        match cause {
+            Some(cause) => message_into_buf(cause, &mut message_buf, message_buf_len, &mut actual_message_len),
+            None => DbResult::done(),
+        }
+    })
+}
 
-        let handle = DbStoreHandle::alloc(DbStore {
-            inner: store::Store::open(path)?,
-        });
+#[ffi(no_catch)]
+fn db_last_result_backtrace(
+    message_buf: Out<u8>,
+    message_buf_len: size_t,
+    actual_message_len: Out<size_t>
+) -> DbResult {
+    DbResult::with_last_details(|last_result| {
+        let backtrace = last_result
+            .and_then(|(_, details)| details)
+            .and_then(|details| details.backtrace.as_ref());
+
+        match backtrace {
+            Some(backtrace) => message_into_buf(backtrace, &mut message_buf, message_buf_len, &mut actual_message_len),
+            None => DbResult::done(),
+        }
+    })
+}
+
+#[ffi(no_catch)]
+fn db_result_details(
+    id: u32,
+    message_buf: Out<u8>,
+    message_buf_len: size_t,
+    actual_message_len: Out<size_t>
+) -> DbResult {
+    DbResult::with_retained(id, |err| {
+        match err {
+            Some(err) => message_into_buf(err, &mut message_buf, message_buf_len, &mut actual_message_len),
+            None => DbResult::done(),
+        }
+    })
+}
+
+#[ffi(no_catch)]
+fn db_set_error_retention(retention: size_t) -> DbResult {
+    DbResult::set_error_retention(retention);
+
+    DbResult::ok()
+}
+
+#[ffi(no_trace)]
+fn db_trace_enable(capacity: size_t) -> DbResult {
+    trace::enable(capacity);
+
+    DbResult::ok()
+}
+
+#[ffi(no_trace)]
+fn db_trace_drain(
+    spans: RefMut<DbSpan>,
+    spans_len: size_t,
+    drained: Out<size_t>,
+    dropped: Out<u64>
+) -> DbResult {
+    // The size of the first span is the size of every span in the buffer
+    let size = if spans_len > 0 {
+        unsafe_block!("The buffer is valid for reads of the first span" => spans.versioned_size())?
+    } else {
+        0
+    };
+
+    let (count, dropped_count) = trace::drain(spans_len, |i, span| {
+        unsafe_block!("The buffer is valid for writes of `size` bytes and the index is within the buffer" => spans.write_versioned_at(size, i, span));
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => drained.init(count));
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => dropped.init(dropped_count));
+
+    DbResult::ok()
+}
+
+#[ffi(no_catch)]
+fn db_last_result_fields(fields: RefMut<DbErrorFields>) -> DbResult {
+    DbResult::with_last_details(|last_result| {
+        let (value, details) = last_result.unwrap_or((DbResult::ok(), None));
+        let details = details.cloned().unwrap_or_default();
+
+        let value = DbErrorFields {
+            size: versioned::current_size::<DbErrorFields>(),
+            result: value,
+            has_key: details.key.is_some(),
+            key: DbKey(details.key.unwrap_or_default()),
+            has_os_error: details.os_error.is_some(),
+            os_error: details.os_error.unwrap_or_default(),
+        };
 
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => store.init(handle));
+        unsafe_block!("The pointer is valid for writes of its size and not mutably aliased elsewhere" => fields.write_versioned(value))?;
 
         DbResult::ok()
-    }
+    })
+}
 
-    fn db_store_close(store: DbStoreHandle) -> DbResult {
-        unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbStoreHandle::dealloc(store, |mut store| {
-            store.inner.close()?;
+/**
+Write a message into a caller-supplied buffer.
 
-            DbResult::ok()
-        }))
+If the message doesn't fit then `DbResult::BufferTooSmall` will be returned
+and `actual_message_len` will contain the minimum size of the buffer needed.
+*/
+fn message_into_buf(
+    message: &str,
+    message_buf: &mut Out<u8>,
+    message_buf_len: size_t,
+    actual_message_len: &mut Out<size_t>,
+) -> DbResult {
+    let message = message.as_bytes();
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_message_len.init(message.len()));
+
+    if message_buf_len < message.len() {
+        return DbResult::buffer_too_small();
     }
 
-    fn db_read_begin(
-        store: DbStoreHandle,
-        reader: Out<DbReaderHandle>
-    ) -> DbResult {
-        let store = store.as_ref();
+    unsafe_block!("The buffer is valid for writes and the length is within the buffer" => message_buf.init_bytes(message));
 
-        let handle = DbReaderHandle::alloc(DbReader {
-            inner: thread_bound::DeferredCleanup::new(store.inner.read_begin()?),
-        });
+    DbResult::ok()
+}
+
+#[ffi]
+fn db_set_log_callback(callback: DbLogCallback, max_level: u32) -> DbResult {
+    logging::set_callback(callback, max_level)?;
+
+    DbResult::ok()
+}
+
+#[ffi(lengths(path_len))]
+fn db_store_open(path: Ref<u8>, path_len: size_t, store: Out<DbStoreHandle>) -> DbResult {
+    let path_slice = unsafe_block!("The path lives as long as `db_store_open` and the length is within the path" => path.as_bytes(path_len));
+    let path = str::from_utf8(path_slice)?;
+
+    let handle = DbStoreHandle::alloc(DbStore {
+        inner: store::Store::open(path)?,
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => store.init(handle));
+
+    DbResult::ok()
+}
 
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => reader.init(handle));
+#[ffi(lengths(path_len))]
+fn db_store_open_with_options(
+    path: Ref<u8>,
+    path_len: size_t,
+    options: Ref<DbStoreOptions>,
+    store: Out<DbStoreHandle>
+) -> DbResult {
+    let path_slice = unsafe_block!("The path lives as long as `db_store_open_with_options` and the length is within the path" => path.as_bytes(path_len));
+    let path = str::from_utf8(path_slice)?;
+
+    let options = unsafe_block!("The options pointer lives as long as `db_store_open_with_options` and points to valid data" => options.read_versioned())?;
+
+    let options = options.to_options()?;
+
+    let handle = DbStoreHandle::alloc(DbStore {
+        inner: store::Store::open_with_options(path, options)?,
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => store.init(handle));
+
+    DbResult::ok()
+}
+
+#[ffi(releases(store))]
+fn db_store_close(store: DbStoreHandle) -> DbResult {
+    unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbStoreHandle::dealloc(store, |mut store| {
+        store.inner.close()?;
 
         DbResult::ok()
-    }
+    }))?
+}
+
+#[ffi]
+fn db_store_metrics(store: DbStoreHandle, metrics: RefMut<DbStoreMetrics>) -> DbResult {
+    let store = store.as_ref()?;
+
+    let value = DbStoreMetrics::from(store.inner.metrics());
+
+    unsafe_block!("The pointer is valid for writes of its size and not mutably aliased elsewhere" => metrics.write_versioned(value))?;
+
+    DbResult::ok()
+}
+
+#[ffi(lengths(text_buf_len))]
+fn db_store_metrics_prometheus(
+    store: DbStoreHandle,
+    text_buf: Out<u8>,
+    text_buf_len: size_t,
+    actual_text_len: Out<size_t>
+) -> DbResult {
+    let store = store.as_ref()?;
+
+    let text = store.inner.metrics().to_prometheus();
+
+    message_into_buf(&text, &mut text_buf, text_buf_len, &mut actual_text_len)
+}
+
+#[ffi]
+fn db_store_reencrypt(
+    store: DbStoreHandle,
+    reencrypted: Out<size_t>,
+    skipped: Out<size_t>
+) -> DbResult {
+    let store = store.as_ref()?;
 
-    fn db_read_next(
-        reader: DbReaderHandle,
-        key: Out<DbKey>,
-        value_buf: Out<u8>,
-        value_buf_len: size_t,
-        actual_value_len: Out<size_t>
-    ) -> DbResult {
-        let reader = reader.as_mut();
-
-        let buf = unsafe_block!("The buffer lives as long as `db_read_next`, the length is within the buffer and the buffer won't be read before initialization" => value_buf.as_uninit_bytes_mut(value_buf_len));
-
-        'read_event: loop {
-            let read_result = reader.inner.with_current(|mut current| {
-                read::into_fixed_buffer(&mut current, buf, &mut key, &mut actual_value_len)
-            });
-
-            match read_result {
-                // If the result is ok then we're done with this event
-                // Fetch the next one
-                Some(result) if result.is_ok() => {
-                    reader.inner.move_next()?;
-
-                    return DbResult::ok();
-                },
-                // If the result is anything but `Ok` then return.
-                // This probably means the caller-supplied buffer was
-                // too small
-                Some(result) => return result,
-                // If there is no result then we don't have an event.
-                // Fetch the next event and recurse.
-                // This probably means we're reading the first event,
-                // or have reached the end.
-                None => {
-                    if reader.inner.move_next()? {
-                        continue 'read_event;
-                    } else {
-                        return DbResult::done();
-                    }
+    let reencryption = store.inner.reencrypt()?;
+
+    unsafe_block!("The out pointers are valid and not mutably aliased elsewhere" => {
+        reencrypted.init(reencryption.reencrypted);
+        skipped.init(reencryption.skipped);
+    });
+
+    DbResult::ok()
+}
+
+#[ffi]
+fn db_read_begin(
+    store: DbStoreHandle,
+    reader: Out<DbReaderHandle>
+) -> DbResult {
+    let store = store.as_ref()?;
+
+    let handle = DbReaderHandle::alloc(DbReader {
+        inner: thread_bound::DeferredCleanup::new(store.inner.read_begin()?),
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => reader.init(handle));
+
+    DbResult::ok()
+}
+
+#[ffi(lengths(filter_len))]
+fn db_read_begin_with_filter(
+    store: DbStoreHandle,
+    filter: Ref<u8>,
+    filter_len: size_t,
+    reader: Out<DbReaderHandle>
+) -> DbResult {
+    let store = store.as_ref()?;
+
+    let filter_slice = unsafe_block!("The filter lives as long as `db_read_begin_with_filter` and the length is within the filter" => filter.as_bytes(filter_len));
+    let filter = store::filter::Filter::parse(str::from_utf8(filter_slice)?)?;
+
+    let handle = DbReaderHandle::alloc(DbReader {
+        inner: thread_bound::DeferredCleanup::new(store.inner.read_begin_with_filter(filter)?),
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => reader.init(handle));
+
+    DbResult::ok()
+}
+
+#[ffi(lengths(index_len, value_len))]
+fn db_index_query(
+    store: DbStoreHandle,
+    index: Ref<u8>,
+    index_len: size_t,
+    value: Ref<u8>,
+    value_len: size_t,
+    reader: Out<DbReaderHandle>
+) -> DbResult {
+    let store = store.as_ref()?;
+
+    let index_slice = unsafe_block!("The index lives as long as `db_index_query` and the length is within the index" => index.as_bytes(index_len));
+    let index = str::from_utf8(index_slice)?;
+
+    let value = unsafe_block!("The value lives as long as `db_index_query` and the length is within the value" => value.as_bytes(value_len));
+
+    let handle = DbReaderHandle::alloc(DbReader {
+        inner: thread_bound::DeferredCleanup::new(store.inner.index_query(index, value)?),
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => reader.init(handle));
+
+    DbResult::ok()
+}
+
+#[ffi(lengths(value_buf_len))]
+fn db_read_next(
+    reader: DbReaderHandle,
+    key: Out<DbKey>,
+    value_buf: Out<u8>,
+    value_buf_len: size_t,
+    actual_value_len: Out<size_t>
+) -> DbResult {
+    let reader = reader.as_mut()?;
+
+    let buf = unsafe_block!("The buffer lives as long as `db_read_next`, the length is within the buffer and the buffer won't be read before initialization" => value_buf.as_uninit_bytes_mut(value_buf_len));
+
+    'read_event: loop {
+        let read_result = reader.inner.with_current_bytes(|mut current| {
+            read::into_fixed_buffer(&mut current, buf, &mut key, &mut actual_value_len)
+        });
+
+        match read_result {
+            // If the result is ok then we're done with this event
+            // Fetch the next one
+            Some(result) if result.is_ok() => {
+                reader.inner.move_next()?;
+
+                return DbResult::ok();
+            },
+            // If the result is anything but `Ok` then return.
+            // This probably means the caller-supplied buffer was
+            // too small
+            Some(result) => return result,
+            // If there is no result then we don't have an event.
+            // Fetch the next event and recurse.
+            // This probably means we're reading the first event,
+            // or have reached the end.
+            None => {
+                if reader.inner.move_next()? {
+                    continue 'read_event;
+                } else {
+                    return DbResult::done();
                 }
             }
         }
     }
+}
 
-    fn db_read_end(reader: DbReaderHandle) -> DbResult {
-        unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbReaderHandle::dealloc(reader, |mut reader| {
-            reader.inner.complete()?;
+#[ffi(releases(reader))]
+fn db_read_end(reader: DbReaderHandle) -> DbResult {
+    unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbReaderHandle::dealloc(reader, |mut reader| {
+        reader.inner.complete()?;
 
-            DbResult::ok()
-        }))
-    }
+        DbResult::ok()
+    }))?
+}
 
-    fn db_write_begin(
-        store: DbStoreHandle,
-        writer: Out<DbWriterHandle>
-    ) -> DbResult {
-        let store = store.as_ref();
+#[ffi]
+fn db_write_begin(
+    store: DbStoreHandle,
+    writer: Out<DbWriterHandle>
+) -> DbResult {
+    let store = store.as_ref()?;
 
-        let handle = DbWriterHandle::alloc(DbWriter {
-            inner: store.inner.write_begin()?,
-        });
+    let handle = DbWriterHandle::alloc(DbWriter {
+        inner: store.inner.write_begin()?,
+    });
 
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => writer.init(handle));
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => writer.init(handle));
 
-        DbResult::ok()
-    }
+    DbResult::ok()
+}
 
-    fn db_write_set(
-        writer: DbWriterHandle,
-        key: Ref<DbKey>,
-        value: Ref<u8>,
-        value_len: size_t
-    ) -> DbResult {
-        let writer = writer.as_mut();
+#[ffi]
+fn db_write_begin_with_options(
+    store: DbStoreHandle,
+    options: Ref<DbWriteOptions>,
+    writer: Out<DbWriterHandle>
+) -> DbResult {
+    let store = store.as_ref()?;
 
-        let key = unsafe_block!("The key pointer lives as long as `db_write_set` and points to valid data" => key.as_ref());
-        let value_slice = unsafe_block!("The buffer lives as long as `db_write_set` and the length is within the buffer" => value.as_bytes(value_len));
+    let options = unsafe_block!("The options pointer lives as long as `db_write_begin_with_options` and points to valid data" => options.read_versioned())?;
 
-        let data = Data {
-            key: data::Key::from_bytes(key.0),
-            payload: value_slice,
-        };
+    let handle = DbWriterHandle::alloc(DbWriter {
+        inner: store.inner.write_begin_with_options(options.to_options()?)?,
+    });
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => writer.init(handle));
+
+    DbResult::ok()
+}
+
+#[ffi(lengths(value_len))]
+fn db_write_set(
+    writer: DbWriterHandle,
+    key: Ref<DbKey>,
+    value: Ref<u8>,
+    value_len: size_t
+) -> DbResult {
+    let writer = writer.as_mut()?;
 
-        writer.inner.set(data)?;
+    let key = unsafe_block!("The key pointer lives as long as `db_write_set` and points to valid data" => key.as_ref());
+    let value_slice = unsafe_block!("The buffer lives as long as `db_write_set` and the length is within the buffer" => value.as_bytes(value_len));
+
+    let data = Data {
+        key: data::Key::from_bytes(key.0),
+        payload: value_slice,
+    };
+
+    writer.inner.set(data)?;
+
+    DbResult::ok()
+}
+
+#[ffi(releases(writer))]
+fn db_write_end(writer: DbWriterHandle) -> DbResult {
+    unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbWriterHandle::dealloc(writer, |mut writer| {
+        writer.inner.complete()?;
 
         DbResult::ok()
-    }
+    }))?
+}
 
-    fn db_write_end(writer: DbWriterHandle) -> DbResult {
-        unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbWriterHandle::dealloc(writer, |mut writer| {
+#[ffi(releases(writer))]
+fn db_write_end_async(
+    writer: DbWriterHandle,
+    callback: DbCallback,
+    state: DbCallbackState
+) -> DbResult {
+    unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbWriterHandle::dealloc(writer, |mut writer| {
+        callback::complete(callback, state, move || {
             writer.inner.complete()?;
 
             DbResult::ok()
-        }))
-    }
+        })
+    }))?
+}
 
-    fn db_delete_begin(
-        store: DbStoreHandle,
-        deleter: Out<DbDeleterHandle>
-    ) -> DbResult {
-        let store = store.as_ref();
+#[ffi]
+fn db_delete_begin(
+    store: DbStoreHandle,
+    deleter: Out<DbDeleterHandle>
+) -> DbResult {
+    let store = store.as_ref()?;
 
-        let handle = DbDeleterHandle::alloc(DbDeleter {
-            inner: store.inner.delete_begin()?,
-        });
+    let handle = DbDeleterHandle::alloc(DbDeleter {
+        inner: store.inner.delete_begin()?,
+        removed: None,
+    });
 
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => deleter.init(handle));
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => deleter.init(handle));
 
-        DbResult::ok()
+    DbResult::ok()
+}
+
+#[ffi]
+fn db_delete_remove(
+    deleter: DbDeleterHandle,
+    key: Ref<DbKey>
+) -> DbResult {
+    let deleter = deleter.as_mut()?;
+
+    let key = unsafe_block!("The key pointer lives as long as `db_delete_remove` and points to valid data" => key.as_ref());
+
+    // A payload held for a retry is only returned by the call straight after it
+    deleter.removed = None;
+
+    // The result is done if there wasn't a record to remove
+    if !deleter.inner.remove(data::Key::from_bytes(key.0))? {
+        return DbResult::done();
     }
 
-    fn db_delete_remove(
-        deleter: DbDeleterHandle,
-        key: Ref<DbKey>
-    ) -> DbResult {
-        let deleter = deleter.as_mut();
+    DbResult::ok()
+}
 
-        let key = unsafe_block!("The key pointer lives as long as `db_delete_remove` and points to valid data" => key.as_ref());
+#[ffi(lengths(value_buf_len))]
+fn db_delete_remove_returning(
+    deleter: DbDeleterHandle,
+    key: Ref<DbKey>,
+    value_buf: Out<u8>,
+    value_buf_len: size_t,
+    actual_value_len: Out<size_t>
+) -> DbResult {
+    let deleter = deleter.as_mut()?;
+
+    let key = unsafe_block!("The key pointer lives as long as `db_delete_remove_returning` and points to valid data" => key.as_ref());
+    let buf = unsafe_block!("The buffer lives as long as `db_delete_remove_returning`, the length is within the buffer and the buffer won't be read before initialization" => value_buf.as_uninit_bytes_mut(value_buf_len));
+
+    // If the payload for this key didn't fit last time then it's already been removed
+    let removed = match deleter.removed.take() {
+        Some((removed_key, payload)) if removed_key.0 == key.0 => Some(Ok(payload)),
+        _ => deleter.inner.remove_returning(data::Key::from_bytes(key.0))?,
+    };
+
+    let payload = match removed {
+        Some(Ok(payload)) => payload,
+        // The record has been removed even though its payload can't be returned
+        Some(Err(e)) => return DbResult::removed_unreadable().context(e),
+        None => return DbResult::done(),
+    };
+
+    let result = read::payload_into_fixed_buffer(&mut &payload[..], buf, &mut actual_value_len);
+
+    // Hold on to the payload so the caller can try again with a bigger buffer
+    if result.is_buffer_too_small() {
+        deleter.removed = Some((DbKey(key.0), payload));
+    }
 
-        deleter.inner.remove(data::Key::from_bytes(key.0))?;
+    result
+}
+
+#[ffi]
+fn db_delete_range(
+    deleter: DbDeleterHandle,
+    start: Ref<DbKey>,
+    end: Ref<DbKey>,
+    removed: Out<size_t>
+) -> DbResult {
+    let deleter = deleter.as_mut()?;
+
+    let start = unsafe_block!("The start key pointer lives as long as `db_delete_range` and points to valid data" => start.as_ref());
+    let end = unsafe_block!("The end key pointer lives as long as `db_delete_range` and points to valid data" => end.as_ref());
+
+    // A payload held for a retry is only returned by the call straight after it
+    deleter.removed = None;
+
+    let count = deleter.inner.remove_range(data::Key::from_bytes(start.0), data::Key::from_bytes(end.0))?;
+
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => removed.init(count));
+
+    DbResult::ok()
+}
+
+#[ffi(releases(deleter))]
+fn db_delete_end(deleter: DbDeleterHandle) -> DbResult {
+    unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbDeleterHandle::dealloc(deleter, |mut deleter| {
+        deleter.inner.complete()?;
 
         DbResult::ok()
-    }
+    }))?
+}
 
-    fn db_delete_end(deleter: DbDeleterHandle) -> DbResult {
-        unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbDeleterHandle::dealloc(deleter, |mut deleter| {
+#[ffi(releases(deleter))]
+fn db_delete_end_async(
+    deleter: DbDeleterHandle,
+    callback: DbCallback,
+    state: DbCallbackState
+) -> DbResult {
+    unsafe_block!("The upstream caller guarantees the handle will not be accessed after being freed" => DbDeleterHandle::dealloc(deleter, |mut deleter| {
+        callback::complete(callback, state, move || {
             deleter.inner.complete()?;
 
             DbResult::ok()
-        }))
-    }
+        })
+    }))?
 }
 
 #[cfg(debug_assertions)]
-ffi! {
-    fn db_test_error() -> DbResult {
-        use std::io;
+#[ffi]
+fn db_test_error() -> DbResult {
+    use std::io;
 
-        DbResult::internal_error().context(io::Error::new(io::ErrorKind::Other, "A test error"))
-    }
+    DbResult::internal_error().context(io::Error::other("A test error"))
+}
 
-    fn db_test_ok() -> DbResult {
-        DbResult::ok()
-    }
+#[cfg(debug_assertions)]
+#[ffi]
+fn db_test_ok() -> DbResult {
+    DbResult::ok()
 }
 
 #[cfg(test)]
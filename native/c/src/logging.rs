@@ -0,0 +1,141 @@
+/*!
+Forwarding log events from native code to a callback.
+
+Events from both this library and the database are sent to the callback,
+using their module path as their target, like `db::store::durability`.
+*/
+
+use failure_derive::*;
+use libc::size_t;
+use log::{
+    Level,
+    LevelFilter,
+};
+
+use db::{
+    error::Error as DbError,
+    logger,
+};
+
+use crate::is_null::IsNull;
+
+/**
+A function called with log events.
+
+Levels are `1` for errors, `2` for warnings, `3` for info, `4` for debug, and `5` for trace.
+The target and message are UTF8 and are only valid for the duration of the call.
+The callback may be called from any thread, including threads owned by this library.
+*/
+#[repr(transparent)]
+pub struct DbLogCallback(
+    Option<
+        extern "C" fn(
+            level: u32,
+            target: *const u8,
+            target_len: size_t,
+            message: *const u8,
+            message_len: size_t,
+        ),
+    >,
+);
+
+// The callback is allowed to be null to stop logging
+impl IsNull for DbLogCallback {
+    fn is_null(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Fail)]
+pub(super) enum Error {
+    #[fail(display = "unknown log level `{}`", _0)]
+    UnknownLevel(u32),
+    #[fail(display = "failed to install the logger")]
+    Install(#[cause] DbError),
+}
+
+/**
+Send log events up to the given level to a callback.
+
+A level of `0` or a null callback turns logging off.
+*/
+pub(super) fn set_callback(callback: DbLogCallback, max_level: u32) -> Result<(), Error> {
+    let level = level_filter(max_level)?;
+
+    let sink = callback.0.map(|callback| {
+        Box::new(move |level: Level, target: &str, message: &str| {
+            callback(
+                level as u32,
+                target.as_ptr(),
+                target.len(),
+                message.as_ptr(),
+                message.len(),
+            )
+        }) as logger::Sink
+    });
+
+    logger::set_sink(level, sink).map_err(Error::Install)
+}
+
+fn level_filter(level: u32) -> Result<LevelFilter, Error> {
+    match level {
+        0 => Ok(LevelFilter::Off),
+        1 => Ok(LevelFilter::Error),
+        2 => Ok(LevelFilter::Warn),
+        3 => Ok(LevelFilter::Info),
+        4 => Ok(LevelFilter::Debug),
+        5 => Ok(LevelFilter::Trace),
+        level => Err(Error::UnknownLevel(level)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        slice,
+        str,
+        sync::Mutex,
+    };
+
+    use crate::DbResult;
+
+    lazy_static! {
+        static ref EVENTS: Mutex<Vec<(u32, String, String)>> = Mutex::new(Vec::new());
+    }
+
+    extern "C" fn collect(
+        level: u32,
+        target: *const u8,
+        target_len: size_t,
+        message: *const u8,
+        message_len: size_t,
+    ) {
+        let (target, message) = unsafe_block!("The target and message are valid for the duration of the call" => {
+            (
+                str::from_utf8(slice::from_raw_parts(target, target_len)).unwrap().to_owned(),
+                str::from_utf8(slice::from_raw_parts(message, message_len)).unwrap().to_owned(),
+            )
+        });
+
+        EVENTS.lock().unwrap().push((level, target, message));
+    }
+
+    #[test]
+    fn panics_are_logged() {
+        set_callback(DbLogCallback(Some(collect)), 2).unwrap();
+
+        let _ = DbResult::catch(|| panic!("a logged panic"));
+
+        let events = EVENTS.lock().unwrap();
+        assert!(events.iter().any(|(level, target, message)| {
+            *level == 1 && target == "dbc::result" && message.contains("a logged panic")
+        }));
+    }
+
+    #[test]
+    fn unknown_level() {
+        assert!(set_callback(DbLogCallback(None), 6).is_err());
+    }
+}
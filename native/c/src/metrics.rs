@@ -0,0 +1,136 @@
+/*!
+Snapshots of the operations made on a store.
+*/
+
+use std::mem;
+
+use db::{
+    error::ERROR_KINDS,
+    store::{
+        durability::BATCH_SIZE_BUCKETS,
+        metrics::{
+            StoreMetrics,
+            LATENCY_BUCKETS,
+        },
+    },
+};
+
+use crate::versioned::{
+    self,
+    Versioned,
+};
+
+/**
+The number of error kinds there's room to count in `DbStoreMetrics`.
+*/
+const MAX_ERROR_KINDS: usize = 32;
+
+const _: () = assert!(ERROR_KINDS <= MAX_ERROR_KINDS);
+
+/**
+A snapshot of the operations made on a store.
+
+`errors` is indexed by the position of the error kind: other, io, corrupted,
+decryption failed, validation failed, key too long, invalid filter, invalid index,
+unknown index, invalid schema, invalid argument.
+`error_kinds` is the number of those kinds that are counted.
+New kinds are only ever added to the end, and the rest of `errors` is zero.
+
+`flush_latency_buckets` counts flushes by how long they took, in microseconds,
+up to 100, 500, 1000, 5000, 10000, 50000, 100000, 500000, 1000000, and the rest.
+
+`size` must be set to the size of the struct before it's written.
+*/
+#[repr(C)]
+#[derive(Default)]
+pub struct DbStoreMetrics {
+    size: u32,
+    reads: u64,
+    writes: u64,
+    deletes: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+    flushes: u64,
+    flush_requests: u64,
+    flush_largest_batch: u64,
+    flush_batch_sizes: [u64; BATCH_SIZE_BUCKETS],
+    flush_latency_count: u64,
+    flush_latency_sum_micros: u64,
+    flush_latency_buckets: [u64; LATENCY_BUCKETS],
+    error_kinds: u32,
+    errors: [u64; MAX_ERROR_KINDS],
+}
+
+impl Versioned for DbStoreMetrics {
+    const MIN_SIZE: usize = mem::size_of::<DbStoreMetrics>();
+}
+
+impl From<StoreMetrics> for DbStoreMetrics {
+    fn from(metrics: StoreMetrics) -> Self {
+        let mut errors = [0; MAX_ERROR_KINDS];
+        errors[..ERROR_KINDS].copy_from_slice(&metrics.errors);
+
+        DbStoreMetrics {
+            size: versioned::current_size::<Self>(),
+            reads: metrics.reads,
+            writes: metrics.writes,
+            deletes: metrics.deletes,
+            bytes_read: metrics.bytes_read,
+            bytes_written: metrics.bytes_written,
+            flushes: metrics.flush.flushes,
+            flush_requests: metrics.flush.requests,
+            flush_largest_batch: metrics.flush.largest_batch,
+            flush_batch_sizes: metrics.flush.batch_sizes,
+            flush_latency_count: metrics.flush.latency.count,
+            flush_latency_sum_micros: metrics.flush.latency.sum_micros,
+            flush_latency_buckets: metrics.flush.latency.buckets,
+            error_kinds: ERROR_KINDS as u32,
+            errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::mem::offset_of;
+
+    #[test]
+    fn field_offsets_are_stable() {
+        // Fields are only ever added to the end, so these offsets must never change
+        assert_eq!(0, offset_of!(DbStoreMetrics, size));
+        assert_eq!(8, offset_of!(DbStoreMetrics, reads));
+        assert_eq!(16, offset_of!(DbStoreMetrics, writes));
+        assert_eq!(24, offset_of!(DbStoreMetrics, deletes));
+        assert_eq!(32, offset_of!(DbStoreMetrics, bytes_read));
+        assert_eq!(40, offset_of!(DbStoreMetrics, bytes_written));
+        assert_eq!(48, offset_of!(DbStoreMetrics, flushes));
+        assert_eq!(56, offset_of!(DbStoreMetrics, flush_requests));
+        assert_eq!(64, offset_of!(DbStoreMetrics, flush_largest_batch));
+        assert_eq!(72, offset_of!(DbStoreMetrics, flush_batch_sizes));
+        assert_eq!(136, offset_of!(DbStoreMetrics, flush_latency_count));
+        assert_eq!(144, offset_of!(DbStoreMetrics, flush_latency_sum_micros));
+        assert_eq!(152, offset_of!(DbStoreMetrics, flush_latency_buckets));
+        assert_eq!(232, offset_of!(DbStoreMetrics, error_kinds));
+        assert_eq!(240, offset_of!(DbStoreMetrics, errors));
+
+        assert_eq!(496, DbStoreMetrics::MIN_SIZE);
+    }
+
+    #[test]
+    fn unused_error_kinds_are_zero() {
+        let metrics = DbStoreMetrics::from(StoreMetrics {
+            errors: [1; ERROR_KINDS],
+            ..Default::default()
+        });
+
+        assert_eq!(ERROR_KINDS as u32, metrics.error_kinds);
+        assert!(metrics.errors[..ERROR_KINDS]
+            .iter()
+            .all(|count| *count == 1));
+        assert!(metrics.errors[ERROR_KINDS..]
+            .iter()
+            .all(|count| *count == 0));
+    }
+}
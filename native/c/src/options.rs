@@ -0,0 +1,288 @@
+use std::{
+    mem,
+    str::{
+        self,
+        Utf8Error,
+    },
+    time::Duration,
+};
+
+use failure_derive::*;
+use libc::size_t;
+
+use db::{
+    error::Error as DbError,
+    store::{
+        self,
+        durability::Durability,
+        encryption::{
+            Encryption,
+            EncryptionKey,
+            ENCRYPTION_KEY_SIZE,
+        },
+        index::Index,
+        record::Compression,
+        validate::Validation,
+    },
+};
+
+use crate::{
+    handle::Ref,
+    is_null::IsNull,
+    versioned::Versioned,
+};
+
+#[derive(Debug, Fail)]
+pub(super) enum Error {
+    #[fail(display = "unknown compression `{}`", _0)]
+    UnknownCompression(u32),
+    #[fail(display = "encryption keys were expected but the pointer was null")]
+    NullEncryptionKeys,
+    #[fail(display = "the active encryption key `{}` wasn't supplied", _0)]
+    MissingActiveKey(u32),
+    #[fail(display = "indexes were expected but a pointer was null")]
+    NullIndexes,
+    #[fail(display = "an index name or path isn't valid UTF8")]
+    IndexNotUtf8(#[cause] Utf8Error),
+    #[fail(display = "an index is invalid")]
+    InvalidIndex(#[cause] DbError),
+    #[fail(display = "a schema was expected but the pointer was null")]
+    NullSchema,
+    #[fail(display = "the schema is invalid")]
+    InvalidSchema(#[cause] DbError),
+    #[fail(display = "unknown durability `{}`", _0)]
+    UnknownDurability(u32),
+    #[fail(display = "`{}` must be `0` or `1` but was `{}`", name, value)]
+    InvalidFlag { name: &'static str, value: u8 },
+}
+
+/**
+Read a flag that's passed as a `u8` instead of a `bool`.
+
+Any byte can be passed across the FFI boundary, but only `0` and `1` are valid `bool`s.
+*/
+fn flag(name: &'static str, value: u8) -> Result<bool, Error> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        value => Err(Error::InvalidFlag { name, value }),
+    }
+}
+
+/**
+A key used to encrypt payloads.
+*/
+#[repr(C)]
+pub struct DbEncryptionKey {
+    id: u32,
+    key: [u8; ENCRYPTION_KEY_SIZE],
+}
+
+/**
+A secondary index over a field in JSON payloads.
+*/
+#[repr(C)]
+pub struct DbIndex<'a> {
+    name: Ref<'a, u8>,
+    name_len: size_t,
+    path: Ref<'a, u8>,
+    path_len: size_t,
+}
+
+/**
+Options for opening a store.
+
+`compression` is `0` for no compression and `1` for LZ4.
+
+If `encryption_keys_len` is `0` then payloads aren't encrypted.
+Otherwise, the key with the id `active_encryption_key` is used to encrypt new payloads
+and any other keys are only used to decrypt existing ones.
+
+If `indexes_len` is `0` then no indexes are maintained.
+
+`checksums` and `validate_json` are flags that must be `0` or `1`.
+
+If `validate_json` is `1` then payloads must be well-formed JSON to be written.
+If `schema_len` is also non-zero then payloads must match the JSON Schema in `schema`.
+
+`size` must be set to the size of the struct.
+*/
+#[repr(C)]
+#[derive(Default)]
+pub struct DbStoreOptions<'a> {
+    size: u32,
+    checksums: u8,
+    compression: u32,
+    encryption_keys: Ref<'a, DbEncryptionKey>,
+    encryption_keys_len: size_t,
+    active_encryption_key: u32,
+    indexes: Ref<'a, DbIndex<'a>>,
+    indexes_len: size_t,
+    validate_json: u8,
+    schema: Ref<'a, u8>,
+    schema_len: size_t,
+}
+
+impl<'a> Versioned for DbStoreOptions<'a> {
+    const MIN_SIZE: usize = mem::size_of::<DbStoreOptions>();
+}
+
+impl<'a> DbStoreOptions<'a> {
+    pub(super) fn to_options(&self) -> Result<store::Options, Error> {
+        let compression = match self.compression {
+            0 => Compression::None,
+            1 => Compression::Lz4,
+            compression => return Err(Error::UnknownCompression(compression)),
+        };
+
+        Ok(store::Options {
+            checksums: flag("checksums", self.checksums)?,
+            compression,
+            encryption: self.to_encryption()?,
+            indexes: self.to_indexes()?,
+            validation: self.to_validation()?,
+        })
+    }
+
+    fn to_encryption(&self) -> Result<Option<Encryption>, Error> {
+        if self.encryption_keys_len == 0 {
+            return Ok(None);
+        }
+
+        if self.encryption_keys.is_null() {
+            return Err(Error::NullEncryptionKeys);
+        }
+
+        let keys = unsafe_block!("The keys live as long as the options and the length is within the keys" => self.encryption_keys.as_slice(self.encryption_keys_len));
+
+        let active = keys
+            .iter()
+            .find(|key| key.id == self.active_encryption_key)
+            .ok_or(Error::MissingActiveKey(self.active_encryption_key))?;
+
+        let encryption = keys.iter().filter(|key| key.id != active.id).fold(
+            Encryption::new(EncryptionKey::from_bytes(active.id, &active.key)),
+            |encryption, key| encryption.with_retired(EncryptionKey::from_bytes(key.id, &key.key)),
+        );
+
+        Ok(Some(encryption))
+    }
+
+    fn to_indexes(&self) -> Result<Vec<Index>, Error> {
+        if self.indexes_len == 0 {
+            return Ok(Vec::new());
+        }
+
+        if self.indexes.is_null() {
+            return Err(Error::NullIndexes);
+        }
+
+        let indexes = unsafe_block!("The indexes live as long as the options and the length is within the indexes" => self.indexes.as_slice(self.indexes_len));
+
+        indexes
+            .iter()
+            .map(|index| {
+                if index.name.is_null() || index.path.is_null() {
+                    return Err(Error::NullIndexes);
+                }
+
+                let name = unsafe_block!("The name lives as long as the options and the length is within the name" => index.name.as_bytes(index.name_len));
+                let path = unsafe_block!("The path lives as long as the options and the length is within the path" => index.path.as_bytes(index.path_len));
+
+                let name = str::from_utf8(name).map_err(Error::IndexNotUtf8)?;
+                let path = str::from_utf8(path).map_err(Error::IndexNotUtf8)?;
+
+                Index::new(name, path).map_err(Error::InvalidIndex)
+            })
+            .collect()
+    }
+
+    fn to_validation(&self) -> Result<Option<Validation>, Error> {
+        if !flag("validate_json", self.validate_json)? {
+            return Ok(None);
+        }
+
+        if self.schema_len == 0 {
+            return Ok(Some(Validation::json()));
+        }
+
+        if self.schema.is_null() {
+            return Err(Error::NullSchema);
+        }
+
+        let schema = unsafe_block!("The schema lives as long as the options and the length is within the schema" => self.schema.as_bytes(self.schema_len));
+
+        Validation::schema(schema)
+            .map(Some)
+            .map_err(Error::InvalidSchema)
+    }
+}
+
+/**
+Options for beginning a writer.
+
+`durability` is one of:
+
+- `0` to never explicitly flush.
+- `1` to flush when the writer is ended.
+- `2` to flush after every write.
+- `3` to flush within `group_commit_ms` of the writer being ended, sharing the flush with other writers.
+  Ending the writer waits for the flush.
+
+`size` must be set to the size of the struct.
+*/
+#[repr(C)]
+#[derive(Default)]
+pub struct DbWriteOptions {
+    size: u32,
+    durability: u32,
+    group_commit_ms: u32,
+}
+
+impl Versioned for DbWriteOptions {
+    const MIN_SIZE: usize = mem::size_of::<DbWriteOptions>();
+}
+
+impl DbWriteOptions {
+    pub(super) fn to_options(&self) -> Result<store::WriteOptions, Error> {
+        let durability = match self.durability {
+            0 => Durability::None,
+            1 => Durability::FlushOnComplete,
+            2 => Durability::FlushEveryWrite,
+            3 => Durability::GroupCommit(Duration::from_millis(self.group_commit_ms.into())),
+            durability => return Err(Error::UnknownDurability(durability)),
+        };
+
+        Ok(store::WriteOptions { durability })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_must_be_zero_or_one() {
+        let options = DbStoreOptions {
+            checksums: 2,
+            ..Default::default()
+        };
+
+        assert_match!(
+            Err(Error::InvalidFlag {
+                name: "checksums",
+                value: 2
+            }) = options.to_options()
+        );
+
+        let options = DbStoreOptions {
+            checksums: 1,
+            validate_json: 0,
+            ..Default::default()
+        };
+
+        let options = options.to_options().unwrap();
+        assert!(options.checksums);
+        assert!(options.validation.is_none());
+    }
+}
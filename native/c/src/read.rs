@@ -25,10 +25,28 @@ pub(super) fn into_fixed_buffer(
     buf: &mut [u8],
     key: &mut Out<DbKey>,
     actual_value_len: &mut Out<usize>,
+) -> DbResult {
+    let result = payload_into_fixed_buffer(&mut data.payload, buf, actual_value_len);
+
+    if result.is_ok() {
+        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => key.init(DbKey(data.key.to_bytes())));
+    }
+
+    result
+}
+
+/// Read a payload into a given buffer.
+///
+/// If the payload doesn't fit then `DbResult::BufferTooSmall` will be returned
+/// and `actual_value_len` will contain the minimum size of the buffer needed.
+pub(super) fn payload_into_fixed_buffer(
+    payload: &mut impl Read,
+    buf: &mut [u8],
+    actual_value_len: &mut Out<usize>,
 ) -> DbResult {
     // A zero-sized input buffer will cause an infinite loop below
     // if we let it through.
-    if buf.len() == 0 {
+    if buf.is_empty() {
         Err(Error::ZeroSizedBuf)?;
     }
 
@@ -40,12 +58,12 @@ pub(super) fn into_fixed_buffer(
 
         // If the buffer is full, continue writing over the previous data
         // This lets us figure out the actual payload size to return
-        if buf.len() == 0 {
+        if buf.is_empty() {
             head = 0;
             continue 'read;
         }
 
-        match data.payload.read(buf)? {
+        match payload.read(buf)? {
             // The complete payload has been read, break and return
             0 => break 'read,
             // Continue reading the payload
@@ -56,16 +74,13 @@ pub(super) fn into_fixed_buffer(
         }
     }
 
+    unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_value_len.init(written));
+
     // If we wrote more bytes than the buffer could fit, return the required size
     if written > buf.len() {
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_value_len.init(written));
-
         DbResult::buffer_too_small()
     // The entire payload fit in the buffer
     } else {
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => actual_value_len.init(written));
-        unsafe_block!("The out pointer is valid and not mutably aliased elsewhere" => key.init(DbKey(data.key.to_bytes())));
-
         DbResult::ok()
     }
 }
@@ -1,30 +1,138 @@
 use std::{
     any::Any,
     cell::RefCell,
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    convert::Infallible,
     fmt::Write,
-    ops::Try,
+    io,
+    ops::{
+        ControlFlow,
+        FromResidual,
+        Residual,
+        Try,
+    },
     panic::{
         catch_unwind,
         UnwindSafe,
     },
-    sync::atomic::{
-        AtomicU32,
-        Ordering,
+    str::Utf8Error,
+    sync::{
+        atomic::{
+            AtomicU32,
+            Ordering,
+        },
+        Mutex,
     },
 };
 
 use failure::Fail;
 
-use crate::std_ext::prelude::*;
+use db::{
+    data::{
+        Key,
+        KEY_SIZE,
+    },
+    error::ErrorKind,
+};
 
-static LAST_ERR_ID: AtomicU32 = AtomicU32::new(0);
+use crate::{
+    handle,
+    logging,
+    options,
+    read,
+    std_ext::prelude::*,
+    versioned,
+};
+
+static LAST_ERR_ID: AtomicU32 = AtomicU32::new(1);
 
 fn next_err_id() -> u32 {
-    LAST_ERR_ID.fetch_add(1, Ordering::SeqCst)
+    // Successful results use `0`, so it's never given to an error, even when the ids wrap
+    loop {
+        let id = LAST_ERR_ID.fetch_add(1, Ordering::SeqCst);
+
+        if id != 0 {
+            return id;
+        }
+    }
 }
 
 thread_local! {
-    static LAST_RESULT: RefCell<Option<LastResult>> = RefCell::new(None);
+    static LAST_RESULT: RefCell<Option<LastResult>> = const { RefCell::new(None) };
+}
+
+/**
+The number of errors retained in the registry when none is configured.
+*/
+pub(super) const DEFAULT_ERROR_RETENTION: usize = 256;
+
+lazy_static! {
+    static ref ERRORS: Mutex<Registry> = Mutex::new(Registry::new(DEFAULT_ERROR_RETENTION));
+}
+
+/**
+A bounded history of errors, indexed by their result id.
+
+Unlike the thread-local last result, errors in the registry can be
+fetched from any thread and survive later calls.
+Once the registry is full the oldest errors are evicted first.
+*/
+struct Registry {
+    retention: usize,
+    order: VecDeque<u32>,
+    errors: HashMap<u32, String>,
+}
+
+impl Registry {
+    fn new(retention: usize) -> Self {
+        Registry {
+            retention,
+            order: VecDeque::new(),
+            errors: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, id: u32, err: String) {
+        if self.retention == 0 {
+            return;
+        }
+
+        if self.errors.insert(id, err).is_none() {
+            self.order.push_back(id);
+        }
+
+        self.evict();
+    }
+
+    fn get(&self, id: u32) -> Option<&str> {
+        self.errors.get(&id).map(|err| err.as_ref())
+    }
+
+    fn set_retention(&mut self, retention: usize) {
+        self.retention = retention;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.order.len() > self.retention {
+            if let Some(id) = self.order.pop_front() {
+                self.errors.remove(&id);
+            }
+        }
+    }
+}
+
+fn retain_error(value: DbResult, err: &str) {
+    if value.as_err().is_none() {
+        return;
+    }
+
+    if let Ok(mut errors) = ERRORS.lock() {
+        errors.insert(value.id, err.to_owned());
+    }
 }
 
 /**
@@ -40,16 +148,38 @@ pub struct DbResult {
     id: u32,
 }
 
+/**
+The kind of a result.
+
+The values of kinds are stable, new kinds are only ever added to the end.
+*/
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Kind {
-    Ok,
+    Ok = 0,
+
+    Done = 1,
+    BufferTooSmall = 2,
+
+    ArgumentNull = 3,
+    InternalError = 4,
 
-    Done,
-    BufferTooSmall,
+    Corrupted = 5,
+    DecryptionFailed = 6,
+    ValidationFailed = 7,
 
-    ArgumentNull,
-    InternalError,
+    Io = 8,
+    InvalidUtf8 = 9,
+    KeyTooLong = 10,
+    InvalidFilter = 11,
+    InvalidIndex = 12,
+    UnknownIndex = 13,
+    InvalidSchema = 14,
+    InvalidArgument = 15,
+
+    InvalidHandle = 16,
+
+    RemovedUnreadable = 17,
 }
 
 impl DbResult {
@@ -108,12 +238,90 @@ impl DbResult {
         self.kind == Kind::InternalError
     }
 
+    pub fn is_corrupted(&self) -> bool {
+        self.kind == Kind::Corrupted
+    }
+
+    pub fn is_decryption_failed(&self) -> bool {
+        self.kind == Kind::DecryptionFailed
+    }
+
+    pub fn is_validation_failed(&self) -> bool {
+        self.kind == Kind::ValidationFailed
+    }
+
+    pub fn is_io(&self) -> bool {
+        self.kind == Kind::Io
+    }
+
+    pub fn is_invalid_utf8(&self) -> bool {
+        self.kind == Kind::InvalidUtf8
+    }
+
+    pub fn is_key_too_long(&self) -> bool {
+        self.kind == Kind::KeyTooLong
+    }
+
+    pub fn is_invalid_filter(&self) -> bool {
+        self.kind == Kind::InvalidFilter
+    }
+
+    pub fn is_invalid_index(&self) -> bool {
+        self.kind == Kind::InvalidIndex
+    }
+
+    pub fn is_unknown_index(&self) -> bool {
+        self.kind == Kind::UnknownIndex
+    }
+
+    pub fn is_invalid_schema(&self) -> bool {
+        self.kind == Kind::InvalidSchema
+    }
+
+    pub fn is_invalid_argument(&self) -> bool {
+        self.kind == Kind::InvalidArgument
+    }
+
+    pub fn is_invalid_handle(&self) -> bool {
+        self.kind == Kind::InvalidHandle
+    }
+
+    pub(super) fn removed_unreadable() -> Self {
+        DbResult::error(Kind::RemovedUnreadable)
+    }
+
+    pub fn is_removed_unreadable(&self) -> bool {
+        self.kind == Kind::RemovedUnreadable
+    }
+
+    fn error(kind: Kind) -> Self {
+        DbResult {
+            kind,
+            id: next_err_id(),
+        }
+    }
+
     pub fn as_err(&self) -> Option<&'static str> {
         match self.kind {
             Kind::Ok | Kind::Done => None,
             Kind::ArgumentNull => Some("a required argument was null"),
             Kind::BufferTooSmall => Some("a supplied buffer was too small"),
             Kind::InternalError => Some("an internal error occurred"),
+            Kind::Corrupted => Some("the store contains corrupted data"),
+            Kind::DecryptionFailed => Some("a record couldn't be decrypted"),
+            Kind::ValidationFailed => Some("a payload failed validation"),
+            Kind::Io => Some("reading from or writing to storage failed"),
+            Kind::InvalidUtf8 => Some("a string argument isn't valid UTF8"),
+            Kind::KeyTooLong => Some("a key is longer than the max allowed"),
+            Kind::InvalidFilter => Some("a filter is invalid"),
+            Kind::InvalidIndex => Some("an index is invalid"),
+            Kind::UnknownIndex => Some("an index doesn't exist"),
+            Kind::InvalidSchema => Some("a schema is invalid"),
+            Kind::InvalidArgument => Some("an argument is invalid"),
+            Kind::InvalidHandle => Some("a handle is invalid or has already been released"),
+            Kind::RemovedUnreadable => {
+                Some("a record was removed but its payload couldn't be read")
+            }
         }
     }
 
@@ -123,10 +331,18 @@ impl DbResult {
             "context can only be attached to errors"
         );
 
-        let err = Some(format_error(&e));
+        let err = format_error(&e);
+        let details = Some(ErrorDetails::from_fail(&e));
+
+        retain_error(self, &err);
+        let err = Some(err);
 
         LAST_RESULT.with(|last_result| {
-            *last_result.borrow_mut() = Some(LastResult { value: self, err });
+            *last_result.borrow_mut() = Some(LastResult {
+                value: self,
+                err,
+                details,
+            });
         });
 
         self
@@ -149,27 +365,28 @@ impl DbResult {
                         .borrow_mut()
                         .map_mut(|last_result| {
                             last_result.value = db_result;
-                            last_result.err.or_else_mut(extract_err);
-                        })
-                        .get_or_insert_with(|| LastResult {
-                            value: db_result,
-                            err: extract_err(),
+                            last_result.or_err_else(extract_err);
                         })
+                        .get_or_insert_with(|| LastResult::new(db_result, extract_err()))
                         .value
                 }
                 Err(e) => {
                     let extract_panic =
                         || extract_panic(&e).map(|s| format!("internal panic with '{}'", s));
 
+                    log::error!(
+                        "caught a panic at the FFI boundary: {}",
+                        extract_panic().as_deref().unwrap_or("unknown panic")
+                    );
+
                     // Set the last error to the panic message if it's not already set
                     last_result
                         .borrow_mut()
                         .map_mut(|last_result| {
-                            last_result.err.or_else_mut(extract_panic);
+                            last_result.or_err_else(extract_panic);
                         })
-                        .get_or_insert_with(|| LastResult {
-                            value: DbResult::internal_error(),
-                            err: extract_panic(),
+                        .get_or_insert_with(|| {
+                            LastResult::new(DbResult::internal_error(), extract_panic())
                         })
                         .value
                 }
@@ -193,6 +410,42 @@ impl DbResult {
             f(last_result)
         })
     }
+
+    /**
+    Get the error message for a result from the registry of retained errors.
+    */
+    pub(super) fn with_retained<R>(id: u32, f: impl FnOnce(Option<&str>) -> R) -> R {
+        let errors = ERRORS.lock().unwrap_or_else(|e| e.into_inner());
+
+        f(errors.get(id))
+    }
+
+    /**
+    Set the number of errors retained in the registry.
+
+    Setting the retention to `0` disables the registry.
+    */
+    pub(super) fn set_error_retention(retention: usize) {
+        let mut errors = ERRORS.lock().unwrap_or_else(|e| e.into_inner());
+
+        errors.set_retention(retention);
+    }
+
+    pub(super) fn with_last_details<R>(
+        f: impl FnOnce(Option<(DbResult, Option<&ErrorDetails>)>) -> R,
+    ) -> R {
+        LAST_RESULT.with(|last_result| {
+            let last_result = last_result.borrow();
+
+            let last_result = last_result.as_ref().map(|last_result| {
+                let details = last_result.value.as_err().and(last_result.details.as_ref());
+
+                (last_result.value, details)
+            });
+
+            f(last_result)
+        })
+    }
 }
 
 /**
@@ -206,44 +459,108 @@ where
     E: Fail,
 {
     fn from(e: E) -> Self {
-        DbResult::internal_error().context(e)
+        DbResult::error(kind_of(&e)).context(e)
     }
 }
 
+/**
+Find the most specific kind for an error.
+
+The first `db` error in the chain determines the kind.
+Otherwise the kind is determined by errors raised at the FFI boundary.
+*/
+fn kind_of(e: &dyn Fail) -> Kind {
+    let db_err = e
+        .iter_chain()
+        .filter_map(|cause| cause.downcast_ref::<db::error::Error>())
+        .next();
+
+    if let Some(db_err) = db_err {
+        return match db_err.kind() {
+            ErrorKind::Io => Kind::Io,
+            ErrorKind::Corrupted => Kind::Corrupted,
+            ErrorKind::DecryptionFailed => Kind::DecryptionFailed,
+            ErrorKind::ValidationFailed => Kind::ValidationFailed,
+            ErrorKind::KeyTooLong => Kind::KeyTooLong,
+            ErrorKind::InvalidFilter => Kind::InvalidFilter,
+            ErrorKind::InvalidIndex => Kind::InvalidIndex,
+            ErrorKind::UnknownIndex => Kind::UnknownIndex,
+            ErrorKind::InvalidSchema => Kind::InvalidSchema,
+            ErrorKind::InvalidArgument => Kind::InvalidArgument,
+            ErrorKind::Other => Kind::InternalError,
+        };
+    }
+
+    for cause in e.iter_chain() {
+        if cause.downcast_ref::<Utf8Error>().is_some() {
+            return Kind::InvalidUtf8;
+        }
+
+        if cause.downcast_ref::<handle::Error>().is_some() {
+            return Kind::InvalidHandle;
+        }
+
+        if cause.downcast_ref::<options::Error>().is_some()
+            || cause.downcast_ref::<read::Error>().is_some()
+            || cause.downcast_ref::<logging::Error>().is_some()
+            || cause.downcast_ref::<versioned::Error>().is_some()
+        {
+            return Kind::InvalidArgument;
+        }
+    }
+
+    Kind::InternalError
+}
+
 /**
 Allow carrying standard `Result`s as `DbResult`s.
 */
 impl Try for DbResult {
-    type Ok = Self;
-    type Error = Self;
+    type Output = Self;
+    type Residual = Self;
+
+    fn from_output(result: Self) -> Self {
+        if result.as_err().is_some() {
+            panic!("attempted to return error code `{:?}` as success", result);
+        }
+
+        result
+    }
 
-    fn into_result(self) -> Result<<Self as Try>::Ok, <Self as Try>::Error> {
+    fn branch(self) -> ControlFlow<Self, Self> {
         match self.kind {
-            Kind::Ok | Kind::Done => Ok(self),
-            _ => Err(self),
+            Kind::Ok | Kind::Done => ControlFlow::Continue(self),
+            _ => ControlFlow::Break(self),
         }
     }
+}
+
+impl Residual<DbResult> for DbResult {
+    type TryType = DbResult;
+}
 
-    fn from_error(result: Self::Error) -> Self {
+impl FromResidual for DbResult {
+    fn from_residual(result: Self) -> Self {
         if result.as_err().is_none() {
-            panic!(format!(
+            panic!(
                 "attempted to return success code `{:?}` as an error",
                 result
-            ));
+            );
         }
 
         result
     }
+}
 
-    fn from_ok(result: <Self as Try>::Ok) -> Self {
-        if result.as_err().is_some() {
-            panic!(format!(
-                "attempted to return error code `{:?}` as success",
-                result
-            ));
+impl<E> FromResidual<Result<Infallible, E>> for DbResult
+where
+    DbResult: From<E>,
+{
+    fn from_residual(result: Result<Infallible, E>) -> Self {
+        match result {
+            Ok(never) => match never {},
+            Err(e) => DbResult::from(e),
         }
-
-        result
     }
 }
 
@@ -251,6 +568,86 @@ impl Try for DbResult {
 struct LastResult {
     value: DbResult,
     err: Option<String>,
+    details: Option<ErrorDetails>,
+}
+
+impl LastResult {
+    fn new(value: DbResult, err: Option<String>) -> Self {
+        let details = err.clone().map(ErrorDetails::from_msg);
+
+        if let Some(ref err) = err {
+            retain_error(value, err);
+        }
+
+        LastResult {
+            value,
+            err,
+            details,
+        }
+    }
+
+    fn or_err_else(&mut self, f: impl FnOnce() -> Option<String>) {
+        if self.err.is_none() {
+            *self = LastResult::new(self.value, f());
+        }
+    }
+}
+
+/**
+Structured details about an error.
+*/
+#[derive(Debug, Clone, Default)]
+pub(super) struct ErrorDetails {
+    /**
+    The messages of the error and each of its causes, outermost first.
+    */
+    pub(super) causes: Vec<String>,
+    pub(super) backtrace: Option<String>,
+    /**
+    The key of the record involved in the error.
+    */
+    pub(super) key: Option<[u8; KEY_SIZE]>,
+    /**
+    The OS error code of the IO error that caused the error.
+    */
+    pub(super) os_error: Option<i32>,
+}
+
+impl ErrorDetails {
+    fn from_fail(err: &dyn Fail) -> Self {
+        let causes = err.iter_chain().map(|cause| cause.to_string()).collect();
+
+        let backtrace = err
+            .iter_chain()
+            .filter_map(|cause| cause.backtrace())
+            .map(|backtrace| backtrace.to_string())
+            .find(|backtrace| !backtrace.is_empty());
+
+        let db_err = err
+            .iter_chain()
+            .filter_map(|cause| cause.downcast_ref::<db::error::Error>())
+            .next();
+
+        let os_error = db_err.and_then(|db_err| db_err.os_error()).or_else(|| {
+            err.iter_chain()
+                .filter_map(|cause| cause.downcast_ref::<io::Error>())
+                .find_map(|err| err.raw_os_error())
+        });
+
+        ErrorDetails {
+            causes,
+            backtrace,
+            key: db_err.and_then(|db_err| db_err.key()).map(Key::to_bytes),
+            os_error,
+        }
+    }
+
+    fn from_msg(msg: String) -> Self {
+        ErrorDetails {
+            causes: vec![msg],
+            ..Default::default()
+        }
+    }
 }
 
 fn format_error(err: &dyn Fail) -> String {
@@ -279,10 +676,9 @@ fn format_error(err: &dyn Fail) -> String {
 fn extract_panic(err: &Box<dyn Any + Send + 'static>) -> Option<String> {
     if let Some(err) = err.downcast_ref::<String>() {
         Some(err.clone())
-    } else if let Some(err) = err.downcast_ref::<&'static str>() {
-        Some((*err).to_owned())
     } else {
-        None
+        err.downcast_ref::<&'static str>()
+            .map(|err| (*err).to_owned())
     }
 }
 
@@ -296,6 +692,8 @@ mod tests {
 
     use super::*;
 
+    use crate::handle::Out;
+
     #[derive(Debug, Fail)]
     enum TestInnerError {
         #[fail(display = "an inner error message")]
@@ -345,7 +743,7 @@ mod tests {
 
     #[test]
     fn last_result_catch_ok() {
-        let result = DbResult::catch(|| DbResult::ok());
+        let result = DbResult::catch(DbResult::ok);
 
         assert_eq!(Kind::Ok, result.kind);
 
@@ -379,7 +777,7 @@ mod tests {
 
     #[test]
     fn last_result_catch_err_return() {
-        let result = DbResult::catch(|| DbResult::argument_null());
+        let result = DbResult::catch(DbResult::argument_null);
 
         assert_eq!(Kind::ArgumentNull, result.kind);
 
@@ -406,4 +804,105 @@ mod tests {
             });
         });
     }
+
+    #[test]
+    fn db_result_from_error_kind() {
+        let key_too_long = db::data::Key::from_slice(&[0; 17]).err().unwrap();
+        assert_eq!(Kind::KeyTooLong, DbResult::from(key_too_long).kind);
+
+        let not_utf8 = String::from_utf8(vec![0xff]).unwrap_err().utf8_error();
+        assert_eq!(Kind::InvalidUtf8, DbResult::from(not_utf8).kind);
+
+        assert_eq!(
+            Kind::InvalidHandle,
+            DbResult::from(handle::Error::InvalidHandle(1)).kind
+        );
+
+        assert_eq!(
+            Kind::InternalError,
+            DbResult::from(TestError::Variant(TestInnerError::Variant)).kind
+        );
+    }
+
+    #[test]
+    fn last_result_details() {
+        thread::spawn(|| {
+            let _ = DbResult::catch(|| {
+                DbResult::internal_error().context(TestError::Variant(TestInnerError::Variant))
+            });
+
+            DbResult::with_last_details(|last_result| {
+                assert_match!(Some((_, Some(details))) = last_result => {
+                    assert_eq!(
+                        vec!["an error message", "an inner error message"],
+                        details.causes
+                    );
+                    assert!(details.key.is_none());
+                });
+            });
+
+            // Errors without a cause chain have a single cause
+            let _ = DbResult::catch(DbResult::argument_null);
+
+            DbResult::with_last_details(|last_result| {
+                assert_match!(Some((_, Some(details))) = last_result => {
+                    assert_eq!(1, details.causes.len());
+                });
+            });
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[test]
+    fn registry_evicts_oldest() {
+        let mut registry = Registry::new(2);
+
+        registry.insert(1, "first".to_owned());
+        registry.insert(2, "second".to_owned());
+        registry.insert(3, "third".to_owned());
+
+        assert_eq!(None, registry.get(1));
+        assert_eq!(Some("second"), registry.get(2));
+        assert_eq!(Some("third"), registry.get(3));
+
+        registry.set_retention(1);
+        assert_eq!(None, registry.get(2));
+
+        registry.set_retention(0);
+        registry.insert(4, "fourth".to_owned());
+        assert_eq!(None, registry.get(4));
+    }
+
+    #[test]
+    fn retained_error_is_available_from_other_threads() {
+        let result = thread::spawn(|| {
+            DbResult::catch(|| DbResult::internal_error().context(TestInnerError::Variant))
+        })
+        .join()
+        .unwrap();
+
+        DbResult::with_retained(result.id, |err| {
+            assert!(err.unwrap().contains("an inner error message"));
+        });
+    }
+
+    #[test]
+    fn result_details_of_ok_are_done() {
+        // Make sure there's at least one retained error
+        let err = DbResult::catch(DbResult::internal_error);
+        assert_ne!(DbResult::ok().id, err.id);
+
+        let mut buf = [0u8; 64];
+        let mut actual = 0;
+
+        let result = unsafe_block!("The buffer and out pointer are valid for writes" => crate::db_result_details(
+            DbResult::ok().id,
+            Out::from_ptr(buf.as_mut_ptr()),
+            buf.len(),
+            Out::from_ptr(&mut actual),
+        ));
+
+        assert!(result.is_done());
+    }
 }
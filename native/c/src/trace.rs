@@ -0,0 +1,314 @@
+/*!
+Recording a span for every FFI call.
+
+Tracing is off until it's enabled with a capacity. While it's on, each call
+through an `#[ffi]` function records its name, the sizes of its arguments, its result,
+and how long it took into a ring buffer that callers periodically drain.
+If the buffer fills up before it's drained then the oldest spans are dropped.
+Calls that enable or drain tracing aren't traced themselves.
+*/
+
+use std::{
+    collections::VecDeque,
+    mem,
+    ptr,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Mutex,
+    },
+    time::{
+        Instant,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use libc::size_t;
+
+use crate::{
+    versioned::{
+        self,
+        Versioned,
+    },
+    DbResult,
+};
+
+/**
+A completed FFI call.
+
+The name is a static string that remains valid for the life of the process.
+
+`size` must be set to the size of the struct in the first span of a buffer before it's written.
+*/
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DbSpan {
+    size: u32,
+    name: *const u8,
+    name_len: size_t,
+    start_unix_micros: u64,
+    duration_nanos: u64,
+    arg_bytes: u64,
+    result: DbResult,
+}
+
+unsafe_impl!("The name is a static string that's never written to" => impl Sync for DbSpan {});
+
+impl Default for DbSpan {
+    fn default() -> Self {
+        DbSpan {
+            size: 0,
+            name: ptr::null(),
+            name_len: 0,
+            start_unix_micros: 0,
+            duration_nanos: 0,
+            arg_bytes: 0,
+            result: DbResult::ok(),
+        }
+    }
+}
+
+impl Versioned for DbSpan {
+    const MIN_SIZE: usize = mem::size_of::<DbSpan>();
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref SPANS: Mutex<Buffer> = Mutex::new(Buffer::new(0));
+}
+
+/**
+The spans recorded since the last drain.
+*/
+struct Buffer {
+    capacity: usize,
+    spans: VecDeque<Span>,
+    dropped: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    name: &'static str,
+    start_unix_micros: u64,
+    duration_nanos: u64,
+    arg_bytes: u64,
+    result: DbResult,
+}
+
+impl Buffer {
+    fn new(capacity: usize) -> Self {
+        Buffer {
+            capacity,
+            // The capacity is supplied by callers, so the buffer only grows as spans are pushed
+            spans: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, span: Span) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.spans.len() >= self.capacity {
+            self.spans.pop_front();
+            self.dropped += 1;
+        }
+
+        self.spans.push_back(span);
+    }
+}
+
+/**
+A span that's been started for an FFI call.
+*/
+pub(super) struct Started {
+    name: &'static str,
+    start: Instant,
+    start_unix_micros: u64,
+    arg_bytes: u64,
+}
+
+/**
+Start a span for an FFI call, if tracing is enabled.
+*/
+pub(super) fn start(name: &'static str, arg_bytes: u64) -> Option<Started> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let start_unix_micros = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_micros() as u64)
+        .unwrap_or_default();
+
+    Some(Started {
+        name,
+        start: Instant::now(),
+        start_unix_micros,
+        arg_bytes,
+    })
+}
+
+impl Started {
+    /**
+    Finish the span with the result of the call.
+    */
+    pub(super) fn end(self, result: DbResult) {
+        let span = Span {
+            name: self.name,
+            start_unix_micros: self.start_unix_micros,
+            duration_nanos: self.start.elapsed().as_nanos() as u64,
+            arg_bytes: self.arg_bytes,
+            result,
+        };
+
+        if let Ok(mut spans) = SPANS.lock() {
+            spans.push(span);
+        }
+    }
+}
+
+/**
+Start recording spans into a buffer with the given capacity.
+
+A capacity of `0` stops recording. Any spans that haven't been drained are discarded.
+*/
+pub(super) fn enable(capacity: usize) {
+    let mut spans = SPANS.lock().expect("failed to lock spans");
+
+    *spans = Buffer::new(capacity);
+    ENABLED.store(capacity > 0, Ordering::SeqCst);
+}
+
+/**
+Move recorded spans into the given buffer, oldest first.
+
+Returns the number of spans moved, and the number of spans that were dropped
+because the buffer was full since the last time spans were drained.
+*/
+pub(super) fn drain(len: usize, mut into: impl FnMut(usize, DbSpan)) -> (usize, u64) {
+    let mut spans = SPANS.lock().expect("failed to lock spans");
+
+    let drained = len.min(spans.spans.len());
+    for (i, span) in spans.spans.drain(..drained).enumerate() {
+        into(
+            i,
+            DbSpan {
+                size: versioned::current_size::<DbSpan>(),
+                name: span.name.as_ptr(),
+                name_len: span.name.len(),
+                start_unix_micros: span.start_unix_micros,
+                duration_nanos: span.duration_nanos,
+                arg_bytes: span.arg_bytes,
+                result: span.result,
+            },
+        );
+    }
+
+    (drained, mem::replace(&mut spans.dropped, 0))
+}
+
+/**
+The size of a buffer length argument passed across an FFI boundary.
+
+Only arguments listed in `#[ffi(lengths(..))]` contribute to the total for a span,
+so scalar arguments like a capacity or an index are never counted as bytes.
+*/
+pub(super) trait ArgSize {
+    fn arg_size(&self) -> u64;
+}
+
+impl ArgSize for usize {
+    fn arg_size(&self) -> u64 {
+        *self as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        mem::MaybeUninit,
+        slice,
+    };
+
+    use crate::handle::{
+        Out,
+        Ref,
+    };
+
+    fn span(name: &'static str) -> Span {
+        Span {
+            name,
+            start_unix_micros: 0,
+            duration_nanos: 0,
+            arg_bytes: 0,
+            result: DbResult::ok(),
+        }
+    }
+
+    #[test]
+    fn buffer_drops_oldest() {
+        let mut buffer = Buffer::new(2);
+
+        buffer.push(span("a"));
+        buffer.push(span("b"));
+        buffer.push(span("c"));
+
+        assert_eq!(1, buffer.dropped);
+        assert_eq!(
+            vec!["b", "c"],
+            buffer.spans.iter().map(|s| s.name).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn ffi_calls_are_traced() {
+        let result =
+            unsafe_block!("The function has no pointer arguments" => crate::db_trace_enable(1024));
+        assert_eq!(DbResult::ok(), result);
+
+        let result = unsafe_block!("The function has no arguments" => crate::db_test_ok());
+        assert_eq!(DbResult::ok(), result);
+
+        // The path isn't valid UTF8, so the store is never opened
+        let path = [0xff, 0xfe, 0xfd, 0xfc, 0xfb];
+        let mut store = MaybeUninit::uninit();
+        let result = unsafe_block!("The path and out pointer are valid" => crate::db_store_open(
+            Ref::from_ptr(path.as_ptr()),
+            path.len(),
+            Out::from_ptr(store.as_mut_ptr()),
+        ));
+        assert_ne!(DbResult::ok(), result);
+
+        let mut spans = Vec::new();
+        let (drained, _) = drain(1024, |_, span| spans.push(span));
+
+        enable(0);
+
+        let names: Vec<_> = spans
+            .iter()
+            .map(|span| unsafe_block!("Span names are static strings" => slice::from_raw_parts(span.name, span.name_len)))
+            .collect();
+
+        assert_eq!(drained, spans.len());
+        assert!(names
+            .iter()
+            .zip(&spans)
+            .any(|(name, span)| *name == b"db_test_ok" && span.result == DbResult::ok()));
+        assert!(!names.contains(&&b"db_trace_enable"[..]));
+
+        // Only the length of the path is counted
+        assert!(names
+            .iter()
+            .zip(&spans)
+            .any(|(name, span)| *name == b"db_store_open" && span.arg_bytes == path.len() as u64));
+    }
+}
@@ -0,0 +1,187 @@
+/*!
+Structs that are versioned by their size.
+
+Option and stats structs begin with a `size: u32` field that's set by the caller to the size
+of the struct they were compiled against. Fields are only ever added to the end of a struct,
+so the size tells us which fields the caller knows about.
+
+Options that are smaller than the current struct have the fields the caller doesn't know about defaulted.
+Options that are larger than the current struct are accepted as long as the fields we don't know about are zero.
+
+Stats are truncated to the caller's size when they're written, and their `size` is set
+to the number of bytes that were written.
+*/
+
+use std::mem;
+
+use failure_derive::*;
+
+/**
+A `#[repr(C)]` struct whose first field is its size as a `u32`.
+*/
+pub trait Versioned: Default {
+    /**
+    The size of the first version of the struct.
+
+    When fields are added to a struct this must remain the size of the first version.
+    */
+    const MIN_SIZE: usize;
+}
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(
+        display = "the struct size `{}` is smaller than the minimum `{}`",
+        size, min
+    )]
+    TooSmall { size: usize, min: usize },
+    #[fail(
+        display = "the struct has fields beyond `{}` bytes that aren't supported",
+        _0
+    )]
+    UnsupportedFields(usize),
+}
+
+/**
+The size of the current version of a struct.
+*/
+pub(crate) fn current_size<T: Versioned>() -> u32 {
+    mem::size_of::<T>() as u32
+}
+
+/**
+Check the size of a struct supplied by a caller.
+*/
+pub(crate) fn check_size<T: Versioned>(size: u32) -> Result<usize, Error> {
+    let size = size as usize;
+
+    if size < T::MIN_SIZE {
+        return Err(Error::TooSmall {
+            size,
+            min: T::MIN_SIZE,
+        });
+    }
+
+    Ok(size)
+}
+
+/**
+Check that the fields of a struct supplied by a caller beyond the ones we know about are zero.
+*/
+pub(crate) fn check_unknown_fields<T: Versioned>(bytes: &[u8]) -> Result<(), Error> {
+    let known = mem::size_of::<T>();
+
+    if bytes.len() > known && bytes[known..].iter().any(|b| *b != 0) {
+        return Err(Error::UnsupportedFields(known));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::handle::{
+        Ref,
+        RefMut,
+    };
+
+    #[repr(C)]
+    #[derive(Debug, Default, PartialEq)]
+    struct V2 {
+        size: u32,
+        a: u32,
+        b: u32,
+    }
+
+    impl Versioned for V2 {
+        // The first version only had `a`
+        const MIN_SIZE: usize = 8;
+    }
+
+    fn as_ref<T>(bytes: &[u8]) -> Ref<'_, T> {
+        Ref::from_ptr(bytes.as_ptr() as *const T)
+    }
+
+    fn as_mut<T>(bytes: &mut [u8]) -> RefMut<'_, T> {
+        RefMut::from_ptr(bytes.as_mut_ptr() as *mut T)
+    }
+
+    fn bytes(words: &[u32]) -> Vec<u8> {
+        words
+            .iter()
+            .flat_map(|w| w.to_ne_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn read_older_version() {
+        let v1 = bytes(&[8, 1]);
+
+        let read = unsafe_block!("The pointer is valid for the size" => as_ref::<V2>(&v1).read_versioned()).unwrap();
+
+        assert_eq!(
+            V2 {
+                size: 8,
+                a: 1,
+                b: 0
+            },
+            read
+        );
+    }
+
+    #[test]
+    fn read_newer_version() {
+        let v3 = bytes(&[16, 1, 2, 0]);
+        let read = unsafe_block!("The pointer is valid for the size" => as_ref::<V2>(&v3).read_versioned()).unwrap();
+        assert_eq!(
+            V2 {
+                size: 16,
+                a: 1,
+                b: 2
+            },
+            read
+        );
+
+        let v3 = bytes(&[16, 1, 2, 3]);
+        let read = unsafe_block!("The pointer is valid for the size" => as_ref::<V2>(&v3).read_versioned());
+        assert!(read.is_err());
+    }
+
+    #[test]
+    fn read_too_small() {
+        let v0 = bytes(&[4]);
+
+        let read = unsafe_block!("The pointer is valid for the size" => as_ref::<V2>(&v0).read_versioned());
+
+        assert!(read.is_err());
+    }
+
+    #[test]
+    fn write_older_version() {
+        let mut v1 = bytes(&[8, 0, 42]);
+
+        unsafe_block!("The pointer is valid for the size" => as_mut::<V2>(&mut v1).write_versioned(V2 { size: 0, a: 1, b: 2 })).unwrap();
+
+        // The field beyond the caller's size isn't touched
+        assert_eq!(bytes(&[8, 1, 42]), v1);
+    }
+
+    #[test]
+    fn write_array() {
+        let mut v3 = bytes(&[16, 0, 0, 0, 0, 0, 0, 0]);
+
+        {
+            let mut out = as_mut::<V2>(&mut v3);
+            let size =
+                unsafe_block!("The pointer is valid for the size" => out.versioned_size()).unwrap();
+
+            for i in 0..2 {
+                unsafe_block!("The buffer is valid for two values" => out.write_versioned_at(size, i, V2 { size: 0, a: 1, b: 2 }));
+            }
+        }
+
+        assert_eq!(bytes(&[12, 1, 2, 0, 12, 1, 2, 0]), v3);
+    }
+}
@@ -0,0 +1,76 @@
+/*!
+Compile the C program in `tests/c` against `include/db.h` and run it with the `dbc` library.
+*/
+
+#![cfg(unix)]
+
+use std::{
+    env,
+    fs,
+    path::Path,
+    process::{
+        self,
+        Command,
+    },
+    sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn compile_and_run(program: &str) {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    // Test binaries are in `target/{profile}/deps` and the library is in `target/{profile}`
+    let exe = env::current_exe().expect("failed to get the test binary path");
+    let lib_dir = exe
+        .parent()
+        .and_then(Path::parent)
+        .expect("failed to find the library directory");
+
+    let id = format!(
+        "{}-{}",
+        process::id(),
+        NEXT_ID.fetch_add(1, Ordering::SeqCst)
+    );
+    let out = env::temp_dir().join(format!("dbc-{}-{}", program, id));
+    let store = env::temp_dir().join(format!("dbc-{}-store-{}", program, id));
+
+    let mut cc = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()));
+    cc.arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Wextra")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/c").join(format!("{}.c", program)))
+        .arg("-o")
+        .arg(&out)
+        .arg("-L")
+        .arg(lib_dir)
+        .arg("-ldbc")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()));
+
+    if cfg!(debug_assertions) {
+        cc.arg("-DDB_DEBUG");
+    }
+
+    let status = cc.status().expect("failed to run the C compiler");
+    assert!(status.success(), "failed to compile `{}.c`", program);
+
+    let status = Command::new(&out)
+        .arg(&store)
+        .status()
+        .expect("failed to run the C program");
+    assert!(status.success(), "`{}.c` failed", program);
+
+    let _ = fs::remove_file(&out);
+    let _ = fs::remove_dir_all(&store);
+}
+
+#[test]
+fn store() {
+    compile_and_run("store");
+}
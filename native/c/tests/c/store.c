@@ -0,0 +1,190 @@
+/*
+Open a store, then write, read and delete through `db.h`.
+
+Usage: `store <path>`
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "db.h"
+
+#define KEYS 3
+
+#define CHECK(call) check(#call, (call), DB_KIND_OK)
+#define CHECK_KIND(call, kind) check(#call, (call), kind)
+
+static void check(const char *call, DbResult result, DbKind expected) {
+    uint8_t message[1024];
+    size_t message_len = 0;
+    DbResult last;
+
+    if (result.kind == expected) {
+        return;
+    }
+
+    db_last_result(message, sizeof(message), &message_len, &last);
+    if (message_len > sizeof(message)) {
+        message_len = sizeof(message);
+    }
+
+    fprintf(stderr, "`%s` returned %u (expected %u): %.*s\n", call, result.kind, expected, (int)message_len, (const char *)message);
+    exit(1);
+}
+
+static DbKey make_key(uint8_t i) {
+    DbKey key;
+
+    memset(&key, 0, sizeof(key));
+    key.value[15] = i;
+
+    return key;
+}
+
+/*
+Read every record in the store, returning a bitmask of the keys that were found.
+*/
+static unsigned read_all(DbStore *store) {
+    DbReader *reader = NULL;
+    unsigned found = 0;
+
+    CHECK(db_read_begin(store, &reader));
+
+    for (;;) {
+        DbKey read_key;
+        uint8_t value[64];
+        size_t value_len = 0;
+        char expected[64];
+
+        DbResult result = db_read_next(reader, &read_key, value, sizeof(value), &value_len);
+        if (result.kind == DB_KIND_DONE) {
+            break;
+        }
+        CHECK_KIND(result, DB_KIND_OK);
+
+        uint8_t i = read_key.value[15];
+        snprintf(expected, sizeof(expected), "value %u", i);
+
+        if (value_len != strlen(expected) || memcmp(value, expected, value_len) != 0) {
+            fprintf(stderr, "unexpected value for key %u: %.*s\n", i, (int)value_len, (const char *)value);
+            exit(1);
+        }
+
+        found |= 1u << i;
+    }
+
+    CHECK(db_read_end(reader));
+
+    return found;
+}
+
+int main(int argc, char **argv) {
+    DbStore *store = NULL;
+    DbWriter *writer = NULL;
+    DbDeleter *deleter = NULL;
+    DbReader *reader = NULL;
+    uint64_t capabilities = 0;
+
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <path>\n", argv[0]);
+        return 2;
+    }
+
+    /* The library must implement the version of the interface we were compiled against */
+    {
+        uint32_t major = 0;
+        uint32_t minor = 0;
+
+        CHECK(db_abi_version(&major, &minor));
+        if (major != DB_ABI_MAJOR || minor < DB_ABI_MINOR) {
+            fprintf(stderr, "unsupported interface version %u.%u\n", major, minor);
+            return 1;
+        }
+
+    }
+
+    CHECK(db_capabilities(&capabilities));
+    if ((capabilities & DB_CAPABILITY_METRICS) == 0) {
+        fprintf(stderr, "expected the metrics capability\n");
+        return 1;
+    }
+
+#ifdef DB_DEBUG
+    CHECK(db_test_ok());
+#endif
+
+    CHECK(db_store_open((const uint8_t *)argv[1], strlen(argv[1]), &store));
+
+    /* Write some records */
+    CHECK(db_write_begin(store, &writer));
+    for (uint8_t i = 0; i < KEYS; i++) {
+        DbKey write_key = make_key(i);
+        char value[64];
+
+        snprintf(value, sizeof(value), "value %u", i);
+        CHECK(db_write_set(writer, &write_key, (const uint8_t *)value, strlen(value)));
+    }
+    CHECK(db_write_end(writer));
+
+    /* Versioned structs are given the size of the struct we were compiled against */
+    {
+        DbStoreMetrics metrics;
+
+        memset(&metrics, 0, sizeof(metrics));
+        metrics.size = sizeof(metrics);
+
+        CHECK(db_store_metrics(store, &metrics));
+        if (metrics.size != sizeof(metrics) || metrics.writes != KEYS) {
+            fprintf(stderr, "unexpected metrics: %u bytes, %llu writes\n", metrics.size, (unsigned long long)metrics.writes);
+            return 1;
+        }
+    }
+
+    /* Read them back */
+    if (read_all(store) != 0x7) {
+        fprintf(stderr, "expected to read every key\n");
+        return 1;
+    }
+
+    /* A buffer that's too small reports the length that's needed */
+    {
+        DbKey read_key;
+        uint8_t value[1];
+        size_t value_len = 0;
+
+        CHECK(db_read_begin(store, &reader));
+        CHECK_KIND(db_read_next(reader, &read_key, value, sizeof(value), &value_len), DB_KIND_BUFFER_TOO_SMALL);
+        CHECK(db_read_end(reader));
+
+        if (value_len != strlen("value 0")) {
+            fprintf(stderr, "unexpected required length %zu\n", value_len);
+            return 1;
+        }
+    }
+
+    /* Delete one of them */
+    {
+        DbKey delete_key = make_key(1);
+
+        CHECK(db_delete_begin(store, &deleter));
+        CHECK(db_delete_remove(deleter, &delete_key));
+        CHECK_KIND(db_delete_remove(deleter, &delete_key), DB_KIND_DONE);
+        CHECK(db_delete_end(deleter));
+    }
+
+    if (read_all(store) != 0x5) {
+        fprintf(stderr, "expected the deleted key to be missing\n");
+        return 1;
+    }
+
+    CHECK(db_store_close(store));
+
+    /* Checked handles can't be used after they've been released */
+    if ((capabilities & DB_CAPABILITY_CHECKED_HANDLES) != 0) {
+        CHECK_KIND(db_store_close(store), DB_KIND_INVALID_HANDLE);
+        CHECK_KIND(db_write_end(writer), DB_KIND_INVALID_HANDLE);
+    }
+
+    return 0;
+}
@@ -0,0 +1,149 @@
+/*!
+Codecs for converting typed values to and from payloads.
+*/
+
+use std::io::Read;
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+
+use crate::error::Error;
+
+/**
+A format for encoding typed values as payloads.
+
+Features that inspect payloads, like indexes, filters, and validation,
+expect them to be JSON, so they only work with the `Json` codec.
+*/
+pub trait Codec {
+    fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize;
+
+    fn decode<T>(&self, payload: impl Read) -> Result<T, Error>
+    where
+        T: DeserializeOwned;
+}
+
+/**
+Encode values as JSON.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize,
+    {
+        serde_json::to_vec(value).map_err(Error::fail)
+    }
+
+    fn decode<T>(&self, payload: impl Read) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_reader(payload).map_err(Error::fail)
+    }
+}
+
+/**
+Encode values as CBOR.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+impl Codec for Cbor {
+    fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize,
+    {
+        let mut payload = Vec::new();
+        ciborium::into_writer(value, &mut payload).map_err(Error::fail)?;
+
+        Ok(payload)
+    }
+
+    fn decode<T>(&self, payload: impl Read) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        ciborium::from_reader(payload).map_err(Error::fail)
+    }
+}
+
+/**
+Encode values as MessagePack.
+
+Structs are encoded as maps so fields can be added and reordered.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+impl Codec for MessagePack {
+    fn encode<T>(&self, value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize,
+    {
+        rmp_serde::to_vec_named(value).map_err(Error::fail)
+    }
+
+    fn decode<T>(&self, payload: impl Read) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        rmp_serde::from_read(payload).map_err(Error::fail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        id: u32,
+        title: String,
+        tags: Vec<String>,
+    }
+
+    fn roundtrip(codec: impl Codec) {
+        let doc = Doc {
+            id: 1,
+            title: "a".to_owned(),
+            tags: vec!["b".to_owned()],
+        };
+
+        let payload = codec.encode(&doc).unwrap();
+
+        assert_eq!(doc, codec.decode::<Doc>(&payload[..]).unwrap());
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        roundtrip(Json);
+
+        assert_eq!(b"[1,2]".to_vec(), Json.encode(&[1, 2]).unwrap());
+    }
+
+    #[test]
+    fn cbor_roundtrip() {
+        roundtrip(Cbor);
+    }
+
+    #[test]
+    fn message_pack_roundtrip() {
+        roundtrip(MessagePack);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert!(Json.decode::<Doc>(&b"{"[..]).is_err());
+        assert!(Cbor.decode::<Doc>(&b"{"[..]).is_err());
+        assert!(MessagePack.decode::<Doc>(&b"{"[..]).is_err());
+    }
+}
@@ -1,4 +1,7 @@
-use crate::error::Error;
+use crate::error::{
+    Error,
+    ErrorKind,
+};
 
 pub struct Data<P> {
     pub key: Key,
@@ -7,7 +10,7 @@ pub struct Data<P> {
 
 pub const KEY_SIZE: usize = 16;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 pub struct Key([u8; KEY_SIZE]);
 
 impl Key {
@@ -25,11 +28,12 @@ impl Key {
                 "key length `{}` is greater than the max allowed `{}`",
                 value.len(),
                 KEY_SIZE
-            )));
+            ))
+            .with_kind(ErrorKind::KeyTooLong));
         }
 
         let mut bytes = [0; KEY_SIZE];
-        (&mut bytes[..value.len()]).copy_from_slice(value);
+        bytes[..value.len()].copy_from_slice(value);
 
         Ok(Key(bytes))
     }
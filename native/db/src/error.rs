@@ -1,6 +1,9 @@
-use std::fmt::{
-    Debug,
-    Display,
+use std::{
+    fmt::{
+        Debug,
+        Display,
+    },
+    io,
 };
 
 use failure::{
@@ -9,19 +12,301 @@ use failure::{
     Fail,
 };
 
+use crate::{
+    data::Key,
+    store::{
+        encryption::DecryptError,
+        record::Corruption,
+        validate::ValidationError,
+    },
+};
+
 /**
 An error encountered while working with a database.
 */
 #[derive(Debug, Fail)]
 #[fail(display = "error using a db")]
-pub struct Error(#[cause] failure::Error);
+pub struct Error {
+    kind: ErrorKind,
+    key: Option<Key>,
+    #[cause]
+    inner: failure::Error,
+}
+
+/**
+The kind of an error.
+
+Kinds are stable, so callers can rely on them instead of error messages.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /**
+    An error that doesn't have a more specific kind.
+    */
+    Other,
+    /**
+    Reading from or writing to the underlying storage failed.
+    */
+    Io,
+    /**
+    The store contains damaged data.
+    */
+    Corrupted,
+    /**
+    A record couldn't be decrypted with any of the supplied keys.
+    */
+    DecryptionFailed,
+    /**
+    A payload was rejected before it was written.
+    */
+    ValidationFailed,
+    /**
+    A key is longer than the maximum allowed.
+    */
+    KeyTooLong,
+    /**
+    A filter expression couldn't be parsed.
+    */
+    InvalidFilter,
+    /**
+    An index has an invalid name or path.
+    */
+    InvalidIndex,
+    /**
+    An index that doesn't exist was queried.
+    */
+    UnknownIndex,
+    /**
+    A JSON Schema couldn't be compiled.
+    */
+    InvalidSchema,
+    /**
+    Some other argument was invalid.
+    */
+    InvalidArgument,
+}
+
+/**
+The number of error kinds.
+*/
+pub const ERROR_KINDS: usize = 11;
+
+impl ErrorKind {
+    /**
+    Every kind of error.
+
+    The position of a kind in this list is stable, so it can be used to index counters.
+    */
+    pub const ALL: [ErrorKind; ERROR_KINDS] = [
+        ErrorKind::Other,
+        ErrorKind::Io,
+        ErrorKind::Corrupted,
+        ErrorKind::DecryptionFailed,
+        ErrorKind::ValidationFailed,
+        ErrorKind::KeyTooLong,
+        ErrorKind::InvalidFilter,
+        ErrorKind::InvalidIndex,
+        ErrorKind::UnknownIndex,
+        ErrorKind::InvalidSchema,
+        ErrorKind::InvalidArgument,
+    ];
+
+    /**
+    Get the position of the kind in `ErrorKind::ALL`.
+    */
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /**
+    Get a short name for the kind, like `key_too_long`.
+    */
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::Io => "io",
+            ErrorKind::Corrupted => "corrupted",
+            ErrorKind::DecryptionFailed => "decryption_failed",
+            ErrorKind::ValidationFailed => "validation_failed",
+            ErrorKind::KeyTooLong => "key_too_long",
+            ErrorKind::InvalidFilter => "invalid_filter",
+            ErrorKind::InvalidIndex => "invalid_index",
+            ErrorKind::UnknownIndex => "unknown_index",
+            ErrorKind::InvalidSchema => "invalid_schema",
+            ErrorKind::InvalidArgument => "invalid_argument",
+        }
+    }
+}
 
 impl Error {
     pub(crate) fn fail(err: impl Fail) -> Self {
-        Error(err.into())
+        let kind = classify(&err);
+
+        Error {
+            kind,
+            key: None,
+            inner: err.into(),
+        }
     }
 
     pub(crate) fn msg(msg: impl Display + Debug + Sync + Send + 'static) -> Self {
         Error::fail(err_msg(msg).compat())
     }
+
+    /**
+    Give the error a more specific kind.
+    */
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /**
+    Attach the key of the record involved in the error.
+    */
+    pub(crate) fn with_key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /**
+    Get the kind of error.
+    */
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /**
+    Get the key of the record involved in the error, if there was one.
+    */
+    pub fn key(&self) -> Option<Key> {
+        self.key
+    }
+
+    /**
+    Get the OS error code of the IO error that caused the error, if there was one.
+    */
+    pub fn os_error(&self) -> Option<i32> {
+        self.inner.iter_chain().find_map(|cause| {
+            if let Some(err) = cause.downcast_ref::<io::Error>() {
+                return err.raw_os_error();
+            }
+
+            match cause.downcast_ref::<sled::Error<()>>() {
+                Some(sled::Error::Io(err)) => err.raw_os_error(),
+                _ => None,
+            }
+        })
+    }
+
+    /**
+    Whether or not the error was caused by damaged data in the store.
+    */
+    pub fn is_corrupted(&self) -> bool {
+        self.kind == ErrorKind::Corrupted
+    }
+
+    /**
+    Whether or not the error was caused by a record that couldn't be decrypted.
+    */
+    pub fn is_decryption_failure(&self) -> bool {
+        self.kind == ErrorKind::DecryptionFailed
+    }
+
+    /**
+    Whether or not the error was caused by a payload that failed validation.
+    */
+    pub fn is_validation_failure(&self) -> bool {
+        self.kind == ErrorKind::ValidationFailed
+    }
+}
+
+/**
+Find the kind of an error from the first cause that has one.
+*/
+fn classify(err: &dyn Fail) -> ErrorKind {
+    for cause in err.iter_chain() {
+        if let Some(err) = cause.downcast_ref::<Error>() {
+            return err.kind;
+        }
+
+        if cause.downcast_ref::<Corruption>().is_some() {
+            return ErrorKind::Corrupted;
+        }
+
+        if cause.downcast_ref::<DecryptError>().is_some() {
+            return ErrorKind::DecryptionFailed;
+        }
+
+        if cause.downcast_ref::<ValidationError>().is_some() {
+            return ErrorKind::ValidationFailed;
+        }
+
+        if let Some(err) = cause.downcast_ref::<sled::Error<()>>() {
+            return match err {
+                sled::Error::Corruption { .. } => ErrorKind::Corrupted,
+                _ => ErrorKind::Io,
+            };
+        }
+
+        if cause.downcast_ref::<io::Error>().is_some() {
+            return ErrorKind::Io;
+        }
+    }
+
+    ErrorKind::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::filter::Filter;
+
+    #[test]
+    fn kind_of_nested_error() {
+        let err = Error::fail(Error::fail(Corruption::Truncated));
+
+        assert_eq!(ErrorKind::Corrupted, err.kind());
+        assert!(err.is_corrupted());
+    }
+
+    #[test]
+    fn kind_of_io_error() {
+        let err = Error::fail(io::Error::other("an io error"));
+
+        assert_eq!(ErrorKind::Io, err.kind());
+    }
+
+    #[test]
+    fn os_error() {
+        let err = Error::fail(io::Error::from_raw_os_error(2));
+
+        assert_eq!(Some(2), err.os_error());
+        assert_eq!(None, Error::msg("an error").os_error());
+    }
+
+    #[test]
+    fn kind_indexes_match_positions() {
+        for (i, kind) in ErrorKind::ALL.iter().enumerate() {
+            assert_eq!(i, kind.index());
+        }
+    }
+
+    #[test]
+    fn kind_of_message() {
+        assert_eq!(ErrorKind::Other, Error::msg("an error").kind());
+    }
+
+    #[test]
+    fn kind_of_invalid_arguments() {
+        assert_eq!(
+            ErrorKind::KeyTooLong,
+            Key::from_slice(&[0; 17]).err().unwrap().kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidFilter,
+            Filter::parse("status ==").unwrap_err().kind()
+        );
+    }
 }
@@ -1,7 +1,7 @@
 // Unsafe is explicitly allowed through `unsafe_*` macros
 #![deny(unsafe_code)]
-// For converting Rust results into FFI results
-#![feature(try_trait)]
+// `failure_derive` implements `Fail` inside an anonymous `const`
+#![allow(non_local_definitions)]
 
 #[macro_use]
 extern crate rental;
@@ -11,6 +11,8 @@ extern crate rental;
 #[allow(unused_macros)]
 mod std_ext;
 
+pub mod codec;
 pub mod data;
 pub mod error;
+pub mod logger;
 pub mod store;
@@ -0,0 +1,130 @@
+/*!
+A `log` logger that forwards events from the database to a sink supplied by the host.
+
+Events use their module path as their target, so hosts can filter them
+the same way as events from any other `log` implementation.
+*/
+
+use std::sync::{
+    atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    RwLock,
+};
+
+use log::{
+    Level,
+    LevelFilter,
+    Log,
+    Metadata,
+    Record,
+};
+
+use crate::error::Error;
+
+/**
+A function that receives the level, target, and message of log events.
+
+The sink may be called from any thread.
+*/
+pub type Sink = Box<dyn Fn(Level, &str, &str) + Send + Sync>;
+
+static LOGGER: Logger = Logger {
+    sink: RwLock::new(None),
+};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+struct Logger {
+    sink: RwLock<Option<Sink>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // A poisoned sink is skipped rather than panicking in whatever code is logging
+        if let Ok(sink) = self.sink.read() {
+            if let Some(ref sink) = *sink {
+                let message = record.args().to_string();
+
+                sink(record.level(), record.target(), &message);
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/**
+Send log events up to the given level to a sink.
+
+This replaces any previous sink. Passing `None` stops sending events.
+The sink must not call `set_sink` itself.
+
+This fails if some other logger has already been installed in the process.
+*/
+pub fn set_sink(level: LevelFilter, sink: Option<Sink>) -> Result<(), Error> {
+    let mut current = LOGGER.sink.write().expect("failed to lock log sink");
+
+    if !INSTALLED.load(Ordering::SeqCst) {
+        log::set_logger(&LOGGER)
+            .map_err(|_| Error::msg("another logger has already been installed"))?;
+
+        INSTALLED.store(true, Ordering::SeqCst);
+    }
+
+    log::set_max_level(if sink.is_some() {
+        level
+    } else {
+        LevelFilter::Off
+    });
+    *current = sink;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+
+    #[test]
+    fn events_are_sent_to_sink() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = {
+            let events = events.clone();
+            Box::new(move |level: Level, target: &str, message: &str| {
+                if target == "db::logger::test" {
+                    events.lock().unwrap().push((level, message.to_owned()));
+                }
+            })
+        };
+
+        set_sink(LevelFilter::Warn, Some(sink)).unwrap();
+
+        log::warn!(target: "db::logger::test", "a {}", "warning");
+        log::info!(target: "db::logger::test", "some info");
+
+        set_sink(LevelFilter::Trace, None).unwrap();
+
+        log::error!(target: "db::logger::test", "an error");
+
+        assert_eq!(
+            vec![(Level::Warn, "a warning".to_owned())],
+            *events.lock().unwrap()
+        );
+    }
+}
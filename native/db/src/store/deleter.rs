@@ -1,38 +1,230 @@
-use std::panic::{
-    RefUnwindSafe,
-    UnwindSafe,
+use std::{
+    ops::Bound,
+    panic::{
+        RefUnwindSafe,
+        UnwindSafe,
+    },
+    sync::Arc,
 };
 
 use crate::{
     data::Key,
     error::Error,
     store::{
+        durability::{
+            Durability,
+            Flusher,
+        },
+        index::Indexes,
+        metrics::Recorder,
+        record::Format,
         Db,
         Store,
+        WriteOptions,
     },
 };
 
+/**
+The number of keys removed at a time from a range or prefix.
+*/
+const REMOVE_BATCH_SIZE: usize = 1024;
+
 pub struct Deleter {
     db: Db,
+    format: Arc<Format>,
+    indexes: Arc<Indexes>,
+    flusher: Arc<Flusher>,
+    durability: Durability,
+    metrics: Arc<Recorder>,
 }
 
 impl Deleter {
-    pub(super) fn begin(store: &Store) -> Self {
+    pub(super) fn begin(store: &Store, options: WriteOptions) -> Self {
         let db = store.db.clone();
+        let format = store.format.clone();
+        let indexes = store.indexes.clone();
+        let flusher = store.flusher.clone();
+        let metrics = store.metrics.clone();
 
-        Deleter { db }
+        Deleter {
+            db,
+            format,
+            indexes,
+            flusher,
+            durability: options.durability,
+            metrics,
+        }
     }
 
-    pub fn remove(&mut self, key: Key) -> Result<(), Error> {
-        self.db.del(key).map_err(Error::fail)?;
+    /**
+    Remove the record with the given key.
 
-        Ok(())
+    Returns whether there was a record to remove.
+    */
+    pub fn remove(&mut self, key: Key) -> Result<bool, Error> {
+        let removed = self.remove_keys(Some(key.as_ref()));
+
+        Ok(self.metrics.track(removed)? == 1)
+    }
+
+    /**
+    Remove the record with the given key, returning its payload if there was one.
+
+    The record is removed even if its payload can't be read.
+    In that case the inner result is an error, so callers can still tell the record is gone.
+    */
+    pub fn remove_returning(&mut self, key: Key) -> Result<Option<Result<Vec<u8>, Error>>, Error> {
+        let removed = self.remove_record_returning(key);
+
+        self.metrics.track(removed)
+    }
+
+    /**
+    Remove every record with a key from `start` up to, but not including, `end`.
+
+    Returns the number of records that were removed.
+    */
+    pub fn remove_range(&mut self, start: Key, end: Key) -> Result<usize, Error> {
+        let removed = self.remove_keys_in_range(start, end);
+
+        self.metrics.track(removed)
+    }
+
+    /**
+    Remove every record with a key that starts with the given bytes.
+
+    Returns the number of records that were removed.
+    */
+    pub fn remove_prefix(&mut self, prefix: &[u8]) -> Result<usize, Error> {
+        let removed = self.remove_keys_with_prefix(prefix);
+
+        self.metrics.track(removed)
     }
 
     pub fn complete(&mut self) -> Result<(), Error> {
-        self.db
-            .flush()
-            .map_err(|_| Error::msg("failed to flush database"))?;
+        let completed = match self.durability {
+            Durability::FlushOnComplete => self.flusher.flush(),
+            Durability::GroupCommit(window) => self.flusher.flush_within(window),
+            Durability::None | Durability::FlushEveryWrite => Ok(()),
+        };
+
+        self.metrics.track(completed)
+    }
+
+    fn remove_record_returning(
+        &mut self,
+        key: Key,
+    ) -> Result<Option<Result<Vec<u8>, Error>>, Error> {
+        let previous = self.remove_record(key.as_ref())?;
+        self.flush_every_write()?;
+
+        Ok(previous.map(|previous| {
+            self.format
+                .decode(&key, previous)
+                .map(|payload| payload.as_ref().to_vec())
+                .map_err(|e| Error::from(e).with_key(key))
+        }))
+    }
+
+    fn remove_keys_in_range(&mut self, start: Key, end: Key) -> Result<usize, Error> {
+        self.remove_keys_while(start.as_ref(), |k| k < end.as_ref())
+    }
+
+    fn remove_keys_with_prefix(&mut self, prefix: &[u8]) -> Result<usize, Error> {
+        // Check the prefix could be part of a key
+        Key::from_slice(prefix)?;
+
+        self.remove_keys_while(prefix, |k| k.starts_with(prefix))
+    }
+
+    /**
+    Remove every record from `start` until a key doesn't match.
+
+    Keys are removed in fixed-size batches while walking the range,
+    so a large range never has to be held in memory at once.
+    */
+    fn remove_keys_while(
+        &mut self,
+        start: &[u8],
+        matches: impl Fn(&[u8]) -> bool,
+    ) -> Result<usize, Error> {
+        let mut removed = 0;
+        let mut from = Bound::Included(start.to_vec());
+
+        loop {
+            let mut batch = Vec::with_capacity(REMOVE_BATCH_SIZE);
+            for kv in self
+                .db
+                .range::<Vec<u8>, _>((from, Bound::Unbounded))
+                .take(REMOVE_BATCH_SIZE)
+            {
+                let (k, _) = kv.map_err(Error::fail)?;
+
+                if !matches(&k) {
+                    break;
+                }
+
+                batch.push(k);
+            }
+
+            removed += self.remove_keys(batch.iter().map(|k| &k[..]))?;
+
+            // A batch that isn't full means the end of the range was reached
+            match batch.last() {
+                Some(last) if batch.len() == REMOVE_BATCH_SIZE => {
+                    from = Bound::Excluded(last.to_vec());
+                }
+                _ => return Ok(removed),
+            }
+        }
+    }
+
+    fn remove_keys<'a>(
+        &mut self,
+        keys: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Result<usize, Error> {
+        let mut removed = 0;
+
+        for key in keys {
+            if self.remove_record(key)?.is_some() {
+                removed += 1;
+            }
+        }
+
+        self.flush_every_write()?;
+
+        Ok(removed)
+    }
+
+    /**
+    Remove a record by its raw key.
+
+    Records are removed even if their key isn't valid, so a damaged key
+    doesn't stop a range or prefix from being purged.
+    */
+    fn remove_record(&mut self, k: &[u8]) -> Result<Option<sled::IVec>, Error> {
+        // Records with invalid keys can't have been indexed
+        let key = Key::from_slice(k).ok();
+
+        let lock = key.and_then(|key| self.indexes.lock(key));
+        let previous = self.db.del(k).map_err(Error::fail)?;
+
+        if let Some(key) = key {
+            self.indexes.remove(&self.format, key, previous.clone())?;
+        }
+        drop(lock);
+
+        if previous.is_some() {
+            self.metrics.delete();
+        }
+
+        Ok(previous)
+    }
+
+    fn flush_every_write(&self) -> Result<(), Error> {
+        if self.durability == Durability::FlushEveryWrite {
+            self.flusher.flush()?;
+        }
 
         Ok(())
     }
@@ -47,3 +239,141 @@ See: https://github.com/spacejam/sled/issues/662
 */
 impl UnwindSafe for Deleter {}
 impl RefUnwindSafe for Deleter {}
+
+#[cfg(test)]
+mod tests {
+    use super::REMOVE_BATCH_SIZE;
+
+    use crate::store::{
+        test_util::{
+            key,
+            write,
+            TempStore,
+        },
+        Options,
+    };
+
+    #[test]
+    fn remove_reports_presence() {
+        let store = TempStore::new();
+        write(&store, &[(b"a", "{}")]);
+
+        let mut deleter = store.delete_begin().unwrap();
+
+        assert_eq!(
+            b"{}".to_vec(),
+            deleter
+                .remove_returning(key(b"a"))
+                .unwrap()
+                .unwrap()
+                .unwrap()
+        );
+        assert!(deleter.remove_returning(key(b"a")).unwrap().is_none());
+        assert!(!deleter.remove(key(b"a")).unwrap());
+
+        write(&store, &[(b"a", "{}")]);
+        assert!(deleter.remove(key(b"a")).unwrap());
+    }
+
+    #[test]
+    fn remove_returning_unreadable() {
+        let store = TempStore::with_options(Options {
+            checksums: true,
+            ..Default::default()
+        });
+        write(&store, &[(b"a", "{}")]);
+
+        // Flip the last byte of the stored payload
+        let mut record = store.db.get(key(b"a")).unwrap().unwrap().to_vec();
+        *record.last_mut().unwrap() ^= 0xff;
+        store.db.set(key(b"a"), record).unwrap();
+
+        let mut deleter = store.delete_begin().unwrap();
+
+        let removed = deleter.remove_returning(key(b"a")).unwrap().unwrap();
+        assert!(removed.unwrap_err().is_corrupted());
+
+        assert_eq!(0, store.db.len());
+    }
+
+    #[test]
+    fn remove_range() {
+        let store = TempStore::new();
+        write(
+            &store,
+            &[(b"a", "{}"), (b"b", "{}"), (b"c", "{}"), (b"d", "{}")],
+        );
+
+        let mut deleter = store.delete_begin().unwrap();
+        let removed = deleter.remove_range(key(b"b"), key(b"d")).unwrap();
+        deleter.complete().unwrap();
+
+        assert_eq!(2, removed);
+        assert_eq!(2, store.db.len());
+    }
+
+    #[test]
+    fn remove_range_across_batches() {
+        let store = TempStore::new();
+
+        let keys = (0..REMOVE_BATCH_SIZE * 2 + 1)
+            .map(|i| format!("{:08}", i))
+            .collect::<Vec<_>>();
+        write(
+            &store,
+            &keys
+                .iter()
+                .map(|k| (k.as_bytes(), "{}"))
+                .collect::<Vec<_>>(),
+        );
+        write(&store, &[(b"z", "{}")]);
+
+        let mut deleter = store.delete_begin().unwrap();
+        let removed = deleter.remove_range(key(b"0"), key(b"9")).unwrap();
+        deleter.complete().unwrap();
+
+        assert_eq!(keys.len(), removed);
+        assert_eq!(1, store.db.len());
+    }
+
+    #[test]
+    fn remove_prefix() {
+        let store = TempStore::new();
+        write(
+            &store,
+            &[
+                (b"tenant-1/a", "{}"),
+                (b"tenant-1/b", "{}"),
+                (b"tenant-2/a", "{}"),
+                (b"tenant-10", "{}"),
+            ],
+        );
+
+        let mut deleter = store.delete_begin().unwrap();
+        let removed = deleter.remove_prefix(b"tenant-1/").unwrap();
+        deleter.complete().unwrap();
+
+        assert_eq!(2, removed);
+        assert_eq!(2, store.db.len());
+
+        assert!(deleter.remove_prefix(&[0; 17]).is_err());
+    }
+
+    #[test]
+    fn remove_prefix_with_invalid_keys() {
+        let store = TempStore::new();
+        write(&store, &[(b"tenant-1/a", "{}")]);
+
+        store
+            .db
+            .set(b"tenant-1/invalid-key", b"{}".to_vec())
+            .unwrap();
+
+        let mut deleter = store.delete_begin().unwrap();
+        let removed = deleter.remove_prefix(b"tenant-1/").unwrap();
+        deleter.complete().unwrap();
+
+        assert_eq!(2, removed);
+        assert_eq!(0, store.db.len());
+    }
+}
@@ -0,0 +1,494 @@
+/*!
+How the changes made by writers and deleters are made durable.
+*/
+
+use std::{
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+        Condvar,
+        Mutex,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::{
+    error::{
+        Error,
+        ErrorKind,
+    },
+    store::{
+        metrics::{
+            AtomicHistogram,
+            Histogram,
+        },
+        Db,
+    },
+};
+
+/**
+When changes are flushed to disk.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Durability {
+    /**
+    Never explicitly flush.
+
+    Changes are eventually flushed by the database in the background,
+    or when the store is closed.
+    */
+    None,
+    /**
+    Flush all changes when the writer or deleter is completed.
+    */
+    #[default]
+    FlushOnComplete,
+    /**
+    Flush after every individual change.
+    */
+    FlushEveryWrite,
+    /**
+    Flush no later than the given window after completing.
+
+    Completing waits for the flush to finish, so changes are durable once it returns.
+    Changes completed within the same window share a single flush.
+    */
+    GroupCommit(Duration),
+}
+
+/**
+The number of buckets in the batch size histogram.
+*/
+pub const BATCH_SIZE_BUCKETS: usize = 8;
+
+/**
+A snapshot of the flushes made by a store.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlushMetrics {
+    /**
+    The number of flushes made.
+    */
+    pub flushes: u64,
+    /**
+    The number of completions that asked for a flush.
+
+    Completions that arrive while a flush is in progress share the next one,
+    so this can be larger than `flushes`.
+    */
+    pub requests: u64,
+    /**
+    The most completions that shared a single flush.
+    */
+    pub largest_batch: u64,
+    /**
+    The number of flushes by the number of completions that shared them.
+
+    Bucket `i` counts the flushes shared by at least `2^i` and fewer than `2^(i + 1)`
+    completions. The last bucket also counts any larger flushes.
+    */
+    pub batch_sizes: [u64; BATCH_SIZE_BUCKETS],
+    /**
+    How long flushes took.
+    */
+    pub latency: Histogram,
+}
+
+impl FlushMetrics {
+    /**
+    The average number of completions that shared a flush.
+    */
+    pub fn mean_batch(&self) -> f64 {
+        if self.flushes == 0 {
+            0.0
+        } else {
+            self.requests as f64 / self.flushes as f64
+        }
+    }
+}
+
+/**
+Coalesces the flushes requested by writers and deleters.
+
+Completions that ask for a flush while one is already in progress wait together
+and share the next flush, so concurrent writers pay for a single fsync between them.
+
+Completions can also join a group that's flushed by a background thread within a window.
+The background thread is only started once a group is formed,
+and exits after the last handle to the flusher is dropped.
+*/
+pub(super) struct Flusher {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    db: Db,
+    state: Mutex<State>,
+    wake: Condvar,
+    flushed: Condvar,
+    metrics: Metrics,
+}
+
+#[derive(Default)]
+struct State {
+    // The batch waiting for the flush in progress to finish
+    next: Option<Arc<Batch>>,
+    flushing: bool,
+    // The group waiting for the background thread to flush it
+    group: Option<Arc<Batch>>,
+    deadline: Option<Instant>,
+    running: bool,
+    shutdown: bool,
+}
+
+/**
+A set of completions that share a flush.
+*/
+#[derive(Default)]
+struct Batch {
+    requests: AtomicU64,
+    result: Mutex<Option<Result<(), String>>>,
+}
+
+#[derive(Default)]
+struct Metrics {
+    flushes: AtomicU64,
+    requests: AtomicU64,
+    largest_batch: AtomicU64,
+    batch_sizes: [AtomicU64; BATCH_SIZE_BUCKETS],
+    latency: AtomicHistogram,
+}
+
+impl Flusher {
+    pub(super) fn new(db: Db) -> Self {
+        Flusher {
+            shared: Arc::new(Shared {
+                db,
+                state: Mutex::new(State::default()),
+                wake: Condvar::new(),
+                flushed: Condvar::new(),
+                metrics: Metrics::default(),
+            }),
+        }
+    }
+
+    /**
+    Flush the database before returning.
+
+    The flush may be shared with other callers.
+    */
+    pub(super) fn flush(&self) -> Result<(), Error> {
+        self.shared.flush(1).map_err(|e| {
+            Error::msg(format!("failed to flush database: {}", e)).with_kind(ErrorKind::Io)
+        })
+    }
+
+    /**
+    Flush the database within the given window before returning.
+
+    The flush is shared with any other callers that join the group before it's flushed.
+    */
+    pub(super) fn flush_within(&self, window: Duration) -> Result<(), Error> {
+        let mut state = self.shared.state.lock().expect("failed to lock flusher");
+
+        let deadline = Instant::now() + window;
+        state.deadline = Some(match state.deadline {
+            Some(scheduled) if scheduled < deadline => scheduled,
+            _ => deadline,
+        });
+
+        let group = state.group.get_or_insert_with(Default::default).clone();
+        group.requests.fetch_add(1, Ordering::SeqCst);
+
+        if !state.running {
+            let shared = self.shared.clone();
+
+            thread::Builder::new()
+                .name("db-flush".to_owned())
+                .spawn(move || shared.run())
+                .map_err(Error::fail)?;
+
+            state.running = true;
+        }
+
+        self.shared.wake.notify_one();
+
+        loop {
+            if let Some(result) = group.result() {
+                return result.map_err(|e| {
+                    Error::msg(format!("failed to flush database: {}", e)).with_kind(ErrorKind::Io)
+                });
+            }
+
+            state = self
+                .shared
+                .flushed
+                .wait(state)
+                .expect("failed to lock flusher");
+        }
+    }
+
+    pub(super) fn metrics(&self) -> FlushMetrics {
+        self.shared.metrics.snapshot()
+    }
+}
+
+impl Drop for Flusher {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.shutdown = true;
+            self.shared.wake.notify_one();
+        }
+    }
+}
+
+impl Shared {
+    /**
+    Flush the database on behalf of some number of requests.
+
+    If a flush is already in progress then the requests join the batch
+    waiting for it to finish. The first waiter to notice the flush has
+    finished flushes the whole batch and wakes the others.
+    */
+    fn flush(&self, requests: u64) -> Result<(), String> {
+        let mut state = self.state.lock().expect("failed to lock flusher");
+
+        let batch = state.next.get_or_insert_with(Default::default).clone();
+        batch.requests.fetch_add(requests, Ordering::SeqCst);
+
+        loop {
+            if let Some(result) = batch.result() {
+                return result;
+            }
+
+            if !state.flushing {
+                // The batch waiting for the flush is always the one we joined
+                let batch = state.next.take().expect("missing flush batch");
+                state.flushing = true;
+                drop(state);
+
+                let requests = batch.requests.load(Ordering::SeqCst);
+                let start = Instant::now();
+                let result = self.db.flush().map(drop).map_err(|e| e.to_string());
+                self.metrics.record(requests, start.elapsed());
+
+                if let Err(ref e) = result {
+                    log::error!(
+                        "failed to flush database for {} completions: {}",
+                        requests,
+                        e
+                    );
+                }
+
+                state = self.state.lock().expect("failed to lock flusher");
+                *batch.result.lock().expect("failed to lock flush batch") = Some(result.clone());
+                state.flushing = false;
+
+                self.flushed.notify_all();
+
+                return result;
+            }
+
+            state = self.flushed.wait(state).expect("failed to lock flusher");
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().expect("failed to lock flusher");
+
+        loop {
+            match state.deadline {
+                // Flush early if the flusher is being shut down
+                Some(deadline) if state.shutdown || Instant::now() >= deadline => {
+                    state.deadline = None;
+                    let group = state.group.take().expect("missing flush group");
+                    drop(state);
+
+                    let flushed = self.flush(group.requests.load(Ordering::SeqCst));
+
+                    state = self.state.lock().expect("failed to lock flusher");
+                    *group.result.lock().expect("failed to lock flush batch") = Some(flushed);
+
+                    self.flushed.notify_all();
+                }
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+
+                    state = self
+                        .wake
+                        .wait_timeout(state, timeout)
+                        .expect("failed to lock flusher")
+                        .0;
+                }
+                None if state.shutdown => {
+                    state.running = false;
+                    return;
+                }
+                None => {
+                    state = self.wake.wait(state).expect("failed to lock flusher");
+                }
+            }
+        }
+    }
+}
+
+impl Batch {
+    fn result(&self) -> Option<Result<(), String>> {
+        self.result
+            .lock()
+            .expect("failed to lock flush batch")
+            .clone()
+    }
+}
+
+impl Metrics {
+    fn record(&self, requests: u64, latency: Duration) {
+        let bucket = (63 - requests.max(1).leading_zeros() as usize).min(BATCH_SIZE_BUCKETS - 1);
+
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        self.requests.fetch_add(requests, Ordering::Relaxed);
+        self.largest_batch.fetch_max(requests, Ordering::Relaxed);
+        self.batch_sizes[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency.record(latency);
+    }
+
+    fn snapshot(&self) -> FlushMetrics {
+        let mut batch_sizes = [0; BATCH_SIZE_BUCKETS];
+        for (snapshot, bucket) in batch_sizes.iter_mut().zip(self.batch_sizes.iter()) {
+            *snapshot = bucket.load(Ordering::Relaxed);
+        }
+
+        FlushMetrics {
+            flushes: self.flushes.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+            largest_batch: self.largest_batch.load(Ordering::Relaxed),
+            batch_sizes,
+            latency: self.latency.snapshot(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        data::Data,
+        store::{
+            test_util::{
+                key,
+                write,
+                TempStore,
+            },
+            Store,
+            WriteOptions,
+        },
+    };
+
+    fn group_commit(store: &Store, window: Duration, k: &[u8]) -> Result<(), Error> {
+        let mut writer = store.write_begin_with_options(WriteOptions {
+            durability: Durability::GroupCommit(window),
+        })?;
+        writer.set(Data {
+            key: key(k),
+            payload: b"{}".to_vec(),
+        })?;
+        writer.complete()
+    }
+
+    #[test]
+    fn group_commit_waits_for_flush() {
+        let store = TempStore::new();
+
+        group_commit(&store, Duration::from_millis(5), b"a").unwrap();
+
+        // The flush has finished by the time the writer is completed
+        let metrics = store.flush_metrics();
+        assert_eq!(1, metrics.flushes);
+        assert_eq!(1, metrics.requests);
+
+        let state = store.flusher.shared.state.lock().unwrap();
+        assert!(state.group.is_none());
+        assert!(state.deadline.is_none());
+    }
+
+    #[test]
+    fn group_commit_flushes_at_earliest_deadline() {
+        let store = Arc::new(TempStore::new());
+
+        let start = Instant::now();
+
+        let slow = {
+            let store = store.clone();
+            thread::spawn(move || group_commit(&store, Duration::from_secs(60), b"a"))
+        };
+
+        // Wait for the slow writer to join the group
+        while store.flusher.shared.state.lock().unwrap().group.is_none() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        group_commit(&store, Duration::from_millis(5), b"b").unwrap();
+        slow.join().unwrap().unwrap();
+
+        // Both writers shared the flush made at the earlier deadline
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        let metrics = store.flush_metrics();
+        assert_eq!(1, metrics.flushes);
+        assert_eq!(2, metrics.largest_batch);
+    }
+
+    #[test]
+    fn concurrent_completions_share_flushes() {
+        let store = Arc::new(TempStore::new());
+        let writers = 8;
+
+        let handles: Vec<_> = (0..writers)
+            .map(|i| {
+                let store = store.clone();
+
+                thread::spawn(move || {
+                    write(&store, &[(&[i as u8], "{}")]);
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let metrics = store.flush_metrics();
+
+        assert_eq!(writers, metrics.requests);
+        assert!(metrics.flushes >= 1 && metrics.flushes <= writers);
+        assert_eq!(metrics.flushes, metrics.batch_sizes.iter().sum::<u64>());
+        assert!(metrics.largest_batch >= 1);
+    }
+
+    #[test]
+    fn batch_size_buckets() {
+        let metrics = Metrics::default();
+
+        for requests in &[1, 2, 3, 4, 200] {
+            metrics.record(*requests, Duration::from_millis(1));
+        }
+
+        let snapshot = metrics.snapshot();
+
+        assert_eq!(5, snapshot.flushes);
+        assert_eq!(210, snapshot.requests);
+        assert_eq!(200, snapshot.largest_batch);
+        assert_eq!([1, 2, 1, 0, 0, 0, 0, 1], snapshot.batch_sizes);
+        assert_eq!(42.0, snapshot.mean_batch());
+        assert_eq!(5, snapshot.latency.count);
+    }
+}
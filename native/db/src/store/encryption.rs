@@ -0,0 +1,403 @@
+use std::{
+    collections::HashMap,
+    fmt,
+};
+
+use chacha20poly1305::{
+    aead::{
+        Aead,
+        AeadCore,
+        KeyInit,
+        OsRng,
+        Payload,
+    },
+    ChaCha20Poly1305,
+    Key as CipherKey,
+    Nonce,
+};
+use failure_derive::*;
+use zeroize::Zeroize;
+
+use crate::{
+    data::Key,
+    error::{
+        Error,
+        ErrorKind,
+    },
+    store::Store,
+};
+
+pub const ENCRYPTION_KEY_SIZE: usize = 32;
+pub(super) const NONCE_SIZE: usize = 12;
+
+/**
+A key used to encrypt payloads with ChaCha20-Poly1305.
+
+Each key has an id that's stored alongside the payloads it encrypts,
+so payloads can be decrypted after the active key has been rotated.
+
+The bytes of the key are zeroed when it's dropped. Ciphers are created
+by borrowing these bytes, and `ChaCha20Poly1305` zeroes its own copy when it's dropped,
+so the only copies left behind are the ones owned by the caller.
+*/
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: u32,
+    bytes: [u8; ENCRYPTION_KEY_SIZE],
+}
+
+impl EncryptionKey {
+    pub fn new(id: u32, bytes: [u8; ENCRYPTION_KEY_SIZE]) -> Self {
+        EncryptionKey { id, bytes }
+    }
+
+    /**
+    Create a key by copying its bytes from a borrowed array.
+
+    Unlike `new`, this doesn't pass a copy of the bytes by value that would never be zeroed.
+    */
+    pub fn from_bytes(id: u32, bytes: &[u8; ENCRYPTION_KEY_SIZE]) -> Self {
+        let mut key = EncryptionKey {
+            id,
+            bytes: [0; ENCRYPTION_KEY_SIZE],
+        };
+        key.bytes.copy_from_slice(bytes);
+
+        key
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Drop for EncryptionKey {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .field("bytes", &"<redacted>")
+            .finish()
+    }
+}
+
+/**
+The keys used to encrypt and decrypt payloads.
+
+New payloads are always encrypted with the active key.
+Retired keys are only used to decrypt payloads that were written before a rotation.
+*/
+#[derive(Debug, Clone)]
+pub struct Encryption {
+    active: EncryptionKey,
+    retired: Vec<EncryptionKey>,
+}
+
+impl Encryption {
+    pub fn new(active: EncryptionKey) -> Self {
+        Encryption {
+            active,
+            retired: Vec::new(),
+        }
+    }
+
+    /**
+    Allow payloads encrypted with a previous key to be read.
+
+    Once `Store::reencrypt` has rewritten any payloads using the key it can be dropped.
+    */
+    pub fn with_retired(mut self, key: EncryptionKey) -> Self {
+        self.retired.push(key);
+        self
+    }
+}
+
+/**
+A payload that can't be decrypted.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Fail)]
+pub enum DecryptError {
+    #[fail(
+        display = "the record is encrypted with key `{}`, which isn't available",
+        _0
+    )]
+    UnknownKey(u32),
+    #[fail(display = "the record failed authentication with key `{}`", _0)]
+    Unauthenticated(u32),
+}
+
+pub(super) struct Ciphers {
+    active: u32,
+    ciphers: HashMap<u32, ChaCha20Poly1305>,
+}
+
+impl Ciphers {
+    pub(super) fn new(encryption: &Encryption) -> Self {
+        let ciphers = encryption
+            .retired
+            .iter()
+            .chain(Some(&encryption.active))
+            // Borrow the key instead of converting it, so there's no temporary copy to zero
+            .map(|key| {
+                (
+                    key.id,
+                    ChaCha20Poly1305::new(CipherKey::from_slice(&key.bytes)),
+                )
+            })
+            .collect();
+
+        Ciphers {
+            active: encryption.active.id,
+            ciphers,
+        }
+    }
+
+    pub(super) fn active(&self) -> u32 {
+        self.active
+    }
+
+    /**
+    Encrypt a payload with the active key.
+
+    The header of the record and its key are authenticated along with the payload,
+    so a payload can't be moved to another key, or have its header tampered with,
+    without being detected.
+    */
+    pub(super) fn encrypt(
+        &self,
+        header: &[u8],
+        key: &Key,
+        payload: &[u8],
+    ) -> Result<([u8; NONCE_SIZE], Vec<u8>), Error> {
+        let cipher = &self.ciphers[&self.active];
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let encrypted = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: payload,
+                    aad: &aad(header, key),
+                },
+            )
+            .map_err(|_| {
+                Error::msg(format!(
+                    "failed to encrypt payload with key `{}`",
+                    self.active
+                ))
+                .with_key(*key)
+            })?;
+
+        Ok((nonce.into(), encrypted))
+    }
+
+    pub(super) fn decrypt(
+        &self,
+        key_id: u32,
+        nonce: &[u8],
+        header: &[u8],
+        key: &Key,
+        encrypted: &[u8],
+    ) -> Result<Vec<u8>, DecryptError> {
+        let cipher = self
+            .ciphers
+            .get(&key_id)
+            .ok_or(DecryptError::UnknownKey(key_id))?;
+
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: encrypted,
+                    aad: &aad(header, key),
+                },
+            )
+            .map_err(|_| DecryptError::Unauthenticated(key_id))
+    }
+}
+
+fn aad(header: &[u8], key: &Key) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(header.len() + key.as_ref().len());
+    aad.extend_from_slice(header);
+    aad.extend_from_slice(key.as_ref());
+
+    aad
+}
+
+/**
+The outcome of rewriting records with the active key.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Reencryption {
+    /**
+    The number of records that were rewritten with the active key.
+    */
+    pub reencrypted: usize,
+    /**
+    The number of records that couldn't be read, so weren't rewritten.
+
+    Use `Store::verify` to find out what's wrong with them.
+    */
+    pub skipped: usize,
+}
+
+pub(super) fn reencrypt(store: &Store) -> Result<Reencryption, Error> {
+    if !store.format.is_encrypted() {
+        return Err(
+            Error::msg("can't re-encrypt a store that was opened without encryption keys")
+                .with_kind(ErrorKind::InvalidArgument),
+        );
+    }
+
+    let mut reencryption = Reencryption::default();
+
+    for kv in store.db.iter() {
+        let (k, v) = kv.map_err(Error::fail)?;
+
+        // Records that can't be read are skipped so the rest of the store can still be rotated
+        let current = match store.format.is_current(&v) {
+            Ok(current) => current,
+            Err(_) => {
+                reencryption.skipped += 1;
+                continue;
+            }
+        };
+
+        if current {
+            continue;
+        }
+
+        let key = match Key::from_slice(&k) {
+            Ok(key) => key,
+            Err(_) => {
+                reencryption.skipped += 1;
+                continue;
+            }
+        };
+
+        let payload = match store.format.decode(&key, v.clone()) {
+            Ok(payload) => payload,
+            Err(_) => {
+                reencryption.skipped += 1;
+                continue;
+            }
+        };
+
+        let record = store.format.encode(&key, payload.as_ref().to_vec())?;
+
+        // If the record has changed since we read it then it's
+        // already been written with the active key
+        match store.db.cas(&k, Some(&v), Some(record)) {
+            Ok(()) => reencryption.reencrypted += 1,
+            Err(sled::Error::CasFailed(_)) => (),
+            Err(e) => return Err(Error::fail(e)),
+        }
+    }
+
+    Ok(reencryption)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::{
+        record::Format,
+        test_util::TempStore,
+        Options,
+    };
+
+    #[test]
+    fn key_from_bytes_encrypts_like_new() {
+        let bytes = [1; ENCRYPTION_KEY_SIZE];
+        let key = Key::from_slice(b"a").unwrap();
+
+        let borrowed = Ciphers::new(&Encryption::new(EncryptionKey::from_bytes(1, &bytes)));
+        let owned = Ciphers::new(&Encryption::new(EncryptionKey::new(1, bytes)));
+
+        let (nonce, encrypted) = borrowed.encrypt(b"header", &key, b"{}").unwrap();
+        assert_eq!(
+            b"{}".to_vec(),
+            owned
+                .decrypt(1, &nonce, b"header", &key, &encrypted)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn reencrypt_rewrites_records_with_retired_keys() {
+        let retired = EncryptionKey::new(1, [1; 32]);
+
+        let store = TempStore::with_options(Options {
+            encryption: Some(
+                Encryption::new(EncryptionKey::new(2, [2; 32])).with_retired(retired.clone()),
+            ),
+            ..Default::default()
+        });
+
+        // Write a record as if it was written before the key was rotated
+        let key = Key::from_slice(b"a").unwrap();
+        let record = Format::new(&Options {
+            encryption: Some(Encryption::new(retired)),
+            ..Default::default()
+        })
+        .encode(&key, b"{}".to_vec())
+        .unwrap();
+        store.db.set(key, record).unwrap();
+
+        assert_eq!(1, store.reencrypt().unwrap().reencrypted);
+        assert_eq!(0, store.reencrypt().unwrap().reencrypted);
+        assert!(store.verify().unwrap().is_intact());
+    }
+
+    #[test]
+    fn reencrypt_skips_unreadable_records() {
+        let retired = EncryptionKey::new(1, [1; 32]);
+
+        let store = TempStore::with_options(Options {
+            encryption: Some(
+                Encryption::new(EncryptionKey::new(2, [2; 32])).with_retired(retired.clone()),
+            ),
+            ..Default::default()
+        });
+
+        let retired = Format::new(&Options {
+            encryption: Some(Encryption::new(retired)),
+            ..Default::default()
+        });
+        let lost = Format::new(&Options {
+            encryption: Some(Encryption::new(EncryptionKey::new(3, [3; 32]))),
+            ..Default::default()
+        });
+
+        // A record encrypted with a key that isn't available
+        let key = Key::from_slice(b"a").unwrap();
+        store
+            .db
+            .set(key, lost.encode(&key, b"{}".to_vec()).unwrap())
+            .unwrap();
+
+        // A record with an invalid key
+        store
+            .db
+            .set(vec![1; 20], retired.encode(&key, b"{}".to_vec()).unwrap())
+            .unwrap();
+
+        // A record that can be rewritten
+        let key = Key::from_slice(b"b").unwrap();
+        store
+            .db
+            .set(key, retired.encode(&key, b"{}".to_vec()).unwrap())
+            .unwrap();
+
+        let reencryption = store.reencrypt().unwrap();
+
+        assert_eq!(1, reencryption.reencrypted);
+        assert_eq!(2, reencryption.skipped);
+    }
+}
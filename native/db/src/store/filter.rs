@@ -0,0 +1,725 @@
+/*!
+Filters evaluated against JSON payloads while they're read.
+
+Filters are written as expressions like:
+
+```text
+status == "open" and (priority >= 2 or not exists(assignee))
+```
+
+The supported expressions are:
+
+- comparisons between a field and a JSON literal: `==`, `!=`, `<`, `<=`, `>`, `>=`.
+- `exists(field)` to check whether a field is present.
+- `and`, `or`, and `not` to combine them, with `(` and `)` for grouping.
+
+Fields are a `.` separated list of object fields, like `status` or `customer.id`.
+Fields that aren't identifiers, or that are keywords like `and` or `exists`, can be
+quoted with backticks, like ``customer.`first name` `` or `` `and` ``. A backtick
+in a quoted field is written as two backticks.
+Expressions can be nested up to 32 levels deep with `not` and parentheses.
+Ranges only match when both sides are numbers or both sides are strings.
+Integers are compared exactly, however large they are.
+Payloads that aren't JSON never match.
+*/
+
+use std::{
+    cmp::Ordering,
+    iter::Peekable,
+    str::CharIndices,
+};
+
+use failure_derive::*;
+use serde_json::{
+    Number,
+    Value,
+};
+
+use crate::error::{
+    Error,
+    ErrorKind,
+};
+
+/*
+The deepest that `not` and parentheses can be nested.
+Filters are parsed and evaluated recursively, so this keeps them from exhausting the stack.
+*/
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid filter at position {}: {}", position, msg)]
+struct ParseError {
+    position: usize,
+    msg: String,
+}
+
+impl ParseError {
+    fn into_error(self) -> Error {
+        Error::fail(self).with_kind(ErrorKind::InvalidFilter)
+    }
+}
+
+/**
+A filter over JSON payloads.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Compare {
+        field: Vec<String>,
+        op: Op,
+        value: Value,
+    },
+    Exists(Vec<String>),
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+/**
+A comparison between a field and a value.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Filter {
+    /**
+    Parse a filter from an expression.
+    */
+    pub fn parse(expr: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(expr).map_err(ParseError::into_error)?,
+            next: 0,
+            len: expr.len(),
+            depth: 0,
+        };
+
+        let filter = parser.or().map_err(ParseError::into_error)?;
+
+        if let Some(&(position, _)) = parser.tokens.get(parser.next) {
+            return Err(ParseError {
+                position,
+                msg: "expected the end of the filter".to_owned(),
+            }
+            .into_error());
+        }
+
+        Ok(filter)
+    }
+
+    /**
+    Whether a payload matches the filter.
+    */
+    pub fn matches(&self, payload: &[u8]) -> bool {
+        match serde_json::from_slice(payload) {
+            Ok(doc) => self.eval(&doc),
+            Err(_) => false,
+        }
+    }
+
+    fn eval(&self, doc: &Value) -> bool {
+        match *self {
+            Filter::Compare {
+                ref field,
+                op,
+                ref value,
+            } => match lookup(doc, field) {
+                Some(field) => compare(field, op, value),
+                None => false,
+            },
+            Filter::Exists(ref field) => lookup(doc, field).is_some(),
+            Filter::Not(ref filter) => !filter.eval(doc),
+            Filter::And(ref filters) => filters.iter().all(|filter| filter.eval(doc)),
+            Filter::Or(ref filters) => filters.iter().any(|filter| filter.eval(doc)),
+        }
+    }
+}
+
+fn lookup<'a>(doc: &'a Value, field: &[String]) -> Option<&'a Value> {
+    field
+        .iter()
+        .try_fold(doc, |value, field| value.get(field.as_str()))
+}
+
+fn compare(field: &Value, op: Op, value: &Value) -> bool {
+    let ord = match (field, value) {
+        (Value::Number(a), Value::Number(b)) => compare_numbers(a, b),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ if field == value => Some(Ordering::Equal),
+        _ => None,
+    };
+
+    match (op, ord) {
+        (Op::Eq, ord) => ord == Some(Ordering::Equal),
+        (Op::Ne, ord) => ord != Some(Ordering::Equal),
+        (_, None) => false,
+        (Op::Lt, Some(ord)) => ord == Ordering::Less,
+        (Op::Le, Some(ord)) => ord != Ordering::Greater,
+        (Op::Gt, Some(ord)) => ord == Ordering::Greater,
+        (Op::Ge, Some(ord)) => ord != Ordering::Less,
+    }
+}
+
+/**
+Compare two numbers.
+
+Integers are compared exactly, because they may not be representable as floats.
+*/
+fn compare_numbers(a: &Number, b: &Number) -> Option<Ordering> {
+    fn integer(n: &Number) -> Option<i128> {
+        n.as_i64()
+            .map(Into::into)
+            .or_else(|| n.as_u64().map(Into::into))
+    }
+
+    match (integer(a), integer(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => a.as_f64().partial_cmp(&b.as_f64()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Field(Vec<String>),
+    Literal(Value),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Exists,
+    Open,
+    Close,
+}
+
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                Token::Open
+            }
+            ')' => {
+                chars.next();
+                Token::Close
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let eq = chars.peek().map(|&(_, c)| c) == Some('=');
+                if eq {
+                    chars.next();
+                }
+
+                match (c, eq) {
+                    ('=', true) => Token::Op(Op::Eq),
+                    ('!', true) => Token::Op(Op::Ne),
+                    ('<', false) => Token::Op(Op::Lt),
+                    ('<', true) => Token::Op(Op::Le),
+                    ('>', false) => Token::Op(Op::Gt),
+                    ('>', true) => Token::Op(Op::Ge),
+                    _ => {
+                        return Err(ParseError {
+                            position: start,
+                            msg: format!("unexpected `{}`", c),
+                        })
+                    }
+                }
+            }
+            '"' => {
+                chars.next();
+
+                let mut escaped = false;
+                let end = loop {
+                    match chars.next() {
+                        Some((_, '\\')) if !escaped => escaped = true,
+                        Some((i, '"')) if !escaped => break i + 1,
+                        Some(_) => escaped = false,
+                        None => {
+                            return Err(ParseError {
+                                position: start,
+                                msg: "unterminated string".to_owned(),
+                            })
+                        }
+                    }
+                };
+
+                Token::Literal(literal(start, &expr[start..end])?)
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.' {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                Token::Literal(literal(start, &expr[start..end])?)
+            }
+            c if c.is_alphabetic() || c == '_' || c == '`' => {
+                let (field, quoted) = field(start, &mut chars)?;
+
+                // Quoted fields are never keywords
+                let keyword = match (quoted, field.as_slice()) {
+                    (false, [word]) => Some(word.as_str()),
+                    _ => None,
+                };
+
+                match keyword {
+                    Some("and") => Token::And,
+                    Some("or") => Token::Or,
+                    Some("not") => Token::Not,
+                    Some("exists") => Token::Exists,
+                    Some(word @ "true") | Some(word @ "false") | Some(word @ "null") => {
+                        Token::Literal(literal(start, word)?)
+                    }
+                    _ => Token::Field(field),
+                }
+            }
+            c => {
+                return Err(ParseError {
+                    position: start,
+                    msg: format!("unexpected `{}`", c),
+                })
+            }
+        };
+
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+/**
+Read a `.` separated field, where each part is either an identifier or quoted with backticks.
+
+Also returns whether any part was quoted, so quoted fields aren't mistaken for keywords.
+*/
+fn field(
+    start: usize,
+    chars: &mut Peekable<CharIndices>,
+) -> Result<(Vec<String>, bool), ParseError> {
+    let mut field = Vec::new();
+    let mut quoted = false;
+
+    loop {
+        let part = if chars.peek().map(|&(_, c)| c) == Some('`') {
+            chars.next();
+            quoted = true;
+
+            let mut part = String::new();
+            loop {
+                match chars.next() {
+                    // Two backticks are an escaped backtick
+                    Some((_, '`')) if chars.peek().map(|&(_, c)| c) == Some('`') => {
+                        chars.next();
+                        part.push('`');
+                    }
+                    Some((_, '`')) => break,
+                    Some((_, c)) => part.push(c),
+                    None => {
+                        return Err(ParseError {
+                            position: start,
+                            msg: "unterminated quoted field".to_owned(),
+                        })
+                    }
+                }
+            }
+
+            part
+        } else {
+            let mut part = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    part.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            if part.is_empty() {
+                return Err(ParseError {
+                    position: start,
+                    msg: "invalid field, expected a name after `.`".to_owned(),
+                });
+            }
+
+            part
+        };
+
+        field.push(part);
+
+        if chars.peek().map(|&(_, c)| c) == Some('.') {
+            chars.next();
+        } else {
+            return Ok((field, quoted));
+        }
+    }
+}
+
+fn literal(position: usize, literal: &str) -> Result<Value, ParseError> {
+    serde_json::from_str(literal).map_err(|e| ParseError {
+        position,
+        msg: format!("invalid literal `{}`: {}", literal, e),
+    })
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    len: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Filter, ParseError> {
+        let mut filters = vec![self.and()?];
+
+        while self.take(&Token::Or) {
+            filters.push(self.and()?);
+        }
+
+        if filters.len() == 1 {
+            Ok(filters.remove(0))
+        } else {
+            Ok(Filter::Or(filters))
+        }
+    }
+
+    fn and(&mut self) -> Result<Filter, ParseError> {
+        let mut filters = vec![self.unary()?];
+
+        while self.take(&Token::And) {
+            filters.push(self.unary()?);
+        }
+
+        if filters.len() == 1 {
+            Ok(filters.remove(0))
+        } else {
+            Ok(Filter::And(filters))
+        }
+    }
+
+    fn unary(&mut self) -> Result<Filter, ParseError> {
+        if self.take(&Token::Not) {
+            return Ok(Filter::Not(Box::new(self.nested(Parser::unary)?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Filter, ParseError> {
+        match self.advance("a filter")? {
+            Token::Open => {
+                let filter = self.nested(Parser::or)?;
+                self.expect(&Token::Close, "`)`")?;
+
+                Ok(filter)
+            }
+            Token::Exists => {
+                self.expect(&Token::Open, "`(`")?;
+                let field = self.field()?;
+                self.expect(&Token::Close, "`)`")?;
+
+                Ok(Filter::Exists(field))
+            }
+            Token::Field(field) => {
+                let op = match self.advance("a comparison")? {
+                    Token::Op(op) => op,
+                    _ => return Err(self.error("expected a comparison")),
+                };
+
+                let value = match self.advance("a literal")? {
+                    Token::Literal(value) => value,
+                    _ => return Err(self.error("expected a literal")),
+                };
+
+                Ok(Filter::Compare { field, op, value })
+            }
+            _ => Err(self.error("expected a filter")),
+        }
+    }
+
+    /**
+    Parse a filter that's nested inside another one.
+    */
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Parser) -> Result<Filter, ParseError>,
+    ) -> Result<Filter, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(&format!(
+                "the filter is nested more than {} levels deep",
+                MAX_DEPTH
+            )));
+        }
+
+        self.depth += 1;
+        let filter = parse(self);
+        self.depth -= 1;
+
+        filter
+    }
+
+    fn field(&mut self) -> Result<Vec<String>, ParseError> {
+        match self.advance("a field")? {
+            Token::Field(field) => Ok(field),
+            _ => Err(self.error("expected a field")),
+        }
+    }
+
+    fn take(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.next).map(|(_, t)| t) == Some(token) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token, expected: &str) -> Result<(), ParseError> {
+        if self.take(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", expected)))
+        }
+    }
+
+    fn advance(&mut self, expected: &str) -> Result<Token, ParseError> {
+        match self.tokens.get(self.next) {
+            Some((_, token)) => {
+                self.next += 1;
+                Ok(token.clone())
+            }
+            None => Err(ParseError {
+                position: self.len,
+                msg: format!("expected {} but the filter ended", expected),
+            }),
+        }
+    }
+
+    /**
+    An error at the most recently consumed token.
+    */
+    fn error(&self, msg: &str) -> ParseError {
+        let position = self
+            .tokens
+            .get(self.next.saturating_sub(1))
+            .map(|&(position, _)| position)
+            .unwrap_or(self.len);
+
+        ParseError {
+            position,
+            msg: msg.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::test_util::{
+        write,
+        TempStore,
+    };
+
+    fn matches(filter: &str, payload: &str) -> bool {
+        Filter::parse(filter).unwrap().matches(payload.as_bytes())
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let filter = Filter::parse("a == 1 or b == 2 and not c == 3").unwrap();
+
+        assert_match!(Filter::Or(ref filters) = filter => {
+            assert_match!(Filter::And(ref filters) = filters[1] => {
+                assert_match!(Filter::Not(_) = filters[1]);
+            });
+        });
+    }
+
+    #[test]
+    fn parse_chains_are_flat() {
+        let filter = Filter::parse(&vec!["a == 1"; 1000].join(" and ")).unwrap();
+
+        assert_match!(Filter::And(ref filters) = filter => {
+            assert_eq!(1000, filters.len());
+        });
+    }
+
+    #[test]
+    fn parse_nested_too_deeply() {
+        let nested = |depth| format!("{}a == 1{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(Filter::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            ErrorKind::InvalidFilter,
+            Filter::parse(&nested(MAX_DEPTH + 1)).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidFilter,
+            Filter::parse(&nested(100_000)).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidFilter,
+            Filter::parse(&format!("{}a == 1", "not ".repeat(100_000)))
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn parse_quoted_fields() {
+        assert_eq!(
+            Filter::Exists(vec!["and".to_owned()]),
+            Filter::parse("exists(`and`)").unwrap()
+        );
+        assert_eq!(
+            Filter::Exists(vec!["a".to_owned(), "b.c".to_owned(), "d`e".to_owned()]),
+            Filter::parse("exists(a.`b.c`.`d``e`)").unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for filter in &[
+            "",
+            "a ==",
+            "a 1",
+            "== 1",
+            "(a == 1",
+            "a == 1)",
+            "a = 1",
+            "exists a",
+            "a == \"open",
+            "a..b == 1",
+            "a == 1 b == 2",
+            "`a == 1",
+            "a. == 1",
+        ] {
+            assert!(Filter::parse(filter).is_err(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn match_equality() {
+        assert!(matches(r#"status == "open""#, r#"{"status":"open"}"#));
+        assert!(!matches(r#"status == "open""#, r#"{"status":"closed"}"#));
+        assert!(matches(r#"status != "open""#, r#"{"status":"closed"}"#));
+        assert!(!matches(r#"status != "open""#, r#"{}"#));
+        assert!(matches("count == 1", r#"{"count":1.0}"#));
+        assert!(matches("done == true", r#"{"done":true}"#));
+        assert!(matches("owner == null", r#"{"owner":null}"#));
+        assert!(matches(r#"a.b == "c""#, r#"{"a":{"b":"c"}}"#));
+    }
+
+    #[test]
+    fn match_ranges() {
+        assert!(matches("n > 1 and n <= 3", r#"{"n":3}"#));
+        assert!(!matches("n > 1 and n <= 3", r#"{"n":4}"#));
+        assert!(matches("n >= -1.5", r#"{"n":-1}"#));
+        assert!(matches(r#"s < "b""#, r#"{"s":"a"}"#));
+        assert!(!matches(r#"n < "b""#, r#"{"n":1}"#));
+    }
+
+    #[test]
+    fn match_large_integers() {
+        let max = u64::MAX;
+
+        assert!(matches(
+            &format!("n == {}", max),
+            &format!(r#"{{"n":{}}}"#, max)
+        ));
+        assert!(!matches(
+            &format!("n == {}", max),
+            &format!(r#"{{"n":{}}}"#, max - 1)
+        ));
+        assert!(matches(
+            &format!("n < {}", max),
+            &format!(r#"{{"n":{}}}"#, max - 1)
+        ));
+        assert!(matches(
+            &format!("n > {}", i64::MIN),
+            &format!(r#"{{"n":{}}}"#, max)
+        ));
+        assert!(!matches(
+            "n == 9007199254740992",
+            r#"{"n":9007199254740993}"#
+        ));
+    }
+
+    #[test]
+    fn match_quoted_fields() {
+        assert!(matches("`true` == true", r#"{"true":true}"#));
+        assert!(matches(
+            r#"not exists(`or`) and `first name` == "a""#,
+            r#"{"first name":"a"}"#
+        ));
+    }
+
+    #[test]
+    fn match_exists_and_combinators() {
+        let filter = r#"status == "open" and (priority >= 2 or not exists(assignee))"#;
+
+        assert!(matches(
+            filter,
+            r#"{"status":"open","priority":3,"assignee":"a"}"#
+        ));
+        assert!(matches(filter, r#"{"status":"open","priority":1}"#));
+        assert!(!matches(
+            filter,
+            r#"{"status":"open","priority":1,"assignee":"a"}"#
+        ));
+        assert!(!matches(filter, r#"{"status":"closed"}"#));
+    }
+
+    #[test]
+    fn match_non_json() {
+        assert!(!matches("not exists(a)", "not json"));
+    }
+
+    #[test]
+    fn read_with_filter() {
+        let store = TempStore::new();
+
+        write(
+            &store,
+            &[
+                (b"a", r#"{"n":1}"#),
+                (b"b", r#"{"n":2}"#),
+                (b"c", r#"{"n":3}"#),
+            ],
+        );
+
+        let mut reader = store
+            .read_begin_with_filter(Filter::parse("n >= 2").unwrap())
+            .unwrap();
+
+        let mut keys = Vec::new();
+        while reader.move_next().unwrap() {
+            keys.push(
+                reader
+                    .with_current(|current| current.key.to_bytes()[0])
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!(vec![b'b', b'c'], keys);
+    }
+}
@@ -0,0 +1,704 @@
+/*!
+Secondary indexes over fields in JSON payloads.
+
+An index maps the value of a field in each payload back to the keys of the records
+that contain it. Index entries live in their own tree, keyed by:
+
+```text
++------------+------+-------------+------+----------+
+| index name | 0x00 | field value | 0x00 | key      |
+| n bytes    |      | n bytes     |      | 16 bytes |
++------------+------+-------------+------+----------+
+```
+
+Field values are stored as compact JSON, which can never contain a `0x00` byte,
+so all records with a given value can be found with a single prefix scan.
+
+Entries aren't written atomically with the records they point to,
+so records found through an index are always checked against the query
+before they're returned. Writes to the same key are serialized by `Indexes::lock`
+though, so concurrent writers can't leave a record without an entry for its value.
+*/
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    panic::{
+        RefUnwindSafe,
+        UnwindSafe,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        PoisonError,
+    },
+};
+
+use failure_derive::*;
+use serde_json::Value;
+
+use crate::{
+    data::{
+        Data,
+        Key,
+    },
+    error::{
+        Error,
+        ErrorKind,
+    },
+    store::{
+        record::{
+            Format,
+            RawPayload,
+        },
+        Db,
+    },
+};
+
+const ENTRIES_TREE: &[u8] = b"__db_index_entries";
+const DEFINITIONS_TREE: &[u8] = b"__db_index_definitions";
+
+const SEPARATOR: u8 = 0;
+
+/**
+The number of locks that writes to indexed records are spread over.
+*/
+const KEY_LOCKS: usize = 64;
+
+#[derive(Debug, Fail)]
+enum IndexError {
+    #[fail(display = "the index name `{}` is invalid", _0)]
+    InvalidName(String),
+    #[fail(display = "the index path `{}` is invalid", _0)]
+    InvalidPath(String),
+    #[fail(display = "the index `{}` doesn't exist", _0)]
+    UnknownIndex(String),
+    #[fail(display = "the index value isn't valid JSON: {}", _0)]
+    InvalidValue(String),
+}
+
+impl IndexError {
+    fn into_error(self) -> Error {
+        let kind = match self {
+            IndexError::InvalidName(_) | IndexError::InvalidPath(_) => ErrorKind::InvalidIndex,
+            IndexError::UnknownIndex(_) => ErrorKind::UnknownIndex,
+            IndexError::InvalidValue(_) => ErrorKind::InvalidArgument,
+        };
+
+        Error::fail(self).with_kind(kind)
+    }
+}
+
+/**
+A secondary index over a field in JSON payloads.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    name: String,
+    path: String,
+}
+
+impl Index {
+    /**
+    Declare an index over the field at the given path.
+
+    The path is a `.` separated list of object fields, like `status` or `customer.id`.
+    Payloads that aren't JSON objects, or don't contain the field, aren't indexed.
+    */
+    pub fn new(name: impl Into<String>, path: impl Into<String>) -> Result<Self, Error> {
+        let name = name.into();
+        let path = path.into();
+
+        if name.is_empty() || name.as_bytes().contains(&SEPARATOR) {
+            return Err(IndexError::InvalidName(name).into_error());
+        }
+
+        if path.split('.').any(str::is_empty) {
+            return Err(IndexError::InvalidPath(path).into_error());
+        }
+
+        Ok(Index { name, path })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /**
+    Get the compact JSON value of the indexed field in a payload.
+    */
+    fn value(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        let doc: Value = serde_json::from_slice(payload).ok()?;
+
+        self.value_of(&doc)
+    }
+
+    /**
+    Get the compact JSON value of the indexed field in a payload that's already been parsed.
+    */
+    fn value_of(&self, doc: &Value) -> Option<Vec<u8>> {
+        let value = self
+            .path
+            .split('.')
+            .try_fold(doc, |value, field| value.get(field))?;
+
+        Some(serde_json::to_vec(value).expect("failed to serialize JSON"))
+    }
+
+    fn prefix(&self, value: &[u8]) -> Vec<u8> {
+        let mut prefix = Vec::with_capacity(self.name.len() + value.len() + 2);
+
+        prefix.extend_from_slice(self.name.as_bytes());
+        prefix.push(SEPARATOR);
+        prefix.extend_from_slice(value);
+        prefix.push(SEPARATOR);
+
+        prefix
+    }
+
+    fn entry(&self, value: &[u8], key: Key) -> Vec<u8> {
+        let mut entry = self.prefix(value);
+        entry.extend_from_slice(key.as_ref());
+
+        entry
+    }
+}
+
+/**
+The values of each index for a single payload.
+*/
+pub(super) type Values = Vec<Option<Vec<u8>>>;
+
+/**
+The set of indexes maintained for a store.
+*/
+pub(super) struct Indexes {
+    db: Db,
+    entries: Arc<sled::Tree>,
+    indexes: Vec<Index>,
+    locks: Vec<Mutex<()>>,
+}
+
+impl Indexes {
+    /**
+    Open the indexes for a store.
+
+    Indexes that are new or whose path has changed are built from the records already in the store.
+    Indexes that were previously declared but aren't anymore are dropped.
+    */
+    pub(super) fn open(db: &Db, format: &Format, indexes: Vec<Index>) -> Result<Self, Error> {
+        let entries = db.open_tree(ENTRIES_TREE.to_vec()).map_err(Error::fail)?;
+        let definitions = db
+            .open_tree(DEFINITIONS_TREE.to_vec())
+            .map_err(Error::fail)?;
+
+        let mut stale = Vec::new();
+        for kv in definitions.iter() {
+            let (name, path) = kv.map_err(Error::fail)?;
+
+            let is_declared = indexes.iter().any(|index| {
+                index.name.as_bytes() == &name[..] && index.path.as_bytes() == &path[..]
+            });
+
+            if !is_declared {
+                stale.push(name);
+            }
+        }
+
+        for name in stale {
+            clear(&entries, &name)?;
+            definitions.del(&name).map_err(Error::fail)?;
+        }
+
+        let indexes = Indexes {
+            db: db.clone(),
+            entries,
+            indexes,
+            locks: (0..KEY_LOCKS).map(|_| Mutex::new(())).collect(),
+        };
+
+        for index in &indexes.indexes {
+            if definitions
+                .get(index.name.as_bytes())
+                .map_err(Error::fail)?
+                .is_none()
+            {
+                indexes.build(format, index)?;
+
+                definitions
+                    .set(index.name.as_bytes(), index.path.as_bytes().to_vec())
+                    .map_err(Error::fail)?;
+            }
+        }
+
+        Ok(indexes)
+    }
+
+    /**
+    Get the values of each index for a payload.
+
+    The payload is only parsed if there are any indexes.
+    */
+    pub(super) fn values(&self, payload: &[u8]) -> Values {
+        if self.indexes.is_empty() {
+            return Vec::new();
+        }
+
+        match serde_json::from_slice(payload) {
+            Ok(doc) => self.values_of(&doc),
+            Err(_) => vec![None; self.indexes.len()],
+        }
+    }
+
+    /**
+    Get the values of each index for a payload that's already been parsed.
+    */
+    pub(super) fn values_of(&self, doc: &Value) -> Values {
+        self.indexes
+            .iter()
+            .map(|index| index.value_of(doc))
+            .collect()
+    }
+
+    /**
+    Lock a key while its record and index entries are updated.
+
+    Without the lock, concurrent writes to the same key could update the record in one order
+    and its entries in another, leaving the record without an entry for its current value.
+    Keys are spread over a fixed set of locks, so unrelated keys may share one.
+    Nothing is locked if there aren't any indexes.
+    */
+    pub(super) fn lock(&self, key: Key) -> Option<MutexGuard<'_, ()>> {
+        if self.indexes.is_empty() {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        key.as_ref().hash(&mut hasher);
+
+        let lock = &self.locks[hasher.finish() as usize % self.locks.len()];
+
+        // The lock doesn't protect any data, so it's still usable if a writer panicked
+        Some(lock.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /**
+    Update the entries for a record that's been written.
+
+    The key must be locked with `lock` while the record is written and its entries are updated.
+
+    The previous record is the one that was replaced, if there was one.
+    */
+    pub(super) fn set(
+        &self,
+        format: &Format,
+        key: Key,
+        previous: Option<sled::IVec>,
+        values: Values,
+    ) -> Result<(), Error> {
+        let previous = self.previous_values(format, key, previous);
+
+        for ((index, previous), value) in self.indexes.iter().zip(previous).zip(values) {
+            if previous == value {
+                continue;
+            }
+
+            if let Some(previous) = previous {
+                self.entries
+                    .del(index.entry(&previous, key))
+                    .map_err(Error::fail)?;
+            }
+
+            if let Some(value) = value {
+                self.entries
+                    .set(index.entry(&value, key), Vec::new())
+                    .map_err(Error::fail)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+    Remove the entries for a record that's been deleted.
+
+    The key must be locked with `lock` while the record is removed and its entries are updated.
+    */
+    pub(super) fn remove(
+        &self,
+        format: &Format,
+        key: Key,
+        previous: Option<sled::IVec>,
+    ) -> Result<(), Error> {
+        let previous = self.previous_values(format, key, previous);
+
+        for (index, previous) in self.indexes.iter().zip(previous) {
+            if let Some(previous) = previous {
+                self.entries
+                    .del(index.entry(&previous, key))
+                    .map_err(Error::fail)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+    Find the records where the indexed field is equal to the given JSON value.
+    */
+    pub(super) fn query(&self, name: &str, value: &[u8]) -> Result<Query, Error> {
+        let index = self
+            .indexes
+            .iter()
+            .find(|index| index.name == name)
+            .ok_or_else(|| IndexError::UnknownIndex(name.to_owned()).into_error())?;
+
+        // Normalize the value so it matches the way it's stored
+        let value: Value = serde_json::from_slice(value)
+            .map_err(|e| IndexError::InvalidValue(e.to_string()).into_error())?;
+        let value = serde_json::to_vec(&value).expect("failed to serialize JSON");
+
+        Ok(Query::new(
+            self.db.clone(),
+            self.entries.clone(),
+            index.clone(),
+            value,
+        ))
+    }
+
+    fn previous_values(&self, format: &Format, key: Key, previous: Option<sled::IVec>) -> Values {
+        if self.indexes.is_empty() {
+            return Vec::new();
+        }
+
+        // If the previous record can't be decoded then any entries for it are left behind.
+        // They'll be skipped when they're queried.
+        match previous.map(|previous| format.decode(&key, previous)) {
+            Some(Ok(previous)) => self.values(previous.as_ref()),
+            _ => vec![None; self.indexes.len()],
+        }
+    }
+
+    fn build(&self, format: &Format, index: &Index) -> Result<(), Error> {
+        clear(&self.entries, index.name.as_bytes())?;
+
+        for kv in self.db.iter() {
+            let (k, v) = kv.map_err(Error::fail)?;
+
+            // Records that can't be read are reported by `Store::verify`
+            let key = match Key::from_slice(&k) {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+
+            let payload = match format.decode(&key, v) {
+                Ok(payload) => payload,
+                Err(_) => continue,
+            };
+
+            if let Some(value) = index.value(payload.as_ref()) {
+                self.entries
+                    .set(index.entry(&value, key), Vec::new())
+                    .map_err(Error::fail)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn clear(entries: &sled::Tree, name: &[u8]) -> Result<(), Error> {
+    let mut prefix = name.to_vec();
+    prefix.push(SEPARATOR);
+
+    let mut stale = Vec::new();
+    for kv in entries.scan(&prefix) {
+        let (k, _) = kv.map_err(Error::fail)?;
+
+        if !k.starts_with(&prefix) {
+            break;
+        }
+
+        stale.push(k);
+    }
+
+    for k in stale {
+        entries.del(k).map_err(Error::fail)?;
+    }
+
+    Ok(())
+}
+
+rental! {
+    mod scan {
+        use super::*;
+
+        #[rental]
+        pub(super) struct Scan {
+            entries: Arc<sled::Tree>,
+            iter: sled::Iter<'entries>,
+        }
+    }
+}
+
+/**
+The records matching an index query.
+*/
+pub(super) struct Query {
+    db: Db,
+    scan: scan::Scan,
+    index: Index,
+    value: Vec<u8>,
+    prefix: Vec<u8>,
+}
+
+impl Query {
+    fn new(db: Db, entries: Arc<sled::Tree>, index: Index, value: Vec<u8>) -> Self {
+        let prefix = index.prefix(&value);
+        let scan = {
+            let prefix = prefix.clone();
+            scan::Scan::new(entries, move |entries| entries.scan(prefix))
+        };
+
+        Query {
+            db,
+            scan,
+            index,
+            value,
+            prefix,
+        }
+    }
+
+    pub(super) fn next(&mut self, format: &Format) -> Result<Option<Data<RawPayload>>, Error> {
+        loop {
+            let entry = self
+                .scan
+                .rent_mut(|iter| iter.next())
+                .transpose()
+                .map_err(Error::fail)?;
+
+            let key = match entry {
+                Some((entry, _)) if entry.starts_with(&self.prefix) => {
+                    Key::from_slice(&entry[self.prefix.len()..])?
+                }
+                _ => return Ok(None),
+            };
+
+            let record = match self.db.get(key).map_err(Error::fail)? {
+                Some(record) => record,
+                None => continue,
+            };
+
+            let payload = format
+                .decode(&key, record)
+                .map_err(|e| Error::from(e).with_key(key))?;
+
+            // Skip entries that are out of date with their record
+            if self.index.value(payload.as_ref()).as_ref() != Some(&self.value) {
+                continue;
+            }
+
+            return Ok(Some(Data { key, payload }));
+        }
+    }
+}
+
+/*
+NOTE: Usually, just declaring a type as unwind safe like this isn't
+a great idea, especially when it contains other types you don't own.
+We do this here to keep the example moving forward.
+
+See: https://github.com/spacejam/sled/issues/662
+*/
+impl UnwindSafe for Indexes {}
+impl RefUnwindSafe for Indexes {}
+
+impl UnwindSafe for Query {}
+impl RefUnwindSafe for Query {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use crate::store::{
+        test_util::{
+            key,
+            write,
+            TempStore,
+        },
+        validate::Validation,
+        Options,
+        Store,
+    };
+
+    fn status() -> Index {
+        Index::new("status", "status").unwrap()
+    }
+
+    fn query(store: &Store, value: &str) -> Vec<Key> {
+        let mut reader = store.index_query("status", value.as_bytes()).unwrap();
+
+        let mut keys = Vec::new();
+        while reader.move_next().unwrap() {
+            keys.push(reader.with_current(|current| current.key).unwrap());
+        }
+
+        keys
+    }
+
+    fn keys(keys: &[&[u8]]) -> Vec<[u8; 16]> {
+        keys.iter().map(|k| key(k).to_bytes()).collect()
+    }
+
+    fn bytes(keys: Vec<Key>) -> Vec<[u8; 16]> {
+        keys.into_iter().map(Key::to_bytes).collect()
+    }
+
+    #[test]
+    fn invalid_indexes() {
+        assert!(Index::new("", "status").is_err());
+        assert!(Index::new("a\0b", "status").is_err());
+        assert!(Index::new("status", "").is_err());
+        assert!(Index::new("status", "a..b").is_err());
+    }
+
+    #[test]
+    fn index_value_at_path() {
+        let index = Index::new("customer", "customer.id").unwrap();
+
+        assert_eq!(
+            Some(b"42".to_vec()),
+            index.value(br#"{"customer":{"id":42}}"#)
+        );
+        assert_eq!(None, index.value(br#"{"customer":42}"#));
+        assert_eq!(None, index.value(b"not json"));
+    }
+
+    #[test]
+    fn query_finds_matching_records() {
+        let store = TempStore::with_options(Options {
+            indexes: vec![status()],
+            ..Default::default()
+        });
+
+        write(
+            &store,
+            &[
+                (b"a", r#"{"status":"open"}"#),
+                (b"b", r#"{"status":"closed"}"#),
+                (b"c", r#"{"status":"open","title":"c"}"#),
+                (b"d", r#"{"title":"d"}"#),
+            ],
+        );
+
+        assert_eq!(keys(&[b"a", b"c"]), bytes(query(&store, r#" "open" "#)));
+        assert_eq!(keys(&[b"b"]), bytes(query(&store, r#""closed""#)));
+        assert!(query(&store, r#""other""#).is_empty());
+    }
+
+    #[test]
+    fn query_finds_validated_records() {
+        let store = TempStore::with_options(Options {
+            indexes: vec![status()],
+            validation: Some(Validation::json()),
+            ..Default::default()
+        });
+
+        write(&store, &[(b"a", r#"{"status":"open"}"#)]);
+
+        assert_eq!(keys(&[b"a"]), bytes(query(&store, r#""open""#)));
+    }
+
+    #[test]
+    fn query_follows_updates_and_removes() {
+        let store = TempStore::with_options(Options {
+            indexes: vec![status()],
+            ..Default::default()
+        });
+
+        write(
+            &store,
+            &[
+                (b"a", r#"{"status":"open"}"#),
+                (b"b", r#"{"status":"open"}"#),
+            ],
+        );
+
+        write(&store, &[(b"a", r#"{"status":"closed"}"#)]);
+
+        let mut deleter = store.delete_begin().unwrap();
+        deleter.remove(key(b"b")).unwrap();
+        deleter.complete().unwrap();
+
+        assert!(query(&store, r#""open""#).is_empty());
+        assert_eq!(keys(&[b"a"]), bytes(query(&store, r#""closed""#)));
+        assert_eq!(1, store.indexes.entries.len());
+    }
+
+    #[test]
+    fn concurrent_writes_keep_entries() {
+        let store = TempStore::with_options(Options {
+            indexes: vec![status()],
+            ..Default::default()
+        });
+
+        thread::scope(|scope| {
+            for status in &["open", "closed", "pending", "done"] {
+                let store = &store;
+                scope.spawn(move || {
+                    let payload = format!(r#"{{"status":"{}"}}"#, status);
+
+                    for _ in 0..200 {
+                        write(store, &[(b"a", payload.as_str())]);
+                    }
+                });
+            }
+        });
+
+        // Whichever write was last, there's exactly one entry and it points to the record
+        let mut reader = store.read_begin().unwrap();
+        assert!(reader.move_next().unwrap());
+        let current = reader
+            .with_current_bytes(|current| status().value(current.payload))
+            .unwrap()
+            .unwrap();
+        let current = String::from_utf8(current).unwrap();
+
+        assert_eq!(keys(&[b"a"]), bytes(query(&store, &current)));
+        assert_eq!(1, store.indexes.entries.len());
+    }
+
+    #[test]
+    fn open_builds_new_indexes() {
+        let store = TempStore::new();
+
+        write(&store, &[(b"a", r#"{"status":"open"}"#)]);
+
+        let indexes = Indexes::open(&store.db, &store.format, vec![status()]).unwrap();
+        let mut query = indexes.query("status", br#""open""#).unwrap();
+
+        let found = query.next(&store.format).unwrap().map(|data| data.key);
+        assert_eq!(Some(key(b"a").to_bytes()), found.map(Key::to_bytes));
+
+        // Dropping the index clears its entries
+        let indexes = Indexes::open(&store.db, &store.format, Vec::new()).unwrap();
+        assert_eq!(0, indexes.entries.len());
+    }
+
+    #[test]
+    fn query_unknown_index() {
+        let store = TempStore::new();
+
+        assert!(store.index_query("status", br#""open""#).is_err());
+    }
+}
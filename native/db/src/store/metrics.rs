@@ -0,0 +1,426 @@
+/*!
+Counting the operations made on a store.
+
+Each store keeps its own counters, so a snapshot only covers the operations
+made through that store since it was opened.
+*/
+
+use std::{
+    fmt::Write,
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    time::Duration,
+};
+
+use crate::{
+    error::{
+        Error,
+        ErrorKind,
+        ERROR_KINDS,
+    },
+    store::durability::{
+        FlushMetrics,
+        BATCH_SIZE_BUCKETS,
+    },
+};
+
+/**
+The upper bounds of the buckets in a latency histogram, in microseconds.
+
+Latencies above the last bound are counted in an extra bucket.
+*/
+pub const LATENCY_BOUNDS_MICROS: [u64; LATENCY_BUCKETS - 1] = [
+    100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000,
+];
+
+/**
+The number of buckets in a latency histogram.
+*/
+pub const LATENCY_BUCKETS: usize = 10;
+
+/**
+A snapshot of a latency histogram.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    /**
+    The number of latencies that fell in each bucket.
+
+    Bucket `i` counts the latencies up to `LATENCY_BOUNDS_MICROS[i]`
+    that weren't counted in an earlier bucket. The last bucket counts the rest.
+    */
+    pub buckets: [u64; LATENCY_BUCKETS],
+    /**
+    The number of latencies recorded.
+    */
+    pub count: u64,
+    /**
+    The sum of all latencies recorded, in microseconds.
+    */
+    pub sum_micros: u64,
+}
+
+/**
+A snapshot of the operations made on a store.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreMetrics {
+    /**
+    The number of records returned by readers.
+    */
+    pub reads: u64,
+    /**
+    The number of records set by writers.
+    */
+    pub writes: u64,
+    /**
+    The number of records removed by deleters.
+    */
+    pub deletes: u64,
+    /**
+    The number of payload bytes returned by readers, before they were encoded.
+    */
+    pub bytes_read: u64,
+    /**
+    The number of payload bytes set by writers, before they were encoded.
+    */
+    pub bytes_written: u64,
+    /**
+    The number of errors returned by readers, writers, and deleters,
+    indexed by `ErrorKind::index`.
+    */
+    pub errors: [u64; ERROR_KINDS],
+    /**
+    The flushes made by writers and deleters.
+    */
+    pub flush: FlushMetrics,
+}
+
+impl StoreMetrics {
+    /**
+    Get the number of errors of the given kind.
+    */
+    pub fn errors_of(&self, kind: ErrorKind) -> u64 {
+        self.errors[kind.index()]
+    }
+
+    /**
+    Render the snapshot in the Prometheus text exposition format.
+    */
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        counter(
+            &mut out,
+            "db_reads_total",
+            "Records returned by readers.",
+            self.reads,
+        );
+        counter(
+            &mut out,
+            "db_writes_total",
+            "Records set by writers.",
+            self.writes,
+        );
+        counter(
+            &mut out,
+            "db_deletes_total",
+            "Records removed by deleters.",
+            self.deletes,
+        );
+        counter(
+            &mut out,
+            "db_read_bytes_total",
+            "Payload bytes returned by readers.",
+            self.bytes_read,
+        );
+        counter(
+            &mut out,
+            "db_written_bytes_total",
+            "Payload bytes set by writers.",
+            self.bytes_written,
+        );
+
+        header(
+            &mut out,
+            "db_errors_total",
+            "Errors returned by store operations.",
+            "counter",
+        );
+        for kind in ErrorKind::ALL.iter() {
+            let _ = writeln!(
+                out,
+                "db_errors_total{{kind=\"{}\"}} {}",
+                kind.name(),
+                self.errors_of(*kind)
+            );
+        }
+
+        counter(
+            &mut out,
+            "db_flushes_total",
+            "Flushes made to disk.",
+            self.flush.flushes,
+        );
+        counter(
+            &mut out,
+            "db_flush_requests_total",
+            "Completions that asked for a flush.",
+            self.flush.requests,
+        );
+
+        header(
+            &mut out,
+            "db_flush_duration_seconds",
+            "How long flushes took.",
+            "histogram",
+        );
+        let latency = &self.flush.latency;
+        let bounds = LATENCY_BOUNDS_MICROS
+            .iter()
+            .map(|micros| Some(*micros as f64 / 1_000_000.0));
+        buckets(
+            &mut out,
+            "db_flush_duration_seconds",
+            bounds,
+            &latency.buckets,
+        );
+        let _ = writeln!(
+            out,
+            "db_flush_duration_seconds_sum {}",
+            latency.sum_micros as f64 / 1_000_000.0
+        );
+        let _ = writeln!(out, "db_flush_duration_seconds_count {}", latency.count);
+
+        header(
+            &mut out,
+            "db_flush_batch_size",
+            "Completions that shared a flush.",
+            "histogram",
+        );
+        let bounds = (0..BATCH_SIZE_BUCKETS - 1).map(|i| Some(((2u64 << i) - 1) as f64));
+        buckets(
+            &mut out,
+            "db_flush_batch_size",
+            bounds,
+            &self.flush.batch_sizes,
+        );
+        let _ = writeln!(out, "db_flush_batch_size_sum {}", self.flush.requests);
+        let _ = writeln!(out, "db_flush_batch_size_count {}", self.flush.flushes);
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, ty: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, ty);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/**
+Write cumulative histogram buckets.
+
+The last count is always written with an upper bound of `+Inf`.
+*/
+fn buckets(
+    out: &mut String,
+    name: &str,
+    bounds: impl Iterator<Item = Option<f64>>,
+    counts: &[u64],
+) {
+    let mut total = 0;
+
+    for (bound, count) in bounds.chain(Some(None)).zip(counts) {
+        total += count;
+
+        match bound {
+            Some(bound) => {
+                let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, total);
+            }
+            None => {
+                let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, total);
+            }
+        }
+    }
+}
+
+/**
+A latency histogram that can be updated concurrently.
+*/
+#[derive(Default)]
+pub(super) struct AtomicHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl AtomicHistogram {
+    pub(super) fn record(&self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        let bucket = LATENCY_BOUNDS_MICROS
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(LATENCY_BUCKETS - 1);
+
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub(super) fn snapshot(&self) -> Histogram {
+        let mut buckets = [0; LATENCY_BUCKETS];
+        for (snapshot, bucket) in buckets.iter_mut().zip(self.buckets.iter()) {
+            *snapshot = bucket.load(Ordering::Relaxed);
+        }
+
+        Histogram {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum_micros: self.sum_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/**
+The counters for a store, shared with its readers, writers, and deleters.
+*/
+#[derive(Default)]
+pub(super) struct Recorder {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    deletes: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    errors: [AtomicU64; ERROR_KINDS],
+}
+
+impl Recorder {
+    pub(super) fn read(&self, bytes: usize) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn write(&self, bytes: usize) {
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn delete(&self) {
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /**
+    Count the error in a result, if there is one.
+    */
+    pub(super) fn track<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        if let Err(ref e) = result {
+            self.errors[e.kind().index()].fetch_add(1, Ordering::Relaxed);
+        }
+
+        result
+    }
+
+    pub(super) fn snapshot(&self, flush: FlushMetrics) -> StoreMetrics {
+        let mut errors = [0; ERROR_KINDS];
+        for (snapshot, count) in errors.iter_mut().zip(self.errors.iter()) {
+            *snapshot = count.load(Ordering::Relaxed);
+        }
+
+        StoreMetrics {
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            errors,
+            flush,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    use crate::store::test_util::{
+        key,
+        write,
+        TempStore,
+    };
+
+    #[test]
+    fn latency_buckets() {
+        let histogram = AtomicHistogram::default();
+
+        histogram.record(Duration::from_micros(50));
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_micros(700));
+        histogram.record(Duration::from_secs(2));
+
+        let snapshot = histogram.snapshot();
+
+        assert_eq!([2, 0, 1, 0, 0, 0, 0, 0, 0, 1], snapshot.buckets);
+        assert_eq!(4, snapshot.count);
+        assert_eq!(2_000_850, snapshot.sum_micros);
+    }
+
+    #[test]
+    fn store_operations_are_counted() {
+        let store = TempStore::new();
+
+        write(&store, &[(b"a", "{}"), (b"b", "{}")]);
+
+        for data in store.iter() {
+            data.unwrap().payload.read_to_end(&mut Vec::new()).unwrap();
+        }
+
+        let mut deleter = store.delete_begin().unwrap();
+        deleter.remove(key(b"a")).unwrap();
+        deleter.remove(key(b"z")).unwrap();
+        assert!(deleter.remove_prefix(&[0; 17]).is_err());
+        deleter.complete().unwrap();
+
+        let metrics = store.metrics();
+
+        assert_eq!(2, metrics.writes);
+        assert_eq!(4, metrics.bytes_written);
+        assert_eq!(2, metrics.reads);
+        assert_eq!(4, metrics.bytes_read);
+        assert_eq!(1, metrics.deletes);
+        assert_eq!(1, metrics.errors_of(ErrorKind::KeyTooLong));
+        assert_eq!(2, metrics.flush.latency.count);
+    }
+
+    #[test]
+    fn prometheus_format() {
+        let mut metrics = StoreMetrics {
+            reads: 3,
+            ..Default::default()
+        };
+        metrics.errors[ErrorKind::Io.index()] = 1;
+        metrics.flush.latency.buckets[0] = 1;
+        metrics.flush.latency.buckets[LATENCY_BUCKETS - 1] = 1;
+        metrics.flush.latency.count = 2;
+
+        let text = metrics.to_prometheus();
+
+        assert!(text.contains("# TYPE db_reads_total counter\ndb_reads_total 3\n"));
+        assert!(text.contains("db_errors_total{kind=\"io\"} 1\n"));
+        assert!(text.contains("db_flush_duration_seconds_bucket{le=\"0.0001\"} 1\n"));
+        assert!(text.contains("db_flush_duration_seconds_bucket{le=\"1\"} 1\n"));
+        assert!(text.contains("db_flush_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("db_flush_batch_size_bucket{le=\"1\"} 0\n"));
+        assert!(text.contains("db_flush_batch_size_bucket{le=\"127\"} 0\n"));
+        assert!(text.contains("db_flush_duration_seconds_count 2\n"));
+    }
+}
@@ -10,7 +10,18 @@ use std::{
 use crate::error::Error;
 
 pub mod deleter;
+pub mod durability;
+pub mod encryption;
+pub mod filter;
+pub mod index;
+pub mod metrics;
+#[cfg(feature = "async")]
+pub mod nonblocking;
 pub mod reader;
+pub mod record;
+pub mod typed;
+pub mod validate;
+pub mod verify;
 pub mod writer;
 
 /**
@@ -18,13 +29,85 @@ A database instance.
 */
 pub struct Store {
     db: Db,
+    format: Arc<record::Format>,
+    indexes: Arc<index::Indexes>,
+    validation: Option<Arc<validate::Validation>>,
+    flusher: Arc<durability::Flusher>,
+    metrics: Arc<metrics::Recorder>,
+}
+
+/**
+Options for opening a store.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /**
+    Whether to store a checksum alongside each payload that's written.
+
+    Checksums are verified whenever a record that has one is read.
+    Records written without a checksum can always be read.
+    */
+    pub checksums: bool,
+    /**
+    The compression to apply to payloads that are written.
+
+    Each record carries its own compression setting,
+    so changing it won't affect records that have already been written.
+    */
+    pub compression: record::Compression,
+    /**
+    The keys to encrypt payloads with.
+
+    Each record carries the id of the key it was encrypted with,
+    so a store can be opened with a new active key while previous
+    keys are retired.
+    */
+    pub encryption: Option<encryption::Encryption>,
+    /**
+    The secondary indexes to maintain over payloads.
+
+    Indexes are kept up-to-date as records are written and removed.
+    Index entries contain the values of indexed fields, so they aren't encrypted.
+    */
+    pub indexes: Vec<index::Index>,
+    /**
+    The checks to apply to payloads before they're written.
+
+    Payloads that fail validation are rejected without being written.
+    */
+    pub validation: Option<validate::Validation>,
+}
+
+/**
+Options for beginning a writer or deleter.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /**
+    When the changes that are made are flushed to disk.
+    */
+    pub durability: durability::Durability,
 }
 
 impl Store {
     pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
-        let db = sled::Db::start_default(path).map_err(Error::fail)?;
+        Store::open_with_options(path, Options::default())
+    }
 
-        Ok(Store { db: Db::new(db) })
+    pub fn open_with_options(path: impl AsRef<Path>, options: Options) -> Result<Self, Error> {
+        let db = Db::new(sled::Db::start_default(path).map_err(Error::fail)?);
+        let format = record::Format::new(&options);
+        let indexes = index::Indexes::open(&db, &format, options.indexes)?;
+        let flusher = durability::Flusher::new(db.clone());
+
+        Ok(Store {
+            db,
+            format: Arc::new(format),
+            indexes: Arc::new(indexes),
+            validation: options.validation.map(Arc::new),
+            flusher: Arc::new(flusher),
+            metrics: Arc::new(metrics::Recorder::default()),
+        })
     }
 
     pub fn close(&mut self) -> Result<(), Error> {
@@ -37,12 +120,93 @@ impl Store {
         Ok(reader::Reader::begin(self))
     }
 
+    /**
+    Iterate over the records in the store.
+
+    This is the same as `read_begin`, but beginning a read can't fail,
+    so the reader can be used directly in a `for` loop.
+    */
+    pub fn iter(&self) -> reader::Reader {
+        reader::Reader::begin(self)
+    }
+
+    /**
+    Read the records whose payloads match a filter.
+
+    Records that don't match are skipped without being returned.
+    */
+    pub fn read_begin_with_filter(&self, filter: filter::Filter) -> Result<reader::Reader, Error> {
+        Ok(reader::Reader::filtered(self, filter))
+    }
+
+    /**
+    Read the records where the field covered by an index is equal to a JSON value.
+    */
+    pub fn index_query(&self, index: &str, value: &[u8]) -> Result<reader::Reader, Error> {
+        let query = self.metrics.track(self.indexes.query(index, value))?;
+
+        Ok(reader::Reader::query(self, query))
+    }
+
     pub fn write_begin(&self) -> Result<writer::Writer, Error> {
-        Ok(writer::Writer::begin(self))
+        self.write_begin_with_options(WriteOptions::default())
+    }
+
+    pub fn write_begin_with_options(&self, options: WriteOptions) -> Result<writer::Writer, Error> {
+        Ok(writer::Writer::begin(self, options))
     }
 
     pub fn delete_begin(&self) -> Result<deleter::Deleter, Error> {
-        Ok(deleter::Deleter::begin(self))
+        self.delete_begin_with_options(WriteOptions::default())
+    }
+
+    pub fn delete_begin_with_options(
+        &self,
+        options: WriteOptions,
+    ) -> Result<deleter::Deleter, Error> {
+        Ok(deleter::Deleter::begin(self, options))
+    }
+
+    /**
+    Walk every record in the store and report any that can't be read.
+    */
+    pub fn verify(&self) -> Result<verify::Verification, Error> {
+        verify::verify(self)
+    }
+
+    /**
+    Copy every readable record into a fresh store at the given path.
+
+    The path must not already exist.
+    Records that can't be read are skipped and reported as problems.
+    */
+    pub fn repair(&self, path: impl AsRef<Path>) -> Result<verify::Verification, Error> {
+        verify::repair(self, path.as_ref())
+    }
+
+    /**
+    Rewrite every record that isn't encrypted with the active key.
+
+    Once a store has been re-encrypted its retired keys are no longer needed.
+    Records that can't be read are skipped and counted,
+    so a damaged record doesn't stop the rest of the store from being rotated.
+    */
+    pub fn reencrypt(&self) -> Result<encryption::Reencryption, Error> {
+        encryption::reencrypt(self)
+    }
+
+    /**
+    Get a snapshot of the flushes made by writers and deleters.
+    */
+    pub fn flush_metrics(&self) -> durability::FlushMetrics {
+        self.flusher.metrics()
+    }
+
+    /**
+    Get a snapshot of the operations made on the store.
+    */
+    pub fn metrics(&self) -> metrics::StoreMetrics {
+        self.metrics.snapshot(self.flusher.metrics())
     }
 }
 
@@ -57,3 +221,132 @@ See: https://github.com/spacejam/sled/issues/662
 */
 impl UnwindSafe for Store {}
 impl RefUnwindSafe for Store {}
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use std::{
+        env,
+        fs,
+        ops::Deref,
+        path::{
+            Path,
+            PathBuf,
+        },
+        process,
+        sync::atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    };
+
+    use super::{
+        Options,
+        Store,
+    };
+    use crate::data::{
+        Data,
+        Key,
+    };
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    fn temp_path() -> PathBuf {
+        env::temp_dir().join(format!(
+            "db-test-{}-{}",
+            process::id(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        ))
+    }
+
+    /**
+    A temporary directory that's removed when dropped.
+    */
+    pub(crate) struct TempDir {
+        path: PathBuf,
+    }
+
+    impl TempDir {
+        pub(crate) fn new() -> Self {
+            TempDir { path: temp_path() }
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    /**
+    A store in a temporary directory that's removed when dropped.
+    */
+    pub(crate) struct TempStore {
+        store: Option<Store>,
+        dir: TempDir,
+    }
+
+    impl TempStore {
+        pub(crate) fn new() -> Self {
+            TempStore::with_options(Options::default())
+        }
+
+        pub(crate) fn with_options(options: Options) -> Self {
+            TempStore::open(temp_path(), options)
+        }
+
+        pub(crate) fn open(path: PathBuf, options: Options) -> Self {
+            let store = Store::open_with_options(&path, options).expect("failed to open store");
+
+            TempStore {
+                store: Some(store),
+                dir: TempDir { path },
+            }
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            self.dir.path()
+        }
+    }
+
+    impl Deref for TempStore {
+        type Target = Store;
+
+        fn deref(&self) -> &Store {
+            self.store.as_ref().expect("missing store")
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            // The store needs to be closed before its directory is removed
+            drop(self.store.take());
+        }
+    }
+
+    /**
+    Get a key, panicking if it's too long.
+    */
+    pub(crate) fn key(key: &[u8]) -> Key {
+        Key::from_slice(key).expect("invalid key")
+    }
+
+    /**
+    Write records with the given keys and payloads to a store in a single write.
+    */
+    pub(crate) fn write(store: &Store, records: &[(&[u8], &str)]) {
+        let mut writer = store.write_begin().expect("failed to begin write");
+        for (k, payload) in records {
+            writer
+                .set(Data {
+                    key: key(k),
+                    payload: payload.as_bytes().to_vec(),
+                })
+                .expect("failed to set record");
+        }
+        writer.complete().expect("failed to complete write");
+    }
+}
@@ -0,0 +1,430 @@
+/*!
+An async facade over a store.
+
+Sled does blocking IO, so every call into the store is run on a dedicated pool of threads
+instead of the caller's executor. The facade doesn't depend on any particular async runtime.
+
+This module is only available with the `async` feature.
+*/
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io::Read,
+    panic::{
+        self,
+        AssertUnwindSafe,
+    },
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{
+        Context,
+        Poll,
+    },
+    thread,
+};
+
+use futures_channel::oneshot;
+use futures_core::Stream;
+
+use crate::{
+    data::{
+        Data,
+        Key,
+    },
+    error::Error,
+    store::{
+        deleter::Deleter,
+        reader::Reader,
+        writer::Writer,
+        Options,
+        Store,
+        WriteOptions,
+    },
+};
+
+/**
+The number of threads used to run blocking work when none is given.
+*/
+pub const DEFAULT_THREADS: usize = 4;
+
+/**
+The number of records read from the store at a time by a `ReadStream`.
+*/
+const READ_BATCH_SIZE: usize = 64;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/**
+A pool of threads that run blocking work.
+
+The threads exit once the pool and all stores using it are dropped.
+*/
+struct Pool {
+    jobs: crossbeam_channel::Sender<Job>,
+}
+
+impl Pool {
+    fn new(threads: usize) -> Result<Self, Error> {
+        let (jobs, queue) = crossbeam_channel::unbounded::<Job>();
+
+        for i in 0..threads.max(1) {
+            let queue = queue.clone();
+
+            thread::Builder::new()
+                .name(format!("db-async-{}", i))
+                .spawn(move || {
+                    for job in queue {
+                        // Panics are reported to the caller through their dropped channel
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                })
+                .map_err(Error::fail)?;
+        }
+
+        Ok(Pool { jobs })
+    }
+
+    fn run<R>(
+        &self,
+        f: impl FnOnce() -> Result<R, Error> + Send + 'static,
+    ) -> impl Future<Output = Result<R, Error>> + Send + 'static
+    where
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        let sent = self.jobs.send(Box::new(move || {
+            let _ = tx.send(f());
+        }));
+
+        async move {
+            sent.map_err(|_| Error::msg("the db worker pool has shut down"))?;
+
+            rx.await.map_err(|_| Error::msg("the db worker panicked"))?
+        }
+    }
+}
+
+/**
+A store that can be used from async code.
+*/
+#[derive(Clone)]
+pub struct AsyncStore {
+    store: Arc<Store>,
+    pool: Arc<Pool>,
+}
+
+impl AsyncStore {
+    /**
+    Use a store from async code, running blocking work on a pool of `DEFAULT_THREADS`.
+    */
+    pub fn new(store: Store) -> Result<Self, Error> {
+        AsyncStore::with_threads(store, DEFAULT_THREADS)
+    }
+
+    /**
+    Use a store from async code, running blocking work on a pool of the given size.
+    */
+    pub fn with_threads(store: Store, threads: usize) -> Result<Self, Error> {
+        Ok(AsyncStore {
+            store: Arc::new(store),
+            pool: Arc::new(Pool::new(threads)?),
+        })
+    }
+
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        AsyncStore::open_with_options(path, Options::default()).await
+    }
+
+    pub async fn open_with_options(
+        path: impl Into<PathBuf>,
+        options: Options,
+    ) -> Result<Self, Error> {
+        let path = path.into();
+        let pool = Pool::new(DEFAULT_THREADS)?;
+
+        let store = pool
+            .run(move || Store::open_with_options(path, options))
+            .await?;
+
+        Ok(AsyncStore {
+            store: Arc::new(store),
+            pool: Arc::new(pool),
+        })
+    }
+
+    /**
+    Get the underlying blocking store.
+    */
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    /**
+    Flush any pending writes to disk.
+    */
+    pub async fn close(self) -> Result<(), Error> {
+        let store = self.store;
+
+        self.pool
+            .run(move || store.db.flush().map(drop).map_err(Error::fail))
+            .await
+    }
+
+    /**
+    Read every record in the store as a stream.
+
+    Records are read in batches, so writes made while the stream is being read
+    may or may not be observed by it.
+    */
+    pub fn read_begin(&self) -> ReadStream {
+        ReadStream {
+            store: self.clone(),
+            buffered: VecDeque::new(),
+            after: None,
+            done: false,
+            pending: None,
+        }
+    }
+
+    pub fn write_begin(&self) -> AsyncWriter {
+        AsyncWriter {
+            writer: Some(Writer::begin(&self.store, WriteOptions::default())),
+            pool: self.pool.clone(),
+        }
+    }
+
+    pub fn delete_begin(&self) -> AsyncDeleter {
+        AsyncDeleter {
+            deleter: Some(Deleter::begin(&self.store, WriteOptions::default())),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+type Batch = Vec<Data<Vec<u8>>>;
+type PendingBatch = Pin<Box<dyn Future<Output = Result<Batch, Error>> + Send>>;
+
+/**
+A stream of the records in a store.
+*/
+pub struct ReadStream {
+    store: AsyncStore,
+    buffered: VecDeque<Data<Vec<u8>>>,
+    after: Option<Key>,
+    done: bool,
+    pending: Option<PendingBatch>,
+}
+
+impl ReadStream {
+    fn read_batch(&self) -> PendingBatch {
+        let store = self.store.store.clone();
+        let after = self.after;
+
+        Box::pin(self.store.pool.run(move || {
+            let reader = match after {
+                Some(key) => Reader::begin_after(&store, key),
+                None => Reader::begin(&store),
+            };
+
+            let mut batch = Vec::with_capacity(READ_BATCH_SIZE);
+            for data in reader.take(READ_BATCH_SIZE) {
+                let mut data = data?;
+
+                let mut payload = Vec::new();
+                data.payload
+                    .read_to_end(&mut payload)
+                    .map_err(Error::fail)?;
+
+                batch.push(Data {
+                    key: data.key,
+                    payload,
+                });
+            }
+
+            Ok(batch)
+        }))
+    }
+}
+
+impl Stream for ReadStream {
+    type Item = Result<Data<Vec<u8>>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(data) = self.buffered.pop_front() {
+                return Poll::Ready(Some(Ok(data)));
+            }
+
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            if self.pending.is_none() {
+                self.pending = Some(self.read_batch());
+            }
+
+            let batch = match self
+                .pending
+                .as_mut()
+                .map(|pending| pending.as_mut().poll(cx))
+            {
+                Some(Poll::Ready(batch)) => batch,
+                _ => return Poll::Pending,
+            };
+
+            self.pending = None;
+
+            match batch {
+                Ok(batch) => {
+                    self.done = batch.len() < READ_BATCH_SIZE;
+                    self.after = batch.last().map(|data| data.key);
+                    self.buffered.extend(batch);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+/**
+A writer that can be used from async code.
+*/
+pub struct AsyncWriter {
+    writer: Option<Writer>,
+    pool: Arc<Pool>,
+}
+
+impl AsyncWriter {
+    pub async fn set(&mut self, data: Data<Vec<u8>>) -> Result<(), Error> {
+        let mut writer = self.take()?;
+
+        let (writer, result) = self
+            .pool
+            .run(move || {
+                let result = writer.set(data);
+                Ok((writer, result))
+            })
+            .await?;
+
+        self.writer = Some(writer);
+        result
+    }
+
+    /**
+    Flush the written records to disk.
+    */
+    pub async fn complete(mut self) -> Result<(), Error> {
+        let mut writer = self.take()?;
+
+        self.pool.run(move || writer.complete()).await
+    }
+
+    fn take(&mut self) -> Result<Writer, Error> {
+        // The writer is only missing if a previous call was cancelled
+        self.writer
+            .take()
+            .ok_or_else(|| Error::msg("the writer was lost by a cancelled call"))
+    }
+}
+
+/**
+A deleter that can be used from async code.
+*/
+pub struct AsyncDeleter {
+    deleter: Option<Deleter>,
+    pool: Arc<Pool>,
+}
+
+impl AsyncDeleter {
+    /**
+    Remove the record with the given key.
+
+    Returns whether there was a record to remove.
+    */
+    pub async fn remove(&mut self, key: Key) -> Result<bool, Error> {
+        let mut deleter = self.take()?;
+
+        let (deleter, result) = self
+            .pool
+            .run(move || {
+                let result = deleter.remove(key);
+                Ok((deleter, result))
+            })
+            .await?;
+
+        self.deleter = Some(deleter);
+        result
+    }
+
+    /**
+    Flush the removed records to disk.
+    */
+    pub async fn complete(mut self) -> Result<(), Error> {
+        let mut deleter = self.take()?;
+
+        self.pool.run(move || deleter.complete()).await
+    }
+
+    fn take(&mut self) -> Result<Deleter, Error> {
+        // The deleter is only missing if a previous call was cancelled
+        self.deleter
+            .take()
+            .ok_or_else(|| Error::msg("the deleter was lost by a cancelled call"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_executor::{
+        block_on,
+        block_on_stream,
+    };
+
+    use crate::store::test_util::TempDir;
+
+    #[test]
+    fn write_read_delete() {
+        let dir = TempDir::new();
+        let store = block_on(AsyncStore::open(dir.path())).unwrap();
+
+        // Write enough records to need more than one batch
+        let count = READ_BATCH_SIZE * 2 + 1;
+
+        block_on(async {
+            let mut writer = store.write_begin();
+            for i in 0..count {
+                writer
+                    .set(Data {
+                        key: Key::from_slice(&(i as u32).to_be_bytes()).unwrap(),
+                        payload: b"{}".to_vec(),
+                    })
+                    .await?;
+            }
+            writer.complete().await?;
+
+            let mut deleter = store.delete_begin();
+            deleter
+                .remove(Key::from_slice(&0u32.to_be_bytes()).unwrap())
+                .await?;
+            deleter.complete().await
+        })
+        .unwrap();
+
+        let read: Vec<_> = block_on_stream(store.read_begin())
+            .map(|data| data.unwrap())
+            .collect();
+
+        assert_eq!(count - 1, read.len());
+        assert_eq!(b"{}".to_vec(), read[0].payload);
+
+        block_on(store.close()).unwrap();
+    }
+}
@@ -8,8 +8,12 @@ use std::{
         RefUnwindSafe,
         UnwindSafe,
     },
+    sync::Arc,
 };
 
+#[cfg(feature = "async")]
+use std::ops::Bound;
+
 use crate::{
     data::{
         Data,
@@ -17,6 +21,13 @@ use crate::{
     },
     error::Error,
     store::{
+        filter::Filter,
+        index::Query,
+        metrics::Recorder,
+        record::{
+            Format,
+            RawPayload,
+        },
         Db,
         Store,
     },
@@ -24,16 +35,55 @@ use crate::{
 
 pub struct Reader {
     iter: Iter,
+    filter: Option<Filter>,
     current: Option<Data<RawPayload>>,
+    metrics: Arc<Recorder>,
 }
 
 impl Reader {
     pub(super) fn begin(store: &Store) -> Self {
         let db = store.db.clone();
+        let format = store.format.clone();
+
+        Reader {
+            iter: Iter::new(db, format),
+            filter: None,
+            current: None,
+            metrics: store.metrics.clone(),
+        }
+    }
+
+    /**
+    Begin reading from the first record after the given key.
+    */
+    #[cfg(feature = "async")]
+    pub(super) fn begin_after(store: &Store, key: Key) -> Self {
+        let db = store.db.clone();
+        let format = store.format.clone();
 
         Reader {
-            iter: Iter::new(db),
+            iter: Iter::after(db, format, key),
+            filter: None,
             current: None,
+            metrics: store.metrics.clone(),
+        }
+    }
+
+    pub(super) fn filtered(store: &Store, filter: Filter) -> Self {
+        Reader {
+            filter: Some(filter),
+            ..Reader::begin(store)
+        }
+    }
+
+    pub(super) fn query(store: &Store, query: Query) -> Self {
+        let format = store.format.clone();
+
+        Reader {
+            iter: Iter::query(query, format),
+            filter: None,
+            current: None,
+            metrics: store.metrics.clone(),
         }
     }
 
@@ -50,19 +100,79 @@ impl Reader {
         }
     }
 
+    /**
+    Lend the payload of the current record without cloning it.
+
+    The payload can't outlive the call, so this is cheaper than `with_current`
+    when the payload is copied somewhere else anyway.
+    */
+    pub fn with_current_bytes<R>(&self, f: impl FnOnce(Data<&[u8]>) -> R) -> Option<R> {
+        self.current.as_ref().map(|current| {
+            f(Data {
+                key: current.key,
+                payload: current.payload.as_ref(),
+            })
+        })
+    }
+
     pub fn move_next(&mut self) -> Result<bool, Error> {
-        if let Some(next) = self.iter.next()? {
-            self.current = Some(next);
-            Ok(true)
-        } else {
-            self.current = None;
-            Ok(false)
-        }
+        self.current = self.next_record()?;
+
+        Ok(self.current.is_some())
     }
 
     pub fn complete(&mut self) -> Result<(), Error> {
         Ok(())
     }
+
+    fn next_record(&mut self) -> Result<Option<Data<RawPayload>>, Error> {
+        let next = self.next_matching();
+        let next = self.metrics.track(next)?;
+
+        if let Some(ref next) = next {
+            self.metrics.read(next.payload.as_ref().len());
+        }
+
+        Ok(next)
+    }
+
+    fn next_matching(&mut self) -> Result<Option<Data<RawPayload>>, Error> {
+        while let Some(next) = self.iter.next()? {
+            // Skip over records that don't match the filter
+            if let Some(ref filter) = self.filter {
+                if !filter.matches(next.payload.as_ref()) {
+                    continue;
+                }
+            }
+
+            return Ok(Some(next));
+        }
+
+        Ok(None)
+    }
+}
+
+/**
+Read records by value.
+
+Records are moved out of the reader instead of cloned, so the cursor
+isn't positioned on them and `with_current` won't return them.
+*/
+impl Iterator for Reader {
+    type Item = Result<Data<Payload>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.current = None;
+
+        self.next_record()
+            .map(|next| {
+                next.map(|next| Data {
+                    key: next.key,
+                    payload: Payload::new(next.payload),
+                })
+            })
+            .transpose()
+    }
 }
 
 rental! {
@@ -77,24 +187,61 @@ rental! {
     }
 }
 
-struct Iter(iter::Iter);
+struct Iter {
+    inner: Inner,
+    format: Arc<Format>,
+}
+
+enum Inner {
+    Scan(iter::Iter),
+    Query(Query),
+}
 
 impl Iter {
-    fn new(db: Db) -> Self {
-        Iter(iter::Iter::new(db, |db| db.iter()))
+    fn new(db: Db, format: Arc<Format>) -> Self {
+        Iter {
+            inner: Inner::Scan(iter::Iter::new(db, |db| db.iter())),
+            format,
+        }
+    }
+
+    #[cfg(feature = "async")]
+    fn after(db: Db, format: Arc<Format>, key: Key) -> Self {
+        Iter {
+            inner: Inner::Scan(iter::Iter::new(db, |db| {
+                db.range::<Key, _>((Bound::Excluded(key), Bound::Unbounded))
+            })),
+            format,
+        }
+    }
+
+    fn query(query: Query, format: Arc<Format>) -> Self {
+        Iter {
+            inner: Inner::Query(query),
+            format,
+        }
     }
 
     fn next(&mut self) -> Result<Option<Data<RawPayload>>, Error> {
-        let kv = self
-            .0
+        let scan = match self.inner {
+            Inner::Scan(ref mut scan) => scan,
+            Inner::Query(ref mut query) => return query.next(&self.format),
+        };
+
+        let kv = scan
             .rent_mut(|iter| iter.next())
             .transpose()
             .map_err(Error::fail)?;
 
         if let Some((k, v)) = kv {
+            let key = Key::from_vec(k)?;
+
             let data = Data {
-                key: Key::from_vec(k)?,
-                payload: RawPayload(v),
+                key,
+                payload: self
+                    .format
+                    .decode(&key, v)
+                    .map_err(|e| Error::from(e).with_key(key))?,
             };
 
             Ok(Some(data))
@@ -116,15 +263,6 @@ impl RefUnwindSafe for Iter {}
 
 pub struct Payload(Cursor<RawPayload>);
 
-#[derive(Clone)]
-struct RawPayload(sled::IVec);
-
-impl AsRef<[u8]> for RawPayload {
-    fn as_ref(&self) -> &[u8] {
-        &*self.0
-    }
-}
-
 impl Payload {
     fn new(value: RawPayload) -> Self {
         Payload(Cursor::new(value))
@@ -136,3 +274,68 @@ impl Read for Payload {
         self.0.read(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::test_util::{
+        write,
+        TempStore,
+    };
+
+    #[test]
+    fn iterate_store() {
+        let store = TempStore::new();
+
+        write(&store, &[(b"a", "{}"), (b"b", "{}"), (b"c", "{}")]);
+
+        let payloads = store
+            .iter()
+            .map(|data| {
+                let mut payload = Vec::new();
+                data?
+                    .payload
+                    .read_to_end(&mut payload)
+                    .map_err(Error::fail)?;
+
+                Ok(payload)
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+
+        assert_eq!(vec![b"{}".to_vec(); 3], payloads);
+
+        // Iterating doesn't leave a current record for the cursor
+        let mut reader = store.read_begin().unwrap();
+        assert!(reader.by_ref().take(2).all(|data| data.is_ok()));
+        assert!(reader.with_current(|_| ()).is_none());
+
+        assert!(reader.move_next().unwrap());
+        assert!(reader.with_current(|_| ()).is_some());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn lend_current_payload() {
+        let store = TempStore::new();
+
+        write(&store, &[(b"a", "{\"a\":1}")]);
+
+        let mut reader = store.read_begin().unwrap();
+        assert!(reader.with_current_bytes(|_| ()).is_none());
+
+        assert!(reader.move_next().unwrap());
+        assert_eq!(
+            Some(b"{\"a\":1}".to_vec()),
+            reader.with_current_bytes(|current| current.payload.to_vec())
+        );
+
+        let mut cloned = Vec::new();
+        reader
+            .with_current(|mut current| current.payload.read_to_end(&mut cloned))
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"{\"a\":1}".to_vec(), cloned);
+    }
+}
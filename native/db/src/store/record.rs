@@ -0,0 +1,654 @@
+/*!
+The on-disk format of records.
+
+Payloads can be wrapped in an envelope that carries extra metadata about them.
+The envelope starts with a marker that can't appear at the start of a valid UTF-8
+payload, so records written before envelopes existed are still read as-is.
+
+An enveloped record looks like:
+
+```text
++---------+---------+-------+--------------+--------------+--------------+----------+---------+
+| marker  | version | flags | crc32c?      | compression? | key id?      | nonce?   | body    |
+| 2 bytes | 1 byte  | 1 byte| 4 bytes (LE) | 1 byte       | 4 bytes (LE) | 12 bytes | n bytes |
++---------+---------+-------+--------------+--------------+--------------+----------+---------+
+```
+
+Optional fields are only present if their flag is set.
+Payloads that start with the marker themselves are always enveloped, even if no flags
+are set, so they aren't mistaken for an envelope when they're read.
+The checksum is calculated over everything that follows it.
+
+Payloads are compressed before they're encrypted, and checksummed after.
+That way corruption can be detected without needing the encryption keys.
+Encrypted payloads authenticate the envelope fields that precede the nonce,
+other than the checksum, so they can't be changed without being detected.
+
+Each record carries its own flags, so records written with different
+options can live side-by-side in the same store.
+*/
+
+use std::{
+    convert::TryInto,
+    sync::Arc,
+};
+
+use failure_derive::*;
+
+use crate::{
+    data::Key,
+    error::Error,
+    store::{
+        encryption::{
+            Ciphers,
+            DecryptError,
+            NONCE_SIZE,
+        },
+        Options,
+    },
+};
+
+const MARKER: [u8; 2] = [0xff, 0xdb];
+const VERSION: u8 = 1;
+
+const FLAG_CHECKSUM: u8 = 0b0000_0001;
+const FLAG_COMPRESSED: u8 = 0b0000_0010;
+const FLAG_ENCRYPTED: u8 = 0b0000_0100;
+const FLAGS: u8 = FLAG_CHECKSUM | FLAG_COMPRESSED | FLAG_ENCRYPTED;
+
+const HEADER_LEN: usize = 4;
+const CHECKSUM_LEN: usize = 4;
+const KEY_ID_LEN: usize = 4;
+
+const COMPRESSION_LZ4: u8 = 1;
+
+/*
+Payloads smaller than this aren't worth compressing.
+The compressed representation and the envelope header
+would likely cost more than they save.
+*/
+const MIN_COMPRESSED_LEN: usize = 64;
+
+/*
+LZ4 can't expand its input by more than this ratio, so a compressed payload
+that claims to be larger than this multiple of its own size is corrupt.
+*/
+const MAX_COMPRESSION_RATIO: usize = 255;
+
+/*
+The largest payload that will be decompressed, however well it compressed.
+The decompressed size is read from the record, so it's capped before it's
+used to allocate a buffer.
+*/
+const MAX_DECOMPRESSED_LEN: usize = 256 * 1024 * 1024;
+
+const DECOMPRESSED_LEN_LEN: usize = 4;
+
+/**
+A record that can't be decoded because its stored bytes are damaged.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Fail)]
+pub enum Corruption {
+    #[fail(display = "the record envelope is truncated")]
+    Truncated,
+    #[fail(display = "the record envelope version `{}` is unsupported", _0)]
+    UnsupportedVersion(u8),
+    #[fail(display = "the record envelope flags `{:#010b}` are unsupported", _0)]
+    UnsupportedFlags(u8),
+    #[fail(
+        display = "the record checksum `{:#010x}` doesn't match the expected `{:#010x}`",
+        actual, expected
+    )]
+    ChecksumMismatch { expected: u32, actual: u32 },
+    #[fail(display = "the record compression `{}` is unsupported", _0)]
+    UnsupportedCompression(u8),
+    #[fail(display = "the record payload couldn't be decompressed: {}", _0)]
+    Decompress(String),
+}
+
+/**
+The compression applied to payloads when they're written.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Lz4,
+}
+
+/**
+A record that couldn't be decoded.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum DecodeError {
+    Corrupted(Corruption),
+    Decrypt(DecryptError),
+}
+
+impl From<Corruption> for DecodeError {
+    fn from(err: Corruption) -> Self {
+        DecodeError::Corrupted(err)
+    }
+}
+
+impl From<DecryptError> for DecodeError {
+    fn from(err: DecryptError) -> Self {
+        DecodeError::Decrypt(err)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::Corrupted(err) => Error::fail(err),
+            DecodeError::Decrypt(err) => Error::fail(err),
+        }
+    }
+}
+
+/**
+How records are encoded when they're written.
+*/
+pub(super) struct Format {
+    checksums: bool,
+    compression: Compression,
+    ciphers: Option<Ciphers>,
+}
+
+impl Format {
+    pub(super) fn new(options: &Options) -> Self {
+        Format {
+            checksums: options.checksums,
+            compression: options.compression,
+            ciphers: options.encryption.as_ref().map(Ciphers::new),
+        }
+    }
+
+    pub(super) fn is_encrypted(&self) -> bool {
+        self.ciphers.is_some()
+    }
+
+    /**
+    Whether a stored record is encrypted with the active key.
+
+    If the store isn't encrypted then every record is current.
+    */
+    pub(super) fn is_current(&self, record: &[u8]) -> Result<bool, Corruption> {
+        match self.ciphers {
+            Some(ref ciphers) => {
+                let header = Header::parse(record)?;
+
+                Ok(header.encryption.map(|(key_id, _)| key_id) == Some(ciphers.active()))
+            }
+            None => Ok(true),
+        }
+    }
+
+    pub(super) fn encode(&self, key: &Key, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let mut flags = 0;
+
+        let mut body = match self.compression {
+            Compression::Lz4 if payload.len() >= MIN_COMPRESSED_LEN => {
+                let compressed = lz4_flex::compress_prepend_size(&payload);
+
+                // Only keep the compressed payload if it's actually smaller
+                if compressed.len() < payload.len() {
+                    flags |= FLAG_COMPRESSED;
+                    compressed
+                } else {
+                    payload
+                }
+            }
+            _ => payload,
+        };
+
+        if self.checksums {
+            flags |= FLAG_CHECKSUM;
+        }
+
+        if self.ciphers.is_some() {
+            flags |= FLAG_ENCRYPTED;
+        }
+
+        if flags == 0 && !body.starts_with(&MARKER) {
+            return Ok(body);
+        }
+
+        let mut record = Vec::with_capacity(
+            HEADER_LEN + CHECKSUM_LEN + 1 + KEY_ID_LEN + NONCE_SIZE + body.len(),
+        );
+
+        record.extend_from_slice(&MARKER);
+        record.push(VERSION);
+        record.push(flags);
+
+        let checksum_at = record.len();
+        if flags & FLAG_CHECKSUM != 0 {
+            record.extend_from_slice(&[0; CHECKSUM_LEN]);
+        }
+
+        if flags & FLAG_COMPRESSED != 0 {
+            record.push(COMPRESSION_LZ4);
+        }
+
+        if let Some(ref ciphers) = self.ciphers {
+            record.extend_from_slice(&ciphers.active().to_le_bytes());
+
+            let header = authenticated_header(&record, flags);
+            let (nonce, encrypted) = ciphers.encrypt(&header, key, &body)?;
+
+            record.extend_from_slice(&nonce);
+            body = encrypted;
+        }
+
+        record.extend_from_slice(&body);
+
+        if flags & FLAG_CHECKSUM != 0 {
+            let checksum = crc32c::crc32c(&record[checksum_at + CHECKSUM_LEN..]);
+            record[checksum_at..checksum_at + CHECKSUM_LEN]
+                .copy_from_slice(&checksum.to_le_bytes());
+        }
+
+        Ok(record)
+    }
+
+    /**
+    Decode a stored record into its original payload.
+    */
+    pub(super) fn decode(&self, key: &Key, record: sled::IVec) -> Result<RawPayload, DecodeError> {
+        let header = Header::parse(&record)?;
+
+        let authenticated = match header.encryption {
+            Some(_) => {
+                // The nonce immediately precedes the body
+                authenticated_header(&record[..header.body - NONCE_SIZE], header.flags)
+            }
+            None => Vec::new(),
+        };
+
+        let mut payload = RawPayload::Stored {
+            offset: header.body,
+            record,
+        };
+
+        if let Some((key_id, nonce)) = header.encryption {
+            let ciphers = self
+                .ciphers
+                .as_ref()
+                .ok_or(DecryptError::UnknownKey(key_id))?;
+
+            let decrypted =
+                ciphers.decrypt(key_id, &nonce, &authenticated, key, payload.as_ref())?;
+            payload = RawPayload::Decoded(decrypted.into());
+        }
+
+        if let Some(compression) = header.compression {
+            if compression != COMPRESSION_LZ4 {
+                return Err(Corruption::UnsupportedCompression(compression).into());
+            }
+
+            let decompressed = decompress(payload.as_ref())?;
+            payload = RawPayload::Decoded(decompressed.into());
+        }
+
+        Ok(payload)
+    }
+}
+
+/**
+The parts of an envelope that are authenticated when its payload is encrypted.
+
+This is everything up to the nonce, except for the checksum, which is
+calculated over the encrypted payload.
+*/
+fn authenticated_header(header: &[u8], flags: u8) -> Vec<u8> {
+    let checksum_len = if flags & FLAG_CHECKSUM != 0 {
+        CHECKSUM_LEN
+    } else {
+        0
+    };
+
+    let mut authenticated = header[..HEADER_LEN].to_vec();
+    authenticated.extend_from_slice(&header[HEADER_LEN + checksum_len..]);
+
+    authenticated
+}
+
+/**
+Decompress a payload that's prefixed with its decompressed size.
+*/
+fn decompress(compressed: &[u8]) -> Result<Vec<u8>, Corruption> {
+    if compressed.len() < DECOMPRESSED_LEN_LEN {
+        return Err(Corruption::Truncated);
+    }
+
+    let (len, body) = compressed.split_at(DECOMPRESSED_LEN_LEN);
+    let len = u32::from_le_bytes(len.try_into().expect("invalid decompressed len")) as usize;
+
+    if len > MAX_DECOMPRESSED_LEN || len > body.len().saturating_mul(MAX_COMPRESSION_RATIO) {
+        return Err(Corruption::Decompress(format!(
+            "the decompressed size `{}` is too large for `{}` compressed bytes",
+            len,
+            body.len()
+        )));
+    }
+
+    let mut decompressed = vec![0; len];
+    let actual = lz4_flex::decompress_into(body, &mut decompressed)
+        .map_err(|e| Corruption::Decompress(e.to_string()))?;
+
+    if actual != len {
+        return Err(Corruption::Decompress(format!(
+            "the decompressed size `{}` doesn't match the expected `{}`",
+            actual, len
+        )));
+    }
+
+    Ok(decompressed)
+}
+
+/**
+The payload of a decoded record.
+*/
+#[derive(Clone)]
+pub(super) enum RawPayload {
+    /**
+    The payload is stored as-is in the record, starting at an offset.
+    */
+    Stored { record: sled::IVec, offset: usize },
+    /**
+    The payload had to be decoded into a new buffer.
+    */
+    Decoded(Arc<[u8]>),
+}
+
+impl AsRef<[u8]> for RawPayload {
+    fn as_ref(&self) -> &[u8] {
+        match *self {
+            RawPayload::Stored { ref record, offset } => &record[offset..],
+            RawPayload::Decoded(ref payload) => payload,
+        }
+    }
+}
+
+/**
+The parsed envelope of a stored record.
+
+Parsing a header also verifies its checksum.
+*/
+struct Header {
+    flags: u8,
+    compression: Option<u8>,
+    encryption: Option<(u32, [u8; NONCE_SIZE])>,
+    body: usize,
+}
+
+impl Header {
+    fn parse(record: &[u8]) -> Result<Self, Corruption> {
+        let mut header = Header {
+            flags: 0,
+            compression: None,
+            encryption: None,
+            body: 0,
+        };
+
+        if !record.starts_with(&MARKER) {
+            return Ok(header);
+        }
+
+        if record.len() < HEADER_LEN {
+            return Err(Corruption::Truncated);
+        }
+
+        let version = record[2];
+        if version != VERSION {
+            return Err(Corruption::UnsupportedVersion(version));
+        }
+
+        let flags = record[3];
+        if flags & !FLAGS != 0 {
+            return Err(Corruption::UnsupportedFlags(flags));
+        }
+
+        header.flags = flags;
+
+        let mut offset = HEADER_LEN;
+        let mut take = |len: usize| {
+            let field = record
+                .get(offset..offset + len)
+                .ok_or(Corruption::Truncated)?;
+            offset += len;
+
+            Ok(field)
+        };
+
+        if flags & FLAG_CHECKSUM != 0 {
+            let checksum = take(CHECKSUM_LEN)?;
+            let expected = u32::from_le_bytes(checksum.try_into().expect("invalid checksum len"));
+            let actual = crc32c::crc32c(&record[HEADER_LEN + CHECKSUM_LEN..]);
+
+            if actual != expected {
+                return Err(Corruption::ChecksumMismatch { expected, actual });
+            }
+        }
+
+        if flags & FLAG_COMPRESSED != 0 {
+            header.compression = Some(take(1)?[0]);
+        }
+
+        if flags & FLAG_ENCRYPTED != 0 {
+            let key_id = take(KEY_ID_LEN)?;
+            let key_id = u32::from_le_bytes(key_id.try_into().expect("invalid key id len"));
+            let nonce = take(NONCE_SIZE)?.try_into().expect("invalid nonce len");
+
+            header.encryption = Some((key_id, nonce));
+        }
+
+        header.body = offset;
+
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::encryption::{
+        Encryption,
+        EncryptionKey,
+    };
+
+    fn format(checksums: bool, compression: Compression) -> Format {
+        Format::new(&Options {
+            checksums,
+            compression,
+            ..Default::default()
+        })
+    }
+
+    fn encrypted(key: EncryptionKey) -> Format {
+        Format::new(&Options {
+            checksums: true,
+            encryption: Some(Encryption::new(key)),
+            ..Default::default()
+        })
+    }
+
+    fn key() -> Key {
+        Key::from_slice(b"a").unwrap()
+    }
+
+    fn json(len: usize) -> Vec<u8> {
+        format!("{{\"a\":\"{}\"}}", "b".repeat(len)).into_bytes()
+    }
+
+    fn roundtrip(format: &Format, payload: Vec<u8>) -> Vec<u8> {
+        let record = format.encode(&key(), payload).unwrap();
+
+        format
+            .decode(&key(), record.into())
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    fn raw_records_are_read_as_is() {
+        let record = format(false, Compression::None)
+            .encode(&key(), json(1))
+            .unwrap();
+
+        assert_eq!(json(1), record);
+        assert_eq!(
+            json(1),
+            roundtrip(&format(false, Compression::None), json(1))
+        );
+    }
+
+    #[test]
+    fn payloads_starting_with_the_marker_roundtrip() {
+        let payload = vec![0xff, 0xdb, VERSION, 0xff, b'a'];
+
+        for format in &[
+            format(false, Compression::None),
+            format(false, Compression::Lz4),
+        ] {
+            let record = format.encode(&key(), payload.clone()).unwrap();
+
+            assert_ne!(payload, record);
+            assert_eq!(payload, roundtrip(format, payload.clone()));
+        }
+    }
+
+    #[test]
+    fn checksummed_records_roundtrip() {
+        assert_eq!(
+            json(1),
+            roundtrip(&format(true, Compression::None), json(1))
+        );
+    }
+
+    #[test]
+    fn compressed_records_roundtrip() {
+        let format = format(true, Compression::Lz4);
+
+        assert!(format.encode(&key(), json(512)).unwrap().len() < json(512).len());
+        assert_eq!(json(512), roundtrip(&format, json(512)));
+    }
+
+    #[test]
+    fn small_records_are_not_compressed() {
+        let record = format(false, Compression::Lz4)
+            .encode(&key(), json(1))
+            .unwrap();
+
+        assert_eq!(json(1), record);
+    }
+
+    #[test]
+    fn compressed_records_with_a_corrupt_size_are_rejected() {
+        let format = format(false, Compression::Lz4);
+        let mut record = format.encode(&key(), json(512)).unwrap();
+
+        // The decompressed size follows the envelope header and compression byte
+        record[HEADER_LEN + 1..HEADER_LEN + 1 + DECOMPRESSED_LEN_LEN]
+            .copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let err = format.decode(&key(), record.into()).err();
+
+        assert_match!(Some(DecodeError::Corrupted(Corruption::Decompress(_))) = err);
+    }
+
+    #[test]
+    fn checksummed_records_detect_corruption() {
+        let format = format(true, Compression::Lz4);
+        let mut record = format.encode(&key(), json(512)).unwrap();
+
+        let last = record.len() - 1;
+        record[last] ^= 0xff;
+
+        let err = format.decode(&key(), record.into()).err();
+
+        assert_match!(Some(DecodeError::Corrupted(Corruption::ChecksumMismatch { .. })) = err);
+    }
+
+    #[test]
+    fn truncated_records_are_corrupt() {
+        let format = format(true, Compression::None);
+        let record = format.encode(&key(), json(1)).unwrap();
+
+        assert_eq!(
+            Some(DecodeError::Corrupted(Corruption::Truncated)),
+            format.decode(&key(), record[..6].into()).err()
+        );
+    }
+
+    #[test]
+    fn encrypted_records_roundtrip() {
+        let format = encrypted(EncryptionKey::new(1, [1; 32]));
+        let record = format.encode(&key(), json(1)).unwrap();
+
+        assert!(!record.windows(json(1).len()).any(|w| w == &json(1)[..]));
+        assert_eq!(json(1), roundtrip(&format, json(1)));
+    }
+
+    #[test]
+    fn encrypted_records_are_bound_to_their_key() {
+        let format = encrypted(EncryptionKey::new(1, [1; 32]));
+        let record = format.encode(&key(), json(1)).unwrap();
+
+        let other = Key::from_slice(b"b").unwrap();
+
+        assert_eq!(
+            Some(DecodeError::Decrypt(DecryptError::Unauthenticated(1))),
+            format.decode(&other, record.into()).err()
+        );
+    }
+
+    #[test]
+    fn encrypted_records_are_bound_to_their_header() {
+        let format = encrypted(EncryptionKey::new(1, [1; 32]));
+        let record = format.encode(&key(), json(1)).unwrap();
+
+        // Strip the checksum from the envelope, which leaves a valid header
+        let mut stripped = record[..HEADER_LEN].to_vec();
+        stripped[3] &= !FLAG_CHECKSUM;
+        stripped.extend_from_slice(&record[HEADER_LEN + CHECKSUM_LEN..]);
+
+        assert_eq!(
+            Some(DecodeError::Decrypt(DecryptError::Unauthenticated(1))),
+            format.decode(&key(), stripped.into()).err()
+        );
+    }
+
+    #[test]
+    fn encrypted_records_need_their_key() {
+        let record = encrypted(EncryptionKey::new(1, [1; 32]))
+            .encode(&key(), json(1))
+            .unwrap();
+
+        let rotated = encrypted(EncryptionKey::new(2, [2; 32]));
+        assert_eq!(
+            Some(DecodeError::Decrypt(DecryptError::UnknownKey(1))),
+            rotated.decode(&key(), record.clone().into()).err()
+        );
+
+        let rotated = Format::new(&Options {
+            encryption: Some(
+                Encryption::new(EncryptionKey::new(2, [2; 32]))
+                    .with_retired(EncryptionKey::new(1, [1; 32])),
+            ),
+            ..Default::default()
+        });
+        assert!(!rotated.is_current(&record).unwrap());
+        assert_eq!(
+            json(1),
+            rotated
+                .decode(&key(), record.into())
+                .unwrap()
+                .as_ref()
+                .to_vec()
+        );
+    }
+}
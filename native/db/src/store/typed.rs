@@ -0,0 +1,226 @@
+/*!
+Typed access to a store through a `Codec`.
+*/
+
+use std::marker::PhantomData;
+
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+
+use crate::{
+    codec::{
+        Codec,
+        Json,
+    },
+    data::{
+        Data,
+        Key,
+    },
+    error::Error,
+    store::{
+        reader::Reader,
+        writer::Writer,
+        Store,
+    },
+};
+
+/**
+A store whose payloads are values of type `T` encoded with a codec.
+*/
+pub struct TypedStore<T, C = Json> {
+    store: Store,
+    codec: C,
+    _marker: PhantomData<fn(T) -> T>,
+}
+
+impl<T> TypedStore<T> {
+    /**
+    Use a store with payloads encoded as JSON.
+    */
+    pub fn json(store: Store) -> Self {
+        TypedStore::new(store, Json)
+    }
+}
+
+impl<T, C> TypedStore<T, C>
+where
+    C: Codec + Clone,
+{
+    pub fn new(store: Store, codec: C) -> Self {
+        TypedStore {
+            store,
+            codec,
+            _marker: PhantomData,
+        }
+    }
+
+    /**
+    Get the underlying store.
+    */
+    pub fn store(&self) -> &Store {
+        &self.store
+    }
+
+    pub fn into_inner(self) -> Store {
+        self.store
+    }
+
+    pub fn read_begin(&self) -> Result<TypedReader<T, C>, Error> {
+        Ok(TypedReader {
+            reader: self.store.read_begin()?,
+            codec: self.codec.clone(),
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn write_begin(&self) -> Result<TypedWriter<T, C>, Error> {
+        Ok(TypedWriter {
+            writer: self.store.write_begin()?,
+            codec: self.codec.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/**
+A reader that decodes payloads into values of type `T`.
+*/
+pub struct TypedReader<T, C = Json> {
+    reader: Reader,
+    codec: C,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, C> TypedReader<T, C>
+where
+    T: DeserializeOwned,
+    C: Codec,
+{
+    /**
+    Wrap a reader, like one returned by `Store::index_query`.
+    */
+    pub fn new(reader: Reader, codec: C) -> Self {
+        TypedReader {
+            reader,
+            codec,
+            _marker: PhantomData,
+        }
+    }
+
+    /**
+    Read and decode the next record.
+
+    Returns `None` when there are no more records to read.
+    */
+    pub fn read_next(&mut self) -> Result<Option<Data<T>>, Error> {
+        if !self.reader.move_next()? {
+            return Ok(None);
+        }
+
+        let codec = &self.codec;
+        let data = self
+            .reader
+            .with_current(|mut current| -> Result<_, Error> {
+                Ok(Data {
+                    key: current.key,
+                    payload: codec.decode(&mut current.payload)?,
+                })
+            })
+            .transpose()?;
+
+        Ok(data)
+    }
+
+    pub fn complete(&mut self) -> Result<(), Error> {
+        self.reader.complete()
+    }
+}
+
+/**
+A writer that encodes values of type `T` into payloads.
+*/
+pub struct TypedWriter<T, C = Json> {
+    writer: Writer,
+    codec: C,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T, C> TypedWriter<T, C>
+where
+    T: Serialize,
+    C: Codec,
+{
+    pub fn set(&mut self, key: Key, value: &T) -> Result<(), Error> {
+        let payload = self.codec.encode(value)?;
+
+        self.writer.set(Data { key, payload })
+    }
+
+    pub fn complete(&mut self) -> Result<(), Error> {
+        self.writer.complete()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    use crate::{
+        codec::{
+            Cbor,
+            MessagePack,
+        },
+        store::test_util::{
+            key,
+            TempDir,
+        },
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        status: String,
+    }
+
+    fn roundtrip(codec: impl Codec + Clone) {
+        let dir = TempDir::new();
+        let store = TypedStore::<Doc, _>::new(Store::open(dir.path()).unwrap(), codec);
+
+        let mut writer = store.write_begin().unwrap();
+        writer
+            .set(
+                key(b"a"),
+                &Doc {
+                    status: "open".to_owned(),
+                },
+            )
+            .unwrap();
+        writer.complete().unwrap();
+
+        let mut reader = store.read_begin().unwrap();
+
+        let data = reader.read_next().unwrap().unwrap();
+        assert_eq!(key(b"a").to_bytes(), data.key.to_bytes());
+        assert_eq!("open", data.payload.status);
+
+        assert!(reader.read_next().unwrap().is_none());
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        roundtrip(Json);
+    }
+
+    #[test]
+    fn cbor_roundtrip() {
+        roundtrip(Cbor);
+    }
+
+    #[test]
+    fn message_pack_roundtrip() {
+        roundtrip(MessagePack);
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use failure_derive::*;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+use crate::error::{
+    Error,
+    ErrorKind,
+};
+
+/**
+A payload that was rejected before it was written.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Fail)]
+pub enum ValidationError {
+    #[fail(display = "the payload isn't valid JSON: {}", msg)]
+    NotJson { msg: String },
+    #[fail(
+        display = "the payload doesn't match the schema at `{}`: {}",
+        path, msg
+    )]
+    Schema { path: String, msg: String },
+}
+
+/**
+The checks applied to payloads before they're written.
+
+Payloads are always checked to be well-formed JSON.
+They can also be checked against a JSON Schema.
+*/
+#[derive(Debug, Clone)]
+pub struct Validation {
+    schema: Option<Arc<JSONSchema>>,
+}
+
+impl Validation {
+    /**
+    Only check that payloads are well-formed JSON.
+    */
+    pub fn json() -> Self {
+        Validation { schema: None }
+    }
+
+    /**
+    Check that payloads match a JSON Schema.
+    */
+    pub fn schema(schema: &[u8]) -> Result<Self, Error> {
+        let schema: Value = serde_json::from_slice(schema)
+            .map_err(|e| Error::fail(e).with_kind(ErrorKind::InvalidSchema))?;
+        let schema = JSONSchema::compile(&schema).map_err(|e| {
+            Error::msg(format!("the schema is invalid: {}", e)).with_kind(ErrorKind::InvalidSchema)
+        })?;
+
+        Ok(Validation {
+            schema: Some(Arc::new(schema)),
+        })
+    }
+
+    /**
+    Check a payload, returning it parsed as JSON if it's valid.
+    */
+    pub(super) fn validate(&self, payload: &[u8]) -> Result<Value, ValidationError> {
+        let payload: Value = serde_json::from_slice(payload)
+            .map_err(|e| ValidationError::NotJson { msg: e.to_string() })?;
+
+        if let Some(ref schema) = self.schema {
+            if let Err(mut errors) = schema.validate(&payload) {
+                // Only the first error is reported
+                if let Some(err) = errors.next() {
+                    return Err(ValidationError::Schema {
+                        path: err.instance_path.to_string(),
+                        msg: err.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        data::{
+            Data,
+            Key,
+        },
+        store::{
+            test_util::TempStore,
+            Options,
+        },
+    };
+
+    const SCHEMA: &[u8] = br#"{
+        "type": "object",
+        "properties": {
+            "status": { "enum": ["open", "closed"] }
+        },
+        "required": ["status"]
+    }"#;
+
+    #[test]
+    fn validate_json() {
+        let validation = Validation::json();
+
+        assert!(validation.validate(br#"{"a":1}"#).is_ok());
+        assert_match!(Err(ValidationError::NotJson { .. }) = validation.validate(b"{"));
+    }
+
+    #[test]
+    fn validate_schema() {
+        let validation = Validation::schema(SCHEMA).unwrap();
+
+        assert!(validation.validate(br#"{"status":"open"}"#).is_ok());
+        assert_match!(Err(ValidationError::Schema { .. }) = validation.validate(br#"{}"#));
+        assert_match!(Err(ValidationError::Schema { ref path, .. }) = validation.validate(br#"{"status":"other"}"#) => {
+            assert_eq!("/status", path);
+        });
+    }
+
+    #[test]
+    fn invalid_schema() {
+        assert_match!(Err(ref err) = Validation::schema(b"{") => {
+            assert_eq!(ErrorKind::InvalidSchema, err.kind());
+        });
+        assert!(Validation::schema(br#"{"type":42}"#).is_err());
+    }
+
+    #[test]
+    fn write_invalid_payload() {
+        let store = TempStore::with_options(Options {
+            validation: Some(Validation::schema(SCHEMA).unwrap()),
+            ..Default::default()
+        });
+
+        let mut writer = store.write_begin().unwrap();
+        let err = writer
+            .set(Data {
+                key: Key::from_slice(b"a").unwrap(),
+                payload: b"{}".to_vec(),
+            })
+            .unwrap_err();
+
+        assert!(err.is_validation_failure());
+        assert_eq!(
+            Key::from_slice(b"a").unwrap().to_bytes(),
+            err.key().unwrap().to_bytes()
+        );
+        assert_eq!(0, store.db.len());
+    }
+}
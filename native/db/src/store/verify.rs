@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use crate::{
+    data::Key,
+    error::{
+        Error,
+        ErrorKind,
+    },
+    store::{
+        encryption::DecryptError,
+        record::{
+            Corruption,
+            DecodeError,
+        },
+        Store,
+    },
+};
+
+/**
+The outcome of walking every record in a store.
+*/
+#[derive(Debug, Default)]
+pub struct Verification {
+    /**
+    The number of records that were visited.
+    */
+    pub checked: usize,
+    /**
+    The number of records that were readable.
+    */
+    pub readable: usize,
+    /**
+    The problems found with individual records.
+    */
+    pub problems: Vec<Problem>,
+}
+
+impl Verification {
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/**
+A problem found with a record in a store.
+*/
+#[derive(Debug)]
+pub struct Problem {
+    /**
+    The raw key of the record.
+
+    If the store couldn't be read far enough to find a key then this will be `None`.
+    */
+    pub key: Option<Vec<u8>>,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProblemKind {
+    /**
+    The key couldn't be decoded by `Key::from_vec`.
+    */
+    InvalidKey { len: usize },
+    /**
+    The record's payload is damaged.
+    */
+    Corrupted(Corruption),
+    /**
+    The record's payload couldn't be decrypted with the store's keys.
+    */
+    Undecryptable(DecryptError),
+    /**
+    The store couldn't be read any further.
+
+    Records after this point haven't been visited.
+    */
+    Unreadable { msg: String },
+}
+
+pub(super) fn verify(store: &Store) -> Result<Verification, Error> {
+    walk(store, |_, _| Ok(()))
+}
+
+pub(super) fn repair(store: &Store, path: &Path) -> Result<Verification, Error> {
+    if path.exists() {
+        return Err(Error::msg(format!(
+            "can't repair into `{}` because it already exists",
+            path.display()
+        ))
+        .with_kind(ErrorKind::InvalidArgument));
+    }
+
+    let mut repaired = Store::open(path)?;
+
+    // Records are copied as they're stored so their keys and envelopes are preserved
+    let verification = walk(store, |key, record| {
+        repaired.db.set(key, record.to_vec()).map_err(Error::fail)?;
+
+        Ok(())
+    })?;
+
+    repaired.close()?;
+
+    Ok(verification)
+}
+
+fn walk(
+    store: &Store,
+    mut f: impl FnMut(&[u8], &[u8]) -> Result<(), Error>,
+) -> Result<Verification, Error> {
+    let mut verification = Verification::default();
+
+    for kv in store.db.iter() {
+        let (k, v) = match kv {
+            Ok(kv) => kv,
+            // If the iterator fails then we can't trust it to make progress
+            Err(e) => {
+                verification.problems.push(Problem {
+                    key: None,
+                    kind: ProblemKind::Unreadable { msg: e.to_string() },
+                });
+
+                break;
+            }
+        };
+
+        verification.checked += 1;
+
+        let key = match Key::from_slice(&k) {
+            Ok(key) => key,
+            Err(_) => {
+                verification.problems.push(Problem {
+                    kind: ProblemKind::InvalidKey { len: k.len() },
+                    key: Some(k),
+                });
+
+                continue;
+            }
+        };
+
+        if let Err(err) = store.format.decode(&key, v.clone()) {
+            let kind = match err {
+                DecodeError::Corrupted(err) => ProblemKind::Corrupted(err),
+                DecodeError::Decrypt(err) => ProblemKind::Undecryptable(err),
+            };
+
+            verification.problems.push(Problem { key: Some(k), kind });
+
+            continue;
+        }
+
+        f(&k, &v)?;
+
+        verification.readable += 1;
+    }
+
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::store::{
+        test_util::{
+            key,
+            write,
+            TempStore,
+        },
+        Options,
+    };
+
+    #[test]
+    fn verify_intact_store() {
+        let store = TempStore::new();
+
+        write(&store, &[(b"a", "{}")]);
+
+        let verification = store.verify().unwrap();
+
+        assert!(verification.is_intact());
+        assert_eq!(1, verification.checked);
+        assert_eq!(1, verification.readable);
+    }
+
+    #[test]
+    fn verify_invalid_key() {
+        let store = TempStore::new();
+
+        store.db.set(vec![1; 20], b"{}".to_vec()).unwrap();
+
+        let verification = store.verify().unwrap();
+
+        assert_eq!(1, verification.checked);
+        assert_eq!(0, verification.readable);
+        assert_eq!(
+            ProblemKind::InvalidKey { len: 20 },
+            verification.problems[0].kind
+        );
+    }
+
+    #[test]
+    fn verify_corrupted_checksum() {
+        let store = TempStore::with_options(Options {
+            checksums: true,
+            ..Default::default()
+        });
+
+        write(&store, &[(b"a", "{}")]);
+
+        // Flip the last byte of the stored payload
+        let mut record = store.db.get(key(b"a")).unwrap().unwrap().to_vec();
+        *record.last_mut().unwrap() ^= 0xff;
+        store.db.set(key(b"a"), record).unwrap();
+
+        let verification = store.verify().unwrap();
+
+        assert_eq!(0, verification.readable);
+        assert_match!(
+            ProblemKind::Corrupted(Corruption::ChecksumMismatch { .. }) =
+                verification.problems[0].kind
+        );
+    }
+
+    #[test]
+    fn repair_skips_invalid_records() {
+        let store = TempStore::new();
+
+        store.db.set(vec![1; 20], b"{}".to_vec()).unwrap();
+        store.db.set(vec![2; 4], b"{}".to_vec()).unwrap();
+
+        let path = store.path().with_extension("repaired");
+        let verification = store.repair(&path).unwrap();
+
+        assert_eq!(1, verification.readable);
+
+        let repaired = TempStore::open(path, Options::default());
+        assert!(repaired.verify().unwrap().is_intact());
+        assert_eq!(1, repaired.db.len());
+        assert!(repaired.db.get(vec![2; 4]).unwrap().is_some());
+    }
+}
@@ -1,40 +1,107 @@
-use std::panic::{
-    RefUnwindSafe,
-    UnwindSafe,
+use std::{
+    panic::{
+        RefUnwindSafe,
+        UnwindSafe,
+    },
+    sync::Arc,
 };
 
 use crate::{
-    data::Data,
+    data::{
+        Data,
+        Key,
+    },
     error::Error,
     store::{
+        durability::{
+            Durability,
+            Flusher,
+        },
+        index::Indexes,
+        metrics::Recorder,
+        record::Format,
+        validate::Validation,
         Db,
         Store,
+        WriteOptions,
     },
 };
 
 pub struct Writer {
     db: Db,
+    format: Arc<Format>,
+    indexes: Arc<Indexes>,
+    validation: Option<Arc<Validation>>,
+    flusher: Arc<Flusher>,
+    durability: Durability,
+    metrics: Arc<Recorder>,
 }
 
 impl Writer {
-    pub(super) fn begin(store: &Store) -> Self {
+    pub(super) fn begin(store: &Store, options: WriteOptions) -> Self {
         let db = store.db.clone();
+        let format = store.format.clone();
+        let indexes = store.indexes.clone();
+        let flusher = store.flusher.clone();
+        let validation = store.validation.clone();
+        let metrics = store.metrics.clone();
 
-        Writer { db }
+        Writer {
+            db,
+            format,
+            indexes,
+            validation,
+            flusher,
+            durability: options.durability,
+            metrics,
+        }
     }
 
     pub fn set(&mut self, data: Data<impl Into<Vec<u8>>>) -> Result<(), Error> {
-        self.db
-            .set(data.key, data.payload.into())
-            .map_err(Error::fail)?;
+        let key = data.key;
+        let payload = data.payload.into();
 
-        Ok(())
+        let set = self.set_record(key, payload);
+        self.metrics.track(set)
     }
 
     pub fn complete(&mut self) -> Result<(), Error> {
-        self.db
-            .flush()
-            .map_err(|_| Error::msg("failed to flush database"))?;
+        let completed = match self.durability {
+            Durability::FlushOnComplete => self.flusher.flush(),
+            Durability::GroupCommit(window) => self.flusher.flush_within(window),
+            Durability::None | Durability::FlushEveryWrite => Ok(()),
+        };
+
+        self.metrics.track(completed)
+    }
+
+    fn set_record(&mut self, key: Key, payload: Vec<u8>) -> Result<(), Error> {
+        let len = payload.len();
+
+        // Validated payloads have already been parsed, so they aren't parsed again for indexes
+        let values = match self.validation {
+            Some(ref validation) => {
+                let doc = validation
+                    .validate(&payload)
+                    .map_err(|e| Error::fail(e).with_key(key))?;
+
+                self.indexes.values_of(&doc)
+            }
+            None => self.indexes.values(&payload),
+        };
+
+        let record = self.format.encode(&key, payload)?;
+
+        let lock = self.indexes.lock(key);
+        let previous = self.db.set(key, record).map_err(Error::fail)?;
+        self.indexes.set(&self.format, key, previous, values)?;
+        drop(lock);
+
+        self.metrics.write(len);
+
+        if self.durability == Durability::FlushEveryWrite {
+            self.flusher.flush()?;
+        }
 
         Ok(())
     }
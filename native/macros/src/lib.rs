@@ -0,0 +1,227 @@
+/*!
+Attributes for exporting functions from `dbc`.
+
+Exported functions are also read by `dbc-bindgen` to generate bindings,
+so the attribute arguments here describe how a function should be called as well as how it's built.
+*/
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input,
+    AttributeArgs,
+    FnArg,
+    Ident,
+    ItemFn,
+    Meta,
+    NestedMeta,
+    Pat,
+    ReturnType,
+    Type,
+};
+
+/**
+Export a function across the FFI boundary.
+
+The function must return a `DbResult`. Every argument is checked with `IsNull::is_null`
+before the body runs, and early returns are supported through `?`. Unless the function is
+marked `#[ffi(no_catch)]`, the body is run inside `DbResult::catch`, so panics are caught
+and the last result is set, and a span is recorded for the call if tracing is enabled.
+Functions marked `#[ffi(no_trace)]` are still run inside `DbResult::catch`, but never record a span.
+
+Arguments that are handles freed by the function are listed in `#[ffi(releases(handle))]`.
+The attribute doesn't change the function itself, but bindings pass those handles as raw pointers.
+
+Arguments that are the lengths of buffers are listed in `#[ffi(lengths(buf_len))]`.
+Only those arguments contribute to the bytes recorded in a span. Other scalar arguments,
+like a capacity or an index, are never counted.
+
+The function doesn't support generics or argument patterns that are more complex than simple identifiers.
+*/
+#[proc_macro_attribute]
+pub fn ffi(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let item = parse_macro_input!(item as ItemFn);
+
+    match expand(args, item) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct Options {
+    catch: bool,
+    trace: bool,
+    lengths: Vec<Ident>,
+}
+
+fn options(args: AttributeArgs) -> syn::Result<Options> {
+    let mut options = Options {
+        catch: true,
+        trace: true,
+        lengths: Vec::new(),
+    };
+
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("no_catch") => {
+                options.catch = false;
+            }
+            NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("no_trace") => {
+                options.trace = false;
+            }
+            NestedMeta::Meta(Meta::List(ref list)) if list.path.is_ident("releases") => {
+                for handle in list.nested.iter() {
+                    match handle {
+                        NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => (),
+                        other => {
+                            return Err(syn::Error::new_spanned(
+                                other,
+                                "expected the name of a handle argument",
+                            ))
+                        }
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::List(ref list)) if list.path.is_ident("lengths") => {
+                for len in list.nested.iter() {
+                    match len {
+                        NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => {
+                            options.lengths.extend(path.get_ident().cloned());
+                        }
+                        other => {
+                            return Err(syn::Error::new_spanned(
+                                other,
+                                "expected the name of a buffer length argument",
+                            ))
+                        }
+                    }
+                }
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected `no_catch`, `no_trace`, `releases(..)`, or `lengths(..)`",
+                ))
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+fn expand(args: AttributeArgs, item: ItemFn) -> syn::Result<TokenStream2> {
+    let options = options(args)?;
+
+    let ItemFn {
+        attrs, sig, block, ..
+    } = item;
+
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "exported functions can't be generic",
+        ));
+    }
+
+    match sig.output {
+        ReturnType::Type(_, ref ty) if is_db_result(ty) => (),
+        ref output => {
+            return Err(syn::Error::new_spanned(
+                output,
+                "exported functions must return a `DbResult`",
+            ))
+        }
+    }
+
+    let mut arg_idents: Vec<Ident> = Vec::new();
+    let mut arg_tys: Vec<Type> = Vec::new();
+    for input in sig.inputs.iter() {
+        match input {
+            FnArg::Typed(arg) => match *arg.pat {
+                Pat::Ident(ref pat) => {
+                    arg_idents.push(pat.ident.clone());
+                    arg_tys.push((*arg.ty).clone());
+                }
+                ref pat => {
+                    return Err(syn::Error::new_spanned(
+                        pat,
+                        "arguments must be simple identifiers",
+                    ))
+                }
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "exported functions can't take `self`",
+                ))
+            }
+        }
+    }
+
+    if let Some(len) = options.lengths.iter().find(|len| !arg_idents.contains(len)) {
+        return Err(syn::Error::new_spanned(
+            len,
+            "buffer lengths must be arguments of the function",
+        ));
+    }
+
+    let name = &sig.ident;
+    let lengths = &options.lengths;
+
+    let invoke = if options.catch && options.trace {
+        quote! {
+            let span = crate::trace::start(
+                stringify!(#name),
+                0 #(+ crate::trace::ArgSize::arg_size(&#lengths))*
+            );
+
+            let result = DbResult::catch(move || call( #(#arg_idents),* ));
+
+            if let Some(span) = span {
+                span.end(result);
+            }
+
+            result
+        }
+    } else if options.catch {
+        quote! {
+            DbResult::catch(move || call( #(#arg_idents),* ))
+        }
+    } else {
+        quote! {
+            call( #(#arg_idents),* )
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #[allow(unsafe_code, unused_attributes)]
+        #[no_mangle]
+        pub unsafe extern "C" fn #name( #(#arg_idents: #arg_tys),* ) -> DbResult {
+            #[allow(unused_mut)]
+            #[deny(unsafe_code)]
+            fn call( #(mut #arg_idents: #arg_tys),* ) -> DbResult {
+                #(
+                    if crate::is_null::IsNull::is_null(&#arg_idents) {
+                        return DbResult::argument_null().context(crate::is_null::Error { arg: stringify!(#arg_idents) });
+                    }
+                )*
+
+                #block
+            }
+
+            #invoke
+        }
+    })
+}
+
+fn is_db_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.qself.is_none() && path.path.is_ident("DbResult"),
+        _ => false,
+    }
+}
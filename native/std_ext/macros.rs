@@ -14,13 +14,14 @@ macro_rules! unsafe_block {
 /**
 Allow an `unsafe` function with a reason.
 
-The macro will expand to an `unsafe fn`.
+The macro will expand to an `unsafe fn`, with the reason as its `# Safety` section.
 */
 macro_rules! unsafe_fn {
     ($reason: tt => fn $name:ident $($body:tt)*) => {
         unsafe_fn!($reason => pub(self) fn $name $($body)*);
     };
     ($reason: tt => $publicity:vis fn $name:ident $($body:tt)*) => {
+        #[doc = concat!("# Safety\n\n", $reason, ".")]
         #[allow(unsafe_code)]
         $publicity unsafe fn $name $($body)*
     };
@@ -19,9 +19,8 @@ impl<T> OptionMutExt<T> for Option<T> {
     where
         F: FnOnce(&mut T),
     {
-        match *self {
-            Some(ref mut t) => f(t),
-            None => (),
+        if let Some(ref mut t) = *self {
+            f(t)
         }
 
         self